@@ -1,14 +1,79 @@
-use crate::crypto::{decrypt, encrypt, EncryptionMetadata};
-use crate::types::{BackupJob, BackupManifest, BackupMode, BackupStatus, BackupType, FileMetadata};
-use std::collections::HashMap;
+use crate::container::{open_archive, ArchiveFormat, CipherId, CompressionId, ContainerHeader, KdfId, MAGIC};
+use crate::crypto::{
+    decrypt_with_aad, generate_data_key, new_stream_metadata, parse_public_key, recovery_key_slot, rekey_recovery_escrow, rekey_slots,
+    unwrap_data_key,
+    wrap_data_key, wrap_data_key_to_recipient, EncryptionMetadata, RecoveryEscrow, StreamDecryptor, StreamEncryptor,
+};
+use crate::retention;
+use crate::selection::RestoreSelection;
+use crate::types::{
+    ArchiveInfo, ArchiveManifest, BackupConfig, BackupJob, BackupManifest, BackupMode, BackupStatus, BackupType, FileMetadata, KdfStrength, RenamedFile,
+};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Read;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
+use zeroize::Zeroizing;
 use tauri::Emitter;
 
+/// Prefix of keyed (HMAC-SHA256) content hashes in manifests of encrypted configs
+pub const KEYED_CHECKSUM_PREFIX: &str = "hmac-sha256:";
+
+/// Where an archive (first tar entry, or file in a Copy-mode folder) keeps its `ArchiveInfo`
+pub const ARCHIVE_INFO_PATH: &str = ".inlocker/archive.json";
+
+/// Where an archive (last tar entry, or file in a Copy-mode folder) keeps its `ArchiveManifest`
+///
+/// Written last because its checksums are computed while the files are archived.
+pub const ARCHIVE_MANIFEST_PATH: &str = ".inlocker/manifest.json";
+
+/// Whether a path inside an archive is InLocker metadata rather than a backed-up file
+pub(crate) fn is_archive_metadata(path: &Path) -> bool {
+    path == Path::new(ARCHIVE_INFO_PATH) || path == Path::new(ARCHIVE_MANIFEST_PATH)
+}
+
+/// Reader that computes the SHA-256 of everything read through it
+struct HashingReader<R> {
+    inner: R,
+    context: ring::digest::Context,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, context: ring::digest::Context::new(&ring::digest::SHA256) }
+    }
+
+    fn finish(self) -> String {
+        hex::encode(self.context.finish().as_ref())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.context.update(&buf[..count]);
+        Ok(count)
+    }
+}
+
+/// Manifest entry of a file as archived
+fn archived_file(relative_path: &Path, metadata: &fs::Metadata, checksum: String) -> FileMetadata {
+    FileMetadata {
+        path: relative_path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        modified_at: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0),
+        checksum,
+    }
+}
+
 /// Progress event payload
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct BackupProgress {
@@ -23,6 +88,17 @@ pub struct BackupProgress {
     pub compressed_size: Option<u64>, // Compressed size in bytes
 }
 
+/// Additional settings for `compress_folder_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    /// Argon2id cost for new encrypted archives (recorded in the archive header)
+    pub kdf_strength: KdfStrength,
+    /// Recovery key of the config; encrypted archives get a second key slot for it
+    pub recovery: Option<RecoveryEscrow>,
+    /// X25519 public keys (`inlocker-pk-...`); each gets a key slot, no password needed
+    pub recipients: Vec<String>,
+}
+
 /// Backup a folder with support for 3 modes: Copy, Compressed, or Encrypted
 ///
 /// # Modes
@@ -35,7 +111,7 @@ pub struct BackupProgress {
 /// - Encryption metadata (salt, nonce) is embedded in the file
 /// - Password is derived using Argon2id (RFC 9106)
 /// Sanitize config name for use in filename (remove special characters)
-fn sanitize_filename(name: &str) -> String {
+pub(crate) fn sanitize_filename(name: &str) -> String {
     name.chars()
         .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
         .collect::<String>()
@@ -54,6 +130,36 @@ pub fn compress_folder(
     app: Option<&tauri::AppHandle>,
     password: Option<&str>,
     cancel_flag: Option<Arc<AtomicBool>>,
+) -> Result<BackupJob, String> {
+    compress_folder_with_options(
+        config_id,
+        config_name,
+        source_path,
+        dest_path,
+        backup_type,
+        mode,
+        previous_manifest,
+        app,
+        password,
+        cancel_flag,
+        &BackupOptions::default(),
+    )
+}
+
+/// Same as `compress_folder`, with explicit `BackupOptions`
+#[allow(clippy::too_many_arguments)]
+pub fn compress_folder_with_options(
+    config_id: &str,
+    config_name: &str,
+    source_path: &Path,
+    dest_path: &Path,
+    backup_type: &BackupType,
+    mode: &BackupMode,
+    previous_manifest: Option<&BackupManifest>,
+    app: Option<&tauri::AppHandle>,
+    password: Option<&str>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    options: &BackupOptions,
 ) -> Result<BackupJob, String> {
     // Capture actual backend start time
     let started_at = SystemTime::now()
@@ -115,18 +221,16 @@ pub fn compress_folder(
     let total_files_count = all_files.len();
 
     // Determine which files to backup
-    let (files_to_backup, total_size) = match backup_type {
-        BackupType::Full => (all_files, total_source_size),
-        BackupType::Incremental => {
-            if previous_manifest.is_none() {
-                // No previous backup - backup everything
-                (all_files, total_source_size)
-            } else {
-                // Has previous backup - find changed files
-                scan_changed_files(source_path, previous_manifest)?
-            }
-        }
-    };
+    let ChangeSet { changed: files_to_backup, changed_size: total_size, deleted, renamed } =
+        match (backup_type, previous_manifest) {
+            // Has previous backup - find changed, deleted and moved files
+            (BackupType::Incremental, Some(manifest)) => scan_changed_files(source_path, &all_files, manifest)?,
+            // Full backup, or no previous backup - backup everything
+            _ => ChangeSet { changed: all_files, changed_size: total_source_size, deleted: Vec::new(), renamed: Vec::new() },
+        };
+    if !renamed.is_empty() {
+        log::info!("🔀 {} moved files detected (recorded, not archived again)", renamed.len());
+    }
 
     let files_count = files_to_backup.len();
     log::info!("✅ Found {} files to backup ({:.2} MB)", files_count, total_size as f64 / 1_048_576.0);
@@ -166,6 +270,37 @@ pub fn compress_folder(
 
     log::info!("📝 Backup will be saved as: {}", backup_filename);
 
+    // An incremental builds on the newest archive of this config in the destination
+    // (not on a same-named config's archive, which records another id)
+    let incremental = actual_backup_type == "incr";
+    let parent = if incremental {
+        retention::archives_of(dest_path, config_id, config_name)?
+            .last()
+            .map(|archive| archive.archive_id().to_string())
+    } else {
+        None
+    };
+    let archive_info = ArchiveInfo {
+        archive_id: retention::archive_id(&backup_filename).to_string(),
+        config_id: config_id.to_string(),
+        config_name: config_name.to_string(),
+        source_path: source_path.to_string_lossy().to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        backup_type: if incremental { BackupType::Incremental } else { BackupType::Full },
+        parent,
+        created_at: started_at,
+        deleted: if incremental { deleted } else { Vec::new() },
+        renamed: if incremental { renamed } else { Vec::new() },
+    };
+    if let Some(parent) = &archive_info.parent {
+        log::info!(
+            "🔗 Parent archive: {} ({} deleted, {} moved files)",
+            parent,
+            archive_info.deleted.len(),
+            archive_info.renamed.len()
+        );
+    }
+
     // Handle Copy mode separately (direct copy, no TAR, no compression)
     if mode == &BackupMode::Copy {
         log::info!("📋 Copy mode - copying files directly (no TAR, no compression)");
@@ -178,6 +313,7 @@ pub fn compress_folder(
         // Copy each file preserving structure with cleanup on error/cancellation
         let copy_result = (|| -> Result<usize, String> {
             let mut copied_count = 0;
+            let mut archived = Vec::with_capacity(files_count);
             for file_path in &files_to_backup {
                 // Check for cancellation
                 check_cancelled()?;
@@ -192,9 +328,12 @@ pub fn compress_folder(
                         .map_err(|e| format!("Failed to create directory: {}", e))?;
                 }
 
-                // Copy file
+                // Copy file, then hash the copy for the embedded manifest
                 fs::copy(file_path, &dest_file)
                     .map_err(|e| format!("Failed to copy file: {}", e))?;
+                let metadata = fs::metadata(file_path)
+                    .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+                archived.push(archived_file(relative_path, &metadata, calculate_checksum(&dest_file)?));
                 copied_count += 1;
 
                 // Emit progress every 10 files
@@ -202,6 +341,17 @@ pub fn compress_folder(
                     emit_progress("copying", "Copying files directly", Some(format!("{} files", copied_count)), Some(copied_count), Some(files_count), Some(total_size), None);
                 }
             }
+
+            let info_path = backup_path.join(ARCHIVE_INFO_PATH);
+            if let Some(parent) = info_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory: {}", e))?;
+            }
+            fs::write(&info_path, metadata_json(&archive_info)?)
+                .map_err(|e| format!("Failed to write archive info: {}", e))?;
+            let manifest = ArchiveManifest { archive_id: archive_info.archive_id.clone(), files: archived };
+            fs::write(backup_path.join(ARCHIVE_MANIFEST_PATH), metadata_json(&manifest)?)
+                .map_err(|e| format!("Failed to write archive manifest: {}", e))?;
             Ok(copied_count)
         })();
 
@@ -262,6 +412,7 @@ pub fn compress_folder(
         let result = create_tar_with_streaming_compression(
             &files_to_backup,
             source_path,
+            &archive_info,
             output_file,
             3, // zstd level
             cancel_flag.clone(),
@@ -299,88 +450,63 @@ pub fn compress_folder(
 
         // CRITICAL: Validate password BEFORE emitting progress events
         // This prevents UI from showing progress when backup will fail immediately
-        let pwd = password.ok_or("Encryption enabled but no password provided")?;
+        // Recipient public keys alone are enough (unattended backups hold no secret)
+        if password.is_none() && options.recipients.is_empty() {
+            return Err("Encryption enabled but no password or recipient key provided".to_string());
+        }
+        for recipient in &options.recipients {
+            parse_public_key(recipient).map_err(|e| format!("Invalid recipient key: {}", e))?;
+        }
 
         emit_progress("compressing", "Compressing and encrypting", Some(format!("{} files", files_count)), Some(0), Some(files_count), Some(total_size), None);
 
-        // Create temporary file for compressed data (before encryption)
-        let temp_compressed = backup_path.with_extension("tmp.zst");
-
-        // Step 1: Stream TAR + zstd to temp file
-        let temp_file = fs::File::create(&temp_compressed)
-            .map_err(|e| {
-                let _ = fs::remove_file(&temp_compressed);
-                format!("Failed to create temp file: {}", e)
-            })?;
-
-        let compressed_size_result = create_tar_with_streaming_compression(
-            &files_to_backup,
-            source_path,
-            temp_file,
-            3, // zstd level
-            cancel_flag.clone(),
-            |current, total| {
-                emit_progress(
-                    "compressing",
-                    "Streaming TAR + zstd",
-                    Some(format!("{} files", current)),
-                    Some(current),
-                    Some(total),
-                    Some(total_size),
-                    None
-                );
-            }
-        );
-
-        let compressed_size = match compressed_size_result {
-            Ok(size) => {
-                log::info!("✅ Compressed to {:.2} MB", size as f64 / 1_048_576.0);
-                size
-            }
-            Err(e) => {
-                // Clean up temp file on error
-                let _ = fs::remove_file(&temp_compressed);
-                return Err(e);
-            }
-        };
-
-        check_cancelled()?;
-
-        // Step 2: Encrypt the compressed data
-        log::info!("🔐 Encrypting with AES-256-GCM...");
-        emit_progress("encrypting", "Encrypting backup", Some(format!("{:.1} MB", compressed_size as f64 / 1_048_576.0)), None, None, Some(total_size), Some(compressed_size));
-
+        // Stream TAR → zstd → chunked AES-256-GCM → file (constant memory, no plaintext temp file)
+        // File format: [container header][key slots][encrypted chunks] (see container.rs)
         let encryption_result = (|| -> Result<u64, String> {
-            // Read compressed data
-            let compressed_data = fs::read(&temp_compressed)
-                .map_err(|e| format!("Failed to read compressed data: {}", e))?;
-
-            // Encrypt
-            let (encrypted, metadata) = encrypt(&compressed_data, pwd)
-                .map_err(|e| format!("Encryption failed: {}", e))?;
-
-            // Embed metadata in file format: [4-byte length][metadata JSON][encrypted data]
-            let metadata_json = serde_json::to_vec(&metadata)
-                .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-
-            let metadata_len = metadata_json.len() as u32;
-            let mut final_data = Vec::with_capacity(4 + metadata_json.len() + encrypted.len());
-            final_data.extend_from_slice(&metadata_len.to_le_bytes());
-            final_data.extend_from_slice(&metadata_json);
-            final_data.extend_from_slice(&encrypted);
-
-            log::info!("✅ Backup encrypted ({:.2} MB)", final_data.len() as f64 / 1_048_576.0);
-
-            // Write encrypted data to final file
-            fs::write(&backup_path, &final_data)
-                .map_err(|e| format!("Failed to write encrypted backup: {}", e))?;
-
-            Ok(final_data.len() as u64)
+            let output_file = fs::File::create(&backup_path)
+                .map_err(|e| format!("Failed to create backup file: {}", e))?;
+            let encryptor = start_encrypted_container(
+                BufWriter::new(output_file),
+                config_id,
+                incremental,
+                started_at,
+                password,
+                options,
+            )?;
+
+            let encryptor = write_tar_zstd(
+                &files_to_backup,
+                source_path,
+                &archive_info,
+                encryptor,
+                3, // zstd level
+                cancel_flag.clone(),
+                |current, total| {
+                    emit_progress(
+                        "compressing",
+                        "Compressing and encrypting",
+                        Some(format!("{} files", current)),
+                        Some(current),
+                        Some(total),
+                        Some(total_size),
+                        None
+                    );
+                }
+            )?;
+
+            let output_file = encryptor.finish()?
+                .into_inner()
+                .map_err(|e| format!("Failed to flush encrypted backup: {}", e))?;
+            output_file.sync_all()
+                .map_err(|e| format!("Failed to sync file to disk: {}", e))?;
+
+            let size = output_file.metadata()
+                .map_err(|e| format!("Failed to get file metadata: {}", e))?
+                .len();
+            log::info!("✅ Backup compressed and encrypted ({:.2} MB)", size as f64 / 1_048_576.0);
+            Ok(size)
         })();
 
-        // Clean up temp file regardless of success/failure
-        let _ = fs::remove_file(&temp_compressed);
-
         match encryption_result {
             Ok(size) => size,
             Err(e) => {
@@ -435,6 +561,53 @@ pub fn compress_folder(
     })
 }
 
+/// Write an encrypted container header and return the payload encryptor
+///
+/// The payload key is random; the password, the recovery key and every
+/// recipient only wrap it in key slots (so they can be changed later).
+/// File format: [container header][key slots][encrypted chunks] (see container.rs)
+fn start_encrypted_container<W: Write>(
+    mut writer: W,
+    config_id: &str,
+    incremental: bool,
+    created_at: i64,
+    password: Option<&str>,
+    options: &BackupOptions,
+) -> Result<StreamEncryptor<W>, String> {
+    let metadata = EncryptionMetadata {
+        salt: String::new(),
+        ..new_stream_metadata()
+    };
+    let mut header = ContainerHeader::encrypted(metadata.clone(), incremental, config_id, created_at);
+    let header_bytes = header.to_bytes()?;
+
+    let data_key = generate_data_key();
+    if let Some(pwd) = password {
+        let slot = wrap_data_key(&data_key, pwd, options.kdf_strength, &header_bytes)?;
+        log::info!("🔑 Key derivation: Argon2id {} MB, {} iterations", slot.kdf.memory_cost / 1024, slot.kdf.iterations);
+        header.key_slots.push(slot);
+        if let Some(recovery) = &options.recovery {
+            header.key_slots.push(recovery_key_slot(&data_key, recovery, pwd, config_id.as_bytes(), &header_bytes)?);
+            log::info!("🛟 Recovery key slot added");
+        }
+    }
+    for recipient in &options.recipients {
+        header.key_slots.push(wrap_data_key_to_recipient(&data_key, recipient, &header_bytes)?);
+    }
+    if !options.recipients.is_empty() {
+        log::info!("📬 Data key wrapped to {} recipient key(s)", options.recipients.len());
+    }
+
+    writer.write_all(&header_bytes)
+        .map_err(|e| format!("Failed to write archive header: {}", e))?;
+    writer.write_all(&header.key_slot_bytes()?)
+        .map_err(|e| format!("Failed to write archive header: {}", e))?;
+
+    // The header is authenticated with every chunk (tampering fails decryption)
+    StreamEncryptor::with_data_key(writer, &data_key, &metadata, &header_bytes)
+        .map_err(|e| format!("Encryption failed: {}", e))
+}
+
 /// Verify that physical backup files actually exist on disk
 /// This prevents using stale manifests when backup files were deleted
 pub fn verify_physical_backup_exists(
@@ -593,129 +766,177 @@ pub fn scan_all_files(source_path: &Path) -> Result<(Vec<PathBuf>, u64), String>
     Ok((files, total_size))
 }
 
-/// Scan only changed files for incremental backup
+/// Changes in the source since the previous manifest
+struct ChangeSet {
+    /// New and modified files (archived)
+    changed: Vec<PathBuf>,
+    changed_size: u64,
+    /// Paths of the previous backup that no longer exist (tombstones)
+    deleted: Vec<String>,
+    /// Files moved without content changes (recorded, not archived again)
+    renamed: Vec<RenamedFile>,
+}
+
+/// Diff the current source files against the previous manifest
+///
+/// A new path whose content matches a path that disappeared (same size and
+/// checksum) is a rename. Content is only hashed for new files whose size
+/// matches a disappeared file, and never compared with fallback checksums.
 fn scan_changed_files(
     source_path: &Path,
-    previous_manifest: Option<&BackupManifest>,
-) -> Result<(Vec<PathBuf>, u64), String> {
-    let (all_files, _) = scan_all_files(source_path)?;
-
-    let mut changed_files = Vec::new();
-    let mut total_size = 0u64;
+    all_files: &[PathBuf],
+    manifest: &BackupManifest,
+) -> Result<ChangeSet, String> {
+    let mut changed = Vec::new();
+    let mut changed_size = 0u64;
+    let mut new_files = Vec::new();
+    let mut current = HashSet::new();
 
     for file_path in all_files {
-        let should_backup = if let Some(manifest) = previous_manifest {
-            // Check if file has changed
-            let relative_path = file_path
-                .strip_prefix(source_path)
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
-
-            if let Some(prev_metadata) = manifest.files.get(&relative_path) {
-                // File exists in previous backup - check if modified
-                if let Ok(metadata) = fs::metadata(&file_path) {
-                    let modified_at = metadata
-                        .modified()
-                        .unwrap()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs() as i64;
-
-                    // Changed if modified time is different or size changed
-                    modified_at != prev_metadata.modified_at || metadata.len() != prev_metadata.size
-                } else {
-                    false
+        let relative_path = file_path
+            .strip_prefix(source_path)
+            .map_err(|e| format!("Failed to get relative path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+        let Ok(metadata) = fs::metadata(file_path) else {
+            continue;
+        };
+        current.insert(relative_path.clone());
+
+        match manifest.files.get(&relative_path) {
+            Some(prev_metadata) => {
+                let modified_at = metadata
+                    .modified()
+                    .unwrap()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs() as i64;
+
+                // Changed if modified time is different or size changed
+                if modified_at != prev_metadata.modified_at || metadata.len() != prev_metadata.size {
+                    changed_size += metadata.len();
+                    changed.push(file_path.clone());
                 }
-            } else {
-                // New file - include it
-                true
             }
+            None => new_files.push((file_path, relative_path, metadata.len())),
+        }
+    }
+
+    // Disappeared files, by (size, checksum): possible sources of a rename
+    let mut gone: Vec<&String> = manifest.files.keys().filter(|path| !current.contains(*path)).collect();
+    gone.sort();
+    let mut sources: HashMap<(u64, String), Vec<&String>> = HashMap::new();
+    for path in gone.iter().rev() {
+        let meta = &manifest.files[*path];
+        if !meta.checksum.starts_with("fallback:") {
+            sources.entry((meta.size, meta.checksum.clone())).or_default().push(path);
+        }
+    }
+    let source_sizes: HashSet<u64> = sources.keys().map(|(size, _)| *size).collect();
+    let hash_key = manifest
+        .hash_key
+        .as_ref()
+        .and_then(|key| hex::decode(key).ok())
+        .map(|key| ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &key));
+
+    let mut renamed = Vec::new();
+    let mut moved_from = HashSet::new();
+    for (file_path, relative_path, size) in new_files {
+        let from = if source_sizes.contains(&size) {
+            calculate_file_checksum(file_path, hash_key.as_ref())
+                .ok()
+                .and_then(|checksum| sources.get_mut(&(size, checksum))?.pop())
         } else {
-            // No previous manifest - backup everything
-            true
+            None
         };
-
-        if should_backup {
-            if let Ok(metadata) = fs::metadata(&file_path) {
-                total_size += metadata.len();
-                changed_files.push(file_path);
+        match from {
+            Some(from) => {
+                moved_from.insert(from.as_str());
+                renamed.push(RenamedFile { from: from.clone(), to: relative_path });
+            }
+            None => {
+                changed_size += size;
+                changed.push(file_path.clone());
             }
         }
     }
 
-    Ok((changed_files, total_size))
+    let deleted = gone
+        .into_iter()
+        .filter(|path| !moved_from.contains(path.as_str()))
+        .cloned()
+        .collect();
+    renamed.sort_by(|a, b| a.to.cmp(&b.to));
+
+    Ok(ChangeSet { changed, changed_size, deleted, renamed })
 }
 
-/// Create a tar archive from file list (in-memory, for encrypted mode)
-fn create_tar_archive<F>(
+/// Create TAR archive with streaming zstd compression directly to file
+/// This avoids loading the entire archive into memory
+fn create_tar_with_streaming_compression<F>(
     files: &[PathBuf],
     base_path: &Path,
+    archive_info: &ArchiveInfo,
+    output_file: fs::File,
+    compression_level: i32,
     cancel_flag: Option<Arc<AtomicBool>>,
-    mut progress_callback: F,
-) -> Result<Vec<u8>, String>
+    progress_callback: F,
+) -> Result<u64, String>
 where
     F: FnMut(usize, usize),
 {
-    let mut tar_data = Vec::new();
-    let total_files = files.len();
-    {
-        let mut tar = tar::Builder::new(&mut tar_data);
-
-        for (index, file_path) in files.iter().enumerate() {
-            // Check for cancellation every 10 files
-            if index % 10 == 0 {
-                if let Some(ref flag) = cancel_flag {
-                    if flag.load(Ordering::SeqCst) {
-                        log::warn!("🚫 TAR creation cancelled by user");
-                        return Err("Backup cancelled by user".to_string());
-                    }
-                }
-            }
-
-            let relative_path = file_path
-                .strip_prefix(base_path)
-                .map_err(|e| format!("Failed to get relative path: {}", e))?;
+    let output_file = write_tar_zstd(
+        files,
+        base_path,
+        archive_info,
+        output_file,
+        compression_level,
+        cancel_flag,
+        progress_callback,
+    )?;
 
-            tar.append_path_with_name(file_path, relative_path)
-                .map_err(|e| format!("Failed to add file to tar: {}", e))?;
+    // Sync to disk
+    output_file.sync_all()
+        .map_err(|e| format!("Failed to sync file to disk: {}", e))?;
 
-            // Emit progress every 100 files or on last file
-            if (index + 1) % 100 == 0 || index + 1 == total_files {
-                progress_callback(index + 1, total_files);
-            }
-        }
+    // Get final compressed size
+    let metadata = output_file.metadata()
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
 
-        tar.finish()
-            .map_err(|e| format!("Failed to finalize tar: {}", e))?;
-    }
-    Ok(tar_data)
+    Ok(metadata.len())
 }
 
-/// Create TAR archive with streaming zstd compression directly to file
-/// This avoids loading the entire archive into memory
-fn create_tar_with_streaming_compression<F>(
+/// Stream TAR → zstd into any writer and return the writer once the zstd frame is finished
+///
+/// `archive_info` is written as the first entry, so lineage can be read without
+/// decompressing the whole archive.
+fn write_tar_zstd<W, F>(
     files: &[PathBuf],
     base_path: &Path,
-    output_file: fs::File,
+    archive_info: &ArchiveInfo,
+    writer: W,
     compression_level: i32,
     cancel_flag: Option<Arc<AtomicBool>>,
     mut progress_callback: F,
-) -> Result<u64, String>
+) -> Result<W, String>
 where
+    W: Write,
     F: FnMut(usize, usize),
 {
     let total_files = files.len();
 
-    // Create zstd encoder that writes directly to file
-    // This streams: TAR → zstd → file (no intermediate buffers)
-    let mut encoder = zstd::stream::write::Encoder::new(output_file, compression_level)
+    // Create zstd encoder that writes directly to the output
+    // This streams: TAR → zstd → writer (no intermediate buffers)
+    let mut encoder = zstd::stream::write::Encoder::new(writer, compression_level)
         .map_err(|e| format!("Failed to create zstd encoder: {}", e))?;
 
     // Create TAR builder that writes to the encoder
     {
         let mut tar = tar::Builder::new(&mut encoder);
 
+        append_metadata(&mut tar, ARCHIVE_INFO_PATH, &metadata_json(archive_info)?, archive_info.created_at)?;
+
+        let mut archived = Vec::with_capacity(total_files);
         for (index, file_path) in files.iter().enumerate() {
             // Check for cancellation every 10 files
             if index % 10 == 0 {
@@ -731,8 +952,17 @@ where
                 .strip_prefix(base_path)
                 .map_err(|e| format!("Failed to get relative path: {}", e))?;
 
-            tar.append_path_with_name(file_path, relative_path)
+            // Hash while archiving (one read per file); never store more than the header announces
+            let file = fs::File::open(file_path)
+                .map_err(|e| format!("Failed to add file to streaming tar: {}", e))?;
+            let metadata = file.metadata()
                 .map_err(|e| format!("Failed to add file to streaming tar: {}", e))?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&metadata);
+            let mut reader = HashingReader::new(file.take(metadata.len()));
+            tar.append_data(&mut header, relative_path, &mut reader)
+                .map_err(|e| format!("Failed to add file to streaming tar: {}", e))?;
+            archived.push(archived_file(relative_path, &metadata, reader.finish()));
 
             // Emit progress every 50 files or on last file
             // More frequent updates since we're streaming
@@ -741,27 +971,107 @@ where
             }
         }
 
+        let manifest = ArchiveManifest { archive_id: archive_info.archive_id.clone(), files: archived };
+        append_metadata(&mut tar, ARCHIVE_MANIFEST_PATH, &metadata_json(&manifest)?, archive_info.created_at)?;
+
         // Finish TAR archive (flushes to encoder)
         tar.finish()
             .map_err(|e| format!("Failed to finalize streaming tar: {}", e))?;
     } // tar is dropped here, encoder now has all data
 
-    // Finish compression and get final file handle
-    let output_file = encoder.finish()
-        .map_err(|e| format!("Failed to finish zstd compression: {}", e))?;
+    // Finish compression and hand back the underlying writer
+    encoder.finish()
+        .map_err(|e| format!("Failed to finish zstd compression: {}", e))
+}
 
-    // Sync to disk
-    output_file.sync_all()
-        .map_err(|e| format!("Failed to sync file to disk: {}", e))?;
+fn metadata_json<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(value).map_err(|e| format!("Failed to serialize archive metadata: {}", e))
+}
 
-    // Get final compressed size
-    let metadata = output_file.metadata()
-        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+/// Add an InLocker metadata file (`ARCHIVE_INFO_PATH`, `ARCHIVE_MANIFEST_PATH`) to a tar stream
+fn append_metadata<W: Write>(tar: &mut tar::Builder<W>, path: &str, json: &[u8], created_at: i64) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(created_at.max(0) as u64);
+    header.set_entry_type(tar::EntryType::Regular);
+    tar.append_data(&mut header, path, json)
+        .map_err(|e| format!("Failed to add archive metadata to streaming tar: {}", e))
+}
 
-    Ok(metadata.len())
+/// Read the `ArchiveInfo` of an archive file or Copy-mode folder
+///
+/// Returns None for archives written before lineage was recorded. Encrypted
+/// archives need the password (or another key that opens them).
+pub fn read_archive_info(archive_path: &Path, password: Option<&str>) -> Result<Option<ArchiveInfo>, String> {
+    let json = if archive_path.is_dir() {
+        match fs::read(archive_path.join(ARCHIVE_INFO_PATH)) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read archive info: {}", e)),
+        }
+    } else {
+        let tar_reader = open_backup_payload(archive_path, password, None, None)?;
+        let mut archive = tar::Archive::new(tar_reader);
+        let mut entries = archive.entries().map_err(|e| format!("Failed to read tar entries: {}", e))?;
+        let Some(entry) = entries.next() else {
+            return Ok(None);
+        };
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry.path().map_err(|e| format!("Invalid path in tar: {}", e))?;
+        if path != Path::new(ARCHIVE_INFO_PATH) {
+            return Ok(None);
+        }
+        let mut json = Vec::new();
+        entry.read_to_end(&mut json)
+            .map_err(|e| format!("Failed to read archive info: {}", e))?;
+        json
+    };
+
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse archive info: {}", e))
+}
+
+/// Read the `ArchiveManifest` embedded in an archive or Copy-mode folder
+///
+/// The manifest is the last tar entry, so this reads the whole archive.
+/// Returns `None` for archives written before manifests were embedded.
+pub fn read_archive_manifest(archive_path: &Path, password: Option<&str>) -> Result<Option<ArchiveManifest>, String> {
+    let json = if archive_path.is_dir() {
+        match fs::read(archive_path.join(ARCHIVE_MANIFEST_PATH)) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read archive manifest: {}", e)),
+        }
+    } else {
+        let tar_reader = open_backup_payload(archive_path, password, None, None)?;
+        let mut archive = tar::Archive::new(tar_reader);
+        let entries = archive.entries().map_err(|e| format!("Failed to read tar entries: {}", e))?;
+        let mut manifest = None;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+            let path = entry.path().map_err(|e| format!("Invalid path in tar: {}", e))?;
+            if path == Path::new(ARCHIVE_MANIFEST_PATH) {
+                let mut json = Vec::new();
+                entry.read_to_end(&mut json)
+                    .map_err(|e| format!("Failed to read archive manifest: {}", e))?;
+                manifest = Some(json);
+            }
+        }
+        match manifest {
+            Some(json) => json,
+            None => return Ok(None),
+        }
+    };
+
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse archive manifest: {}", e))
 }
 
 /// Compress data with zstd (level 3 for balanced performance)
+#[cfg(test)]
 fn compress_with_zstd(data: &[u8]) -> Result<Vec<u8>, String> {
     zstd::encode_all(data, 3).map_err(|e| format!("Failed to compress: {}", e))
 }
@@ -794,6 +1104,21 @@ fn calculate_checksum(file_path: &Path) -> Result<String, String> {
 
 /// Build manifest from file list
 pub fn build_manifest(config_id: &str, files: &[PathBuf], base_path: &Path) -> Result<BackupManifest, String> {
+    build_manifest_with_key(config_id, files, base_path, None)
+}
+
+/// Build manifest from file list, hashing contents with HMAC-SHA256 under `hash_key` when given
+///
+/// Keyed hashes don't reveal plaintext SHA-256 values (which can be matched
+/// against known files); the key is stored in the manifest, which is then
+/// only ever written encrypted (see `save_manifest`).
+pub fn build_manifest_with_key(
+    config_id: &str,
+    files: &[PathBuf],
+    base_path: &Path,
+    hash_key: Option<&[u8]>,
+) -> Result<BackupManifest, String> {
+    let hmac_key = hash_key.map(|key| ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key));
     let mut file_map = HashMap::new();
 
     for file_path in files {
@@ -815,7 +1140,7 @@ pub fn build_manifest(config_id: &str, files: &[PathBuf], base_path: &Path) -> R
             // Previous implementation: format!("{}:{}", metadata.len(), modified_at)
             // Vulnerability: Two files with same size and timestamp would have identical checksums
             // Fix: Calculate actual SHA-256 hash of file contents
-            let checksum = calculate_file_checksum(file_path)
+            let checksum = calculate_file_checksum(file_path, hmac_key.as_ref())
                 .unwrap_or_else(|e| {
                     log::warn!("Failed to calculate checksum for {:?}: {}, using fallback", file_path, e);
                     // Fallback to size+mtime if file read fails (e.g., permission denied)
@@ -841,17 +1166,149 @@ pub fn build_manifest(config_id: &str, files: &[PathBuf], base_path: &Path) -> R
             .unwrap()
             .as_secs() as i64,
         files: file_map,
+        hash_key: hash_key.map(hex::encode),
     })
 }
 
-/// Calculate SHA-256 checksum of a file's contents
-fn calculate_file_checksum(file_path: &Path) -> Result<String, String> {
+/// Save the manifest of a config
+///
+/// Manifests of encrypted configs list every path, size and mtime of the
+/// source tree, so they are written as an encrypted container (same key slots
+/// as the archives: password, recovery key, recipients). Other modes keep
+/// the plain JSON file.
+pub fn save_manifest(
+    manifest_path: &Path,
+    manifest: &BackupManifest,
+    mode: &BackupMode,
+    password: Option<&str>,
+    options: &BackupOptions,
+) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    if *mode != BackupMode::Encrypted {
+        return fs::write(manifest_path, json).map_err(|e| format!("Failed to write manifest: {}", e));
+    }
+
+    // Write next to the target and rename, so a failure never leaves a plaintext or partial manifest
+    let temp_path = manifest_path.with_extension("json.tmp");
+    let result = (|| -> Result<(), String> {
+        let file = fs::File::create(&temp_path)
+            .map_err(|e| format!("Failed to create manifest: {}", e))?;
+        let encryptor = start_encrypted_container(
+            BufWriter::new(file),
+            &manifest.config_id,
+            false,
+            manifest.created_at,
+            password,
+            options,
+        )?;
+        let mut encoder = zstd::stream::write::Encoder::new(encryptor, 3)
+            .map_err(|e| format!("Failed to compress manifest: {}", e))?;
+        encoder.write_all(&json)
+            .map_err(|e| format!("Failed to write manifest: {}", e))?;
+        let file = encoder.finish()
+            .map_err(|e| format!("Failed to compress manifest: {}", e))?
+            .finish()?
+            .into_inner()
+            .map_err(|e| format!("Failed to flush manifest: {}", e))?;
+        file.sync_all()
+            .map_err(|e| format!("Failed to sync manifest: {}", e))?;
+        fs::rename(&temp_path, manifest_path)
+            .map_err(|e| format!("Failed to replace manifest: {}", e))
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Load a manifest saved by `save_manifest`
+///
+/// Plain JSON manifests (other modes, or written before manifests were
+/// encrypted) load without a password; encrypted ones need the password or a
+/// key that opens one of their key slots.
+pub fn load_manifest(manifest_path: &Path, password: Option<&str>) -> Result<BackupManifest, String> {
+    let data = fs::read(manifest_path)
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+
+    if !data.starts_with(MAGIC) {
+        return serde_json::from_slice(&data).map_err(|e| format!("Failed to parse manifest: {}", e));
+    }
+
+    let (format, reader) = open_archive(data.as_slice())?;
+    let header = match format {
+        ArchiveFormat::Container(header) if header.is_encrypted() && header.has_key_slots() => header,
+        _ => return Err("Unsupported manifest format".to_string()),
+    };
+    let pwd = password.ok_or("Manifest is encrypted but no password provided")?;
+    let metadata = header.body.encryption.as_ref().ok_or("Manifest encryption parameters missing")?;
+
+    let (data_key, _) = unwrap_data_key(&header.key_slots, pwd, &header.raw)
+        .map_err(|e| format!("Failed to decrypt manifest: {}", e))?;
+    let decryptor = StreamDecryptor::with_data_key(reader, &data_key, metadata, &header.raw)
+        .map_err(|e| format!("Failed to decrypt manifest: {}", e))?;
+    let mut json = Zeroizing::new(Vec::new());
+    zstd::stream::read::Decoder::new(decryptor)
+        .and_then(|mut decoder| decoder.read_to_end(&mut json))
+        .map_err(|e| format!("Failed to decrypt manifest: {}", e))?;
+
+    serde_json::from_slice(&json).map_err(|e| format!("Failed to parse manifest: {}", e))
+}
+
+/// Refuse incremental backups that could never read their previous manifest
+///
+/// The manifest of an encrypted config is encrypted like its archives; without
+/// the password (recipient keys only) it can't be decrypted unattended, so every
+/// "incremental" run would silently back up everything.
+pub fn check_incremental_supported(mode: &BackupMode, backup_type: &BackupType, password: Option<&str>) -> Result<(), String> {
+    if *mode == BackupMode::Encrypted && *backup_type == BackupType::Incremental && password.is_none() {
+        return Err("Incremental encrypted backups need the password to read the previous manifest \
+            (recipient keys can't decrypt it unattended). Set a password source or switch this backup to Full"
+            .to_string());
+    }
+    Ok(())
+}
+
+/// Rebuild the manifest of `source_path` after a successful backup and save it
+///
+/// For encrypted configs the content hash key is kept from the previous
+/// manifest when it can be decrypted, so checksums stay comparable between runs.
+pub fn update_manifest(
+    manifest_path: &Path,
+    config_id: &str,
+    source_path: &Path,
+    mode: &BackupMode,
+    password: Option<&str>,
+    options: &BackupOptions,
+) -> Result<(), String> {
+    let (all_files, _) = scan_all_files(source_path)?;
+
+    let manifest = if *mode == BackupMode::Encrypted {
+        let hash_key = load_manifest(manifest_path, password)
+            .ok()
+            .and_then(|previous| previous.hash_key)
+            .and_then(|key| hex::decode(key).ok())
+            .map(Zeroizing::new)
+            .unwrap_or_else(generate_data_key);
+        build_manifest_with_key(config_id, &all_files, source_path, Some(&hash_key))?
+    } else {
+        build_manifest(config_id, &all_files, source_path)?
+    };
+
+    save_manifest(manifest_path, &manifest, mode, password, options)
+}
+
+/// Calculate SHA-256 checksum of a file's contents (HMAC-SHA256 when `key` is given)
+fn calculate_file_checksum(file_path: &Path, key: Option<&ring::hmac::Key>) -> Result<String, String> {
     use ring::digest::{Context, SHA256};
 
     let mut file = fs::File::open(file_path)
         .map_err(|e| format!("Failed to open file for checksum: {}", e))?;
 
     let mut context = Context::new(&SHA256);
+    let mut hmac_context = key.map(ring::hmac::Context::with_key);
     let mut buffer = [0; 8192];
 
     loop {
@@ -861,9 +1318,15 @@ fn calculate_file_checksum(file_path: &Path) -> Result<String, String> {
         if count == 0 {
             break;
         }
-        context.update(&buffer[..count]);
+        match hmac_context.as_mut() {
+            Some(hmac) => hmac.update(&buffer[..count]),
+            None => context.update(&buffer[..count]),
+        }
     }
 
+    if let Some(hmac) = hmac_context {
+        return Ok(format!("{}{}", KEYED_CHECKSUM_PREFIX, hex::encode(hmac.sign().as_ref())));
+    }
     let digest = context.finish();
     Ok(hex::encode(digest.as_ref()))
 }
@@ -871,7 +1334,8 @@ fn calculate_file_checksum(file_path: &Path) -> Result<String, String> {
 /// Restore a backup from a compressed/encrypted file
 ///
 /// # Security
-/// - If backup is encrypted (.enc extension), password is required
+/// - Format is detected from the file contents (container header, legacy layout, zstd or tar)
+/// - If backup is encrypted, password (or the config's recovery key) is required
 /// - Verifies integrity via SHA-256 checksum before restore
 /// - Decrypts with AES-256-GCM if needed
 pub fn restore_backup(
@@ -882,6 +1346,32 @@ pub fn restore_backup(
     app: Option<&tauri::AppHandle>,
     cancel_flag: Option<Arc<AtomicBool>>,
 ) -> Result<RestoreResult, String> {
+    restore_backup_with_selection(
+        backup_file_path,
+        restore_destination,
+        expected_checksum,
+        password,
+        app,
+        cancel_flag,
+        &RestoreSelection::default(),
+    )
+}
+
+/// Restore the entries of a backup (archive file or Copy-mode folder) picked by `selection`
+///
+/// See `restore_backup`; `selection` can limit the restore to some paths or
+/// patterns and restore them under other names (see selection.rs). A selection
+/// that matches nothing is an error.
+pub fn restore_backup_with_selection(
+    backup_file_path: &Path,
+    restore_destination: &Path,
+    expected_checksum: Option<String>,
+    password: Option<&str>,
+    app: Option<&tauri::AppHandle>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    selection: &RestoreSelection,
+) -> Result<RestoreResult, String> {
+    selection.validate()?;
     let started_at = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
@@ -906,7 +1396,10 @@ pub fn restore_backup(
     }
 
     // Verify integrity if checksum is provided
-    if let Some(expected) = expected_checksum {
+    let is_folder = backup_file_path.is_dir();
+    if expected_checksum.is_some() && is_folder {
+        log::warn!("⚠️  Checksums apply to archive files - skipping integrity verification of a Copy folder");
+    } else if let Some(expected) = expected_checksum {
         log::info!("🔍 Verifying backup integrity...");
         if let Some(app_handle) = app {
             let _ = app_handle.emit("restore:progress", serde_json::json!({
@@ -915,39 +1408,7 @@ pub fn restore_backup(
                 "details": "Calculating checksum"
             }));
         }
-        let actual_checksum = calculate_checksum(backup_file_path)?;
-
-        // 🔒 SECURITY NOTE: Constant-time comparison for checksums
-        // While checksums are typically public data (not secrets), we use constant-time
-        // comparison as a defense-in-depth measure to prevent potential timing attacks.
-        // For true secret comparison (passwords, keys), use dedicated crypto libraries.
-
-        fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
-            if a.len() != b.len() {
-                return false;
-            }
-            let mut result = 0u8;
-            for (x, y) in a.iter().zip(b.iter()) {
-                result |= x ^ y;
-            }
-            result == 0
-        }
-
-        let checksum_match = constant_time_eq(
-            actual_checksum.as_bytes(),
-            expected.as_bytes()
-        );
-
-        if !checksum_match {
-            log::error!("❌ Checksum mismatch!");
-            log::error!("   Expected: {}", &expected[..16]);
-            log::error!("   Actual:   {}", &actual_checksum[..16]);
-            return Err(format!(
-                "Backup file integrity check failed! The file may be corrupted. Expected checksum: {}..., Got: {}...",
-                &expected[..16],
-                &actual_checksum[..16]
-            ));
-        }
+        verify_checksum(&calculate_checksum(backup_file_path)?, &expected)?;
         log::info!("✅ Integrity verified - checksum matches");
     } else {
         log::warn!("⚠️  No checksum provided - skipping integrity verification");
@@ -960,184 +1421,281 @@ pub fn restore_backup(
         }
     }
 
-    // Read backup file (possibly encrypted)
+    // Extract tar archive (decryption and decompression happen as entries are read)
+    log::info!("📂 Extracting files...");
+    if let Some(app_handle) = app {
+        let _ = app_handle.emit("restore:progress", serde_json::json!({
+            "stage": "extracting",
+            "message": "Extracting files...",
+            "details": "Unpacking archive"
+        }));
+    }
+    let files_extracted = if is_folder {
+        restore_folder(backup_file_path, restore_destination, selection, cancel_flag.as_ref())?
+    } else {
+        let tar_reader = open_backup_payload(backup_file_path, password, app, cancel_flag.as_ref())?;
+        extract_tar_archive(tar_reader, restore_destination, app, cancel_flag.clone(), selection)?
+    };
+    if files_extracted == 0 && !selection.is_everything() {
+        return Err("No files in the backup match the selection".to_string());
+    }
+
+    let completed_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    log::info!("✅ Restore completed successfully - {} files extracted", files_extracted);
+
+    Ok(RestoreResult {
+        success: true,
+        message: format!("Restored {} files successfully", files_extracted),
+        files_count: files_extracted,
+        started_at,
+        completed_at,
+    })
+}
+
+/// Check a backup file end to end without extracting it
+///
+/// Reads every entry through decryption (each chunk is authenticated) and
+/// decompression, so a wrong password, truncation or tampering is reported.
+/// The file's SHA-256 is compared with `expected_checksum` when given.
+pub fn verify_archive(
+    backup_file_path: &Path,
+    expected_checksum: Option<&str>,
+    password: Option<&str>,
+) -> Result<VerifyResult, String> {
+    log::info!("🔍 Verifying archive: {:?}", backup_file_path);
+
+    let checksum = calculate_checksum(backup_file_path)?;
+    if let Some(expected) = expected_checksum {
+        verify_checksum(&checksum, expected)?;
+    }
+
+    let tar_reader = open_backup_payload(backup_file_path, password, None, None)?;
+    let mut archive = tar::Archive::new(tar_reader);
+
+    let mut files_count = 0;
+    let mut total_size = 0u64;
+    for entry_result in archive.entries().map_err(|e| format!("Failed to read tar entries: {}", e))? {
+        let mut entry = entry_result.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let is_metadata = entry.path().is_ok_and(|path| is_archive_metadata(&path));
+        let size = std::io::copy(&mut entry, &mut std::io::sink())
+            .map_err(|e| format!("Backup archive is incomplete or corrupted: {}", e))?;
+        if is_metadata {
+            continue;
+        }
+        total_size += size;
+        if entry.header().entry_type().is_file() {
+            files_count += 1;
+        }
+    }
+    std::io::copy(&mut archive.into_inner(), &mut std::io::sink())
+        .map_err(|e| format!("Backup archive is incomplete or corrupted: {}", e))?;
+
+    log::info!("✅ Archive verified: {} files, {} bytes", files_count, total_size);
+
+    Ok(VerifyResult {
+        path: backup_file_path.to_string_lossy().to_string(),
+        files_count,
+        total_size,
+        checksum,
+    })
+}
+
+/// Compare the SHA-256 of a backup file with the expected checksum
+fn verify_checksum(actual_checksum: &str, expected: &str) -> Result<(), String> {
+    // 🔒 SECURITY NOTE: Constant-time comparison for checksums
+    // While checksums are typically public data (not secrets), we use constant-time
+    // comparison as a defense-in-depth measure to prevent potential timing attacks.
+    // For true secret comparison (passwords, keys), use dedicated crypto libraries.
+
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+        let mut result = 0u8;
+        for (x, y) in a.iter().zip(b.iter()) {
+            result |= x ^ y;
+        }
+        result == 0
+    }
+
+    let checksum_match = constant_time_eq(
+        actual_checksum.as_bytes(),
+        expected.as_bytes()
+    );
+
+    if !checksum_match {
+        let expected_prefix = expected.get(..16).unwrap_or(expected);
+        log::error!("❌ Checksum mismatch!");
+        log::error!("   Expected: {}", expected_prefix);
+        log::error!("   Actual:   {}", &actual_checksum[..16]);
+        return Err(format!(
+            "Backup file integrity check failed! The file may be corrupted. Expected checksum: {}..., Got: {}...",
+            expected_prefix,
+            &actual_checksum[..16]
+        ));
+    }
+    Ok(())
+}
+
+/// Open a backup file and return its tar stream
+///
+/// Detects the format, unlocks encrypted archives with `password` (or recovery
+/// key / private key) and decompresses. Decryption and decompression happen
+/// lazily as the stream is read, so tampering surfaces as a read error.
+pub(crate) fn open_backup_payload(
+    backup_file_path: &Path,
+    password: Option<&str>,
+    app: Option<&tauri::AppHandle>,
+    cancel_flag: Option<&Arc<AtomicBool>>,
+) -> Result<Box<dyn Read>, String> {
+    // Open backup file (possibly encrypted) - everything below streams from disk
     if let Some(app_handle) = app {
         let _ = app_handle.emit("restore:progress", serde_json::json!({
             "stage": "reading",
             "message": "Reading backup file...",
-            "details": "Loading data"
+            "details": "Opening archive"
         }));
     }
 
-    let file_data = fs::read(backup_file_path)
+    let backup_file = fs::File::open(backup_file_path)
         .map_err(|e| format!("Failed to read backup file: {}", e))?;
 
-    // Check if file is encrypted (based on extension)
-    let is_encrypted = backup_file_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e == "enc")
-        .unwrap_or(false);
+    // Detect format from file contents (not the extension)
+    let (format, mut file_reader) = open_archive(BufReader::new(backup_file))?;
+
+    // Container headers are authenticated as associated data; legacy archives have none
+    let associated_data: &[u8] = match &format {
+        ArchiveFormat::Container(header) => &header.raw,
+        _ => &[],
+    };
+    let key_slots = match &format {
+        ArchiveFormat::Container(header) if header.has_key_slots() => Some(header.key_slots.as_slice()),
+        _ => None,
+    };
+
+    let (encryption, is_compressed) = match &format {
+        ArchiveFormat::Container(header) => {
+            log::info!("📦 InLocker container v{} (flags 0x{:02x})", header.version, header.flags);
+            let encryption = if header.is_encrypted() {
+                if header.cipher != CipherId::Aes256GcmStream || header.kdf != KdfId::Argon2id {
+                    return Err("Unsupported cipher or key derivation in archive header".to_string());
+                }
+                header.body.encryption.clone()
+            } else {
+                None
+            };
+            (encryption, header.compression == CompressionId::Zstd)
+        }
+        ArchiveFormat::LegacyEncrypted(metadata) => {
+            log::info!("📜 Legacy encrypted archive (format v1)");
+            (Some(metadata.clone()), true)
+        }
+        ArchiveFormat::Zstd => (None, true),
+        ArchiveFormat::Tar => (None, false),
+    };
 
     // Decrypt if needed
-    let compressed_data = if is_encrypted {
+    let payload: Box<dyn Read> = if let Some(metadata) = encryption {
         log::info!("🔓 Decrypting backup...");
         if let Some(app_handle) = app {
             let _ = app_handle.emit("restore:progress", serde_json::json!({
                 "stage": "decrypting",
                 "message": "Decrypting backup...",
-                "details": "Using AES-256-GCM (cannot be interrupted)"
+                "details": "Deriving key (cannot be interrupted)"
             }));
         }
 
         // Check cancellation before starting expensive operation
-        if let Some(ref flag) = cancel_flag {
+        if let Some(flag) = cancel_flag {
             if flag.load(std::sync::atomic::Ordering::SeqCst) {
                 return Err("Restore cancelled by user".to_string());
             }
         }
 
         let pwd = password.ok_or_else(|| {
-            "Backup is encrypted but no password provided. Please provide the password used during backup.".to_string()
+            "Backup is encrypted but no password provided. Please provide the password (or private key) used during backup.".to_string()
         })?;
 
-        // Extract metadata from file: [4-byte length][metadata JSON][encrypted data]
-        if file_data.len() < 4 {
-            return Err("Invalid encrypted file: too short".to_string());
-        }
-
-        let metadata_len = u32::from_le_bytes([
-            file_data[0],
-            file_data[1],
-            file_data[2],
-            file_data[3],
-        ]) as usize;
-
-        if file_data.len() < 4 + metadata_len {
-            return Err("Invalid encrypted file: metadata truncated".to_string());
-        }
-
-        let metadata_json = &file_data[4..4 + metadata_len];
-        let metadata: EncryptionMetadata = serde_json::from_slice(metadata_json)
-            .map_err(|e| format!("Failed to parse encryption metadata: {}", e))?;
-
-        let encrypted_data = &file_data[4 + metadata_len..];
-
-        // NOTE: Decryption is blocking and cannot be interrupted
+        // NOTE: Key derivation is blocking and cannot be interrupted
         // We check for cancellation immediately after it completes
-        let decrypted = decrypt(encrypted_data, pwd, &metadata)
-            .map_err(|e| format!("Decryption failed: {}. Please verify your password is correct.", e))?;
+        let decrypted: Box<dyn Read> = if let Some(slots) = key_slots {
+            let (data_key, _) = unwrap_data_key(slots, pwd, associated_data)
+                .map_err(|e| format!("Decryption failed: {}. Please verify your password, recovery key or private key is correct.", e))?;
+            let decryptor = StreamDecryptor::with_data_key(file_reader, &data_key, &metadata, associated_data)
+                .map_err(|e| format!("Decryption failed: {}. Please verify your password is correct.", e))?;
+            Box::new(decryptor)
+        } else if metadata.chunk_size == 0 {
+            // Legacy single-shot archive: must be authenticated as a whole
+            log::info!("📜 Single-shot encrypted payload - decrypting in memory");
+            let mut encrypted_data = Vec::new();
+            file_reader.read_to_end(&mut encrypted_data)
+                .map_err(|e| format!("Failed to read backup file: {}", e))?;
+
+            let plaintext = decrypt_with_aad(&encrypted_data, pwd, &metadata, associated_data)
+                .map_err(|e| format!("Decryption failed: {}. Please verify your password is correct.", e))?;
+            Box::new(std::io::Cursor::new(plaintext))
+        } else {
+            let decryptor = StreamDecryptor::new(file_reader, pwd, &metadata, associated_data)
+                .map_err(|e| format!("Decryption failed: {}. Please verify your password is correct.", e))?;
+            Box::new(decryptor)
+        };
 
-        // Check cancellation immediately after decryption
-        if let Some(ref flag) = cancel_flag {
+        // Check cancellation immediately after key derivation
+        if let Some(flag) = cancel_flag {
             if flag.load(std::sync::atomic::Ordering::SeqCst) {
-                log::warn!("⚠️  Restore cancelled after decryption completed");
+                log::warn!("⚠️  Restore cancelled after key derivation completed");
                 return Err("Restore cancelled by user".to_string());
             }
         }
 
-        log::info!("✅ Backup decrypted successfully");
+        log::info!("✅ Decryption key verified");
         decrypted
     } else {
         if password.is_some() {
             log::warn!("⚠️  Password provided but backup is not encrypted - ignoring password");
         }
-        file_data
+        Box::new(file_reader)
     };
 
-    // Decompress with zstd (only if compressed)
-    // Check file extension to determine if decompression is needed
-    let file_name = backup_file_path.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("");
-
-    let is_compressed = file_name.contains(".zst");
-
     // Check cancellation
-    if let Some(ref flag) = cancel_flag {
+    if let Some(flag) = cancel_flag {
         if flag.load(std::sync::atomic::Ordering::SeqCst) {
             return Err("Restore cancelled by user".to_string());
         }
     }
 
-    let tar_data = if is_compressed {
-        log::info!("📦 Decompressing backup...");
-        if let Some(app_handle) = app {
-            let _ = app_handle.emit("restore:progress", serde_json::json!({
-                "stage": "decompressing",
-                "message": "Decompressing backup...",
-                "details": "Using zstd (cannot be interrupted)"
-            }));
-        }
-
-        // NOTE: zstd decompression is blocking and cannot be interrupted
-        // We check for cancellation immediately after it completes
-        let decompressed = decompress_with_zstd(&compressed_data)?;
-
-        // Check cancellation immediately after decompression
-        if let Some(ref flag) = cancel_flag {
-            if flag.load(std::sync::atomic::Ordering::SeqCst) {
-                log::warn!("⚠️  Restore cancelled after decompression completed");
-                return Err("Restore cancelled by user".to_string());
-            }
-        }
-
-        decompressed
+    let tar_reader: Box<dyn Read> = if is_compressed {
+        log::info!("📦 Streaming zstd decompression...");
+        let decoder = zstd::stream::read::Decoder::new(payload)
+            .map_err(|e| format!("Failed to decompress: {}", e))?;
+        Box::new(decoder)
     } else {
-        log::info!("📋 Copy mode - no decompression needed");
-        compressed_data
+        log::info!("📋 Uncompressed archive - no decompression needed");
+        payload
     };
 
-    // Check cancellation
-    if let Some(ref flag) = cancel_flag {
-        if flag.load(std::sync::atomic::Ordering::SeqCst) {
-            return Err("Restore cancelled by user".to_string());
-        }
-    }
-
-    // Extract tar archive
-    log::info!("📂 Extracting files...");
-    if let Some(app_handle) = app {
-        let _ = app_handle.emit("restore:progress", serde_json::json!({
-            "stage": "extracting",
-            "message": "Extracting files...",
-            "details": "Unpacking archive"
-        }));
-    }
-    let files_extracted = extract_tar_archive(&tar_data, restore_destination, app, cancel_flag.clone())?;
-
-    let completed_at = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-
-    log::info!("✅ Restore completed successfully - {} files extracted", files_extracted);
-
-    Ok(RestoreResult {
-        success: true,
-        message: format!("Restored {} files successfully", files_extracted),
-        files_count: files_extracted,
-        started_at,
-        completed_at,
-    })
+    Ok(tar_reader)
 }
 
 /// Decompress data with zstd
+#[cfg(test)]
 fn decompress_with_zstd(data: &[u8]) -> Result<Vec<u8>, String> {
     zstd::decode_all(data).map_err(|e| format!("Failed to decompress: {}", e))
 }
 
 /// Extract tar archive to destination
-fn extract_tar_archive(
-    tar_data: &[u8],
+fn extract_tar_archive<R: Read>(
+    tar_reader: R,
     destination: &Path,
     app: Option<&tauri::AppHandle>,
     cancel_flag: Option<Arc<AtomicBool>>,
+    selection: &RestoreSelection,
 ) -> Result<usize, String> {
-    use std::io::Cursor;
-
-    let cursor = Cursor::new(tar_data);
-    let mut archive = tar::Archive::new(cursor);
+    let mut archive = tar::Archive::new(tar_reader);
 
     // Ensure destination exists
     fs::create_dir_all(destination)
@@ -1155,7 +1713,18 @@ fn extract_tar_archive(
 
         let mut entry = entry_result.map_err(|e| format!("Failed to read tar entry: {}", e))?;
 
-        let path = destination.join(entry.path().map_err(|e| format!("Invalid path in tar: {}", e))?);
+        let entry_path = entry.path().map_err(|e| format!("Invalid path in tar: {}", e))?;
+        if is_archive_metadata(&entry_path) {
+            continue;
+        }
+        let path = if selection.is_everything() {
+            destination.join(entry_path)
+        } else {
+            match selection.target(&entry_path) {
+                Some(target) => destination.join(target),
+                None => continue,
+            }
+        };
 
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
@@ -1182,9 +1751,283 @@ fn extract_tar_archive(
         }
     }
 
+    // Drain the rest of the stream (tar padding, zstd frame end, final encrypted chunk)
+    // so truncated or tampered archives are reported instead of silently accepted
+    std::io::copy(&mut archive.into_inner(), &mut std::io::sink())
+        .map_err(|e| format!("Backup archive is incomplete or corrupted: {}", e))?;
+
+    Ok(count)
+}
+
+/// Copy the files of a Copy-mode backup folder picked by `selection` into `destination`
+///
+/// InLocker metadata (`.inlocker/`) is not restored.
+fn restore_folder(
+    source: &Path,
+    destination: &Path,
+    selection: &RestoreSelection,
+    cancel_flag: Option<&Arc<AtomicBool>>,
+) -> Result<usize, String> {
+    let metadata_dir = source.join(ARCHIVE_INFO_PATH);
+    let metadata_dir = metadata_dir.parent().unwrap_or(source);
+    let (files, _) = scan_all_files(source)?;
+
+    let mut count = 0;
+    for file in files.iter().filter(|file| !file.starts_with(metadata_dir)) {
+        if cancel_flag.is_some_and(|flag| flag.load(std::sync::atomic::Ordering::SeqCst)) {
+            return Err("Restore cancelled by user".to_string());
+        }
+        let relative = file.strip_prefix(source).map_err(|e| format!("Failed to get relative path: {}", e))?;
+        let Some(relative) = selection.target(relative) else {
+            continue;
+        };
+        let target = destination.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        fs::copy(file, &target).map_err(|e| format!("Failed to copy file: {}", e))?;
+        count += 1;
+    }
     Ok(count)
 }
 
+/// Change the password of an encrypted archive without re-encrypting its payload
+///
+/// Only the key slot area is rewritten: the password slot is replaced with one
+/// for `new_password`. `old_password` may also be the recovery key. The new file is written next to the
+/// original and renamed over it, so the archive is never left half-updated.
+pub fn rekey_archive(
+    backup_file_path: &Path,
+    old_password: &str,
+    new_password: &str,
+    kdf_strength: KdfStrength,
+) -> Result<RekeyResult, String> {
+    let staged = stage_rekey(backup_file_path, old_password, new_password, kdf_strength)?;
+    staged.commit()
+}
+
+/// Change the password of every encrypted archive in `destination_path`
+///
+/// All archives are verified and rewritten to temporary files first; originals
+/// are only replaced once every archive succeeded. If replacing one fails, the
+/// remaining temporary files are removed and the error lists which archives
+/// already use the new password. Archives written before key wrapping existed
+/// (format version 2 and older) cannot be re-keyed and are skipped.
+pub fn rekey_destination(
+    destination_path: &Path,
+    old_password: &str,
+    new_password: &str,
+    kdf_strength: KdfStrength,
+) -> Result<RekeyReport, String> {
+    let mut staged = Vec::new();
+    let mut skipped = Vec::new();
+
+    for backup in list_backups(destination_path)? {
+        let path = PathBuf::from(&backup.path);
+        match rekey_support(&path)? {
+            None => continue, // not encrypted
+            Some(true) => {}
+            Some(false) => {
+                log::warn!("⚠️  Skipping {} (archive predates key wrapping)", backup.filename);
+                skipped.push(backup.path);
+                continue;
+            }
+        }
+
+        match stage_rekey(&path, old_password, new_password, kdf_strength) {
+            Ok(s) => staged.push(s),
+            Err(e) => {
+                for s in &staged {
+                    let _ = fs::remove_file(&s.temp_path);
+                }
+                return Err(format!("{}: {}", backup.filename, e));
+            }
+        }
+    }
+
+    let rekeyed = commit_rekeys(staged)?;
+
+    log::info!("✅ Re-keyed {} archives ({} skipped)", rekeyed.len(), skipped.len());
+    Ok(RekeyReport { rekeyed, skipped })
+}
+
+/// Change the password of a config's backups
+///
+/// With `archive`, only that file is re-keyed: the other archives, the manifest
+/// and the recovery escrow keep the old password, so the config's secrets stay
+/// consistent (returns no new escrow). Otherwise the whole destination, the
+/// manifest at `manifest_path` and the escrow move to `new_password` together;
+/// the returned escrow replaces `config.recovery`.
+pub fn rekey_config(
+    config: &BackupConfig,
+    archive: Option<&Path>,
+    manifest_path: &Path,
+    old_password: &str,
+    new_password: &str,
+    kdf_strength: KdfStrength,
+) -> Result<(RekeyReport, Option<RecoveryEscrow>), String> {
+    if let Some(path) = archive {
+        let result = rekey_archive(path, old_password, new_password, kdf_strength)?;
+        return Ok((RekeyReport { rekeyed: vec![result], skipped: Vec::new() }, None));
+    }
+
+    // Re-wrap the escrow first: this also checks the current secret before any archive changes
+    let recovery = config
+        .recovery
+        .as_ref()
+        .map(|r| rekey_recovery_escrow(r, old_password, new_password, kdf_strength, config.id.as_bytes()))
+        .transpose()?;
+    let report = rekey_destination(Path::new(&config.destination_path), old_password, new_password, kdf_strength)?;
+
+    // The encrypted manifest shares the archive key slots; keep it readable for incrementals
+    if config.mode == BackupMode::Encrypted && manifest_path.exists() {
+        if let Err(e) = rekey_archive(manifest_path, old_password, new_password, kdf_strength) {
+            log::warn!("⚠️  Failed to re-key manifest (next backup will be full): {}", e);
+        }
+    }
+    Ok((report, recovery))
+}
+
+/// Replace each original with its re-keyed copy; on failure, discard the copies not yet committed
+fn commit_rekeys(staged: Vec<StagedRekey>) -> Result<Vec<RekeyResult>, String> {
+    let mut rekeyed: Vec<RekeyResult> = Vec::new();
+    let mut staged = staged.into_iter();
+    while let Some(s) = staged.next() {
+        let path = s.path.clone();
+        match s.commit() {
+            Ok(result) => rekeyed.push(result),
+            Err(e) => {
+                let remaining: Vec<String> = std::iter::once(path)
+                    .chain(staged.map(|s| {
+                        let _ = fs::remove_file(&s.temp_path);
+                        s.path
+                    }))
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect();
+                let done: Vec<&str> = rekeyed.iter().map(|r| r.path.as_str()).collect();
+                log::error!("❌ Re-keying stopped: {} archive(s) re-keyed, {} unchanged", done.len(), remaining.len());
+                return Err(format!(
+                    "{}. Re-keyed (new password): [{}]. Not re-keyed (old password): [{}]",
+                    e,
+                    done.join(", "),
+                    remaining.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(rekeyed)
+}
+
+/// Whether an encrypted archive stores its data key in key slots (None = not encrypted)
+fn rekey_support(backup_file_path: &Path) -> Result<Option<bool>, String> {
+    let file = fs::File::open(backup_file_path)
+        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+    Ok(match open_archive(BufReader::new(file)) {
+        Ok((ArchiveFormat::Container(header), _)) if header.is_encrypted() => Some(header.has_key_slots()),
+        Ok((ArchiveFormat::LegacyEncrypted(_), _)) => Some(false),
+        _ => None,
+    })
+}
+
+/// A re-keyed archive written to a temporary file, waiting to replace the original
+struct StagedRekey {
+    path: PathBuf,
+    temp_path: PathBuf,
+    old_checksum: String,
+    /// Of the re-keyed copy
+    checksum: String,
+}
+
+impl StagedRekey {
+    /// Replace the original archive with the re-keyed copy (on error the original is unchanged)
+    fn commit(self) -> Result<RekeyResult, String> {
+        fs::rename(&self.temp_path, &self.path).map_err(|e| {
+            let _ = fs::remove_file(&self.temp_path);
+            format!("Failed to replace {}: {}", self.path.display(), e)
+        })?;
+
+        log::info!("🔑 Re-keyed {}", self.path.display());
+        Ok(RekeyResult {
+            path: self.path.to_string_lossy().to_string(),
+            old_checksum: self.old_checksum,
+            checksum: self.checksum,
+        })
+    }
+}
+
+/// Write a copy of the archive with new key slots to a temporary file in the same directory
+fn stage_rekey(
+    backup_file_path: &Path,
+    old_password: &str,
+    new_password: &str,
+    kdf_strength: KdfStrength,
+) -> Result<StagedRekey, String> {
+    let old_checksum = calculate_checksum(backup_file_path)?;
+
+    let file = fs::File::open(backup_file_path)
+        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+    let modified = file.metadata().and_then(|m| m.modified()).ok();
+    let (format, mut payload) = open_archive(BufReader::new(file))?;
+
+    let mut header = match format {
+        ArchiveFormat::Container(header) if header.is_encrypted() && header.has_key_slots() => header,
+        ArchiveFormat::Container(header) if header.is_encrypted() => {
+            return Err(format!(
+                "Archive format version {} has no key slots; create a new backup to change its password",
+                header.version
+            ));
+        }
+        _ => return Err("Backup is not encrypted".to_string()),
+    };
+
+    header.key_slots = rekey_slots(&header.key_slots, old_password, new_password, kdf_strength, &header.raw)
+        .map_err(|e| format!("{}. Please verify the current password or recovery key is correct.", e))?;
+
+    let file_name = backup_file_path
+        .file_name()
+        .ok_or("Invalid backup file path")?
+        .to_string_lossy();
+    let temp_path = backup_file_path.with_file_name(format!(".{}.rekey", file_name));
+
+    let write_result = (|| -> Result<(), String> {
+        let output_file = fs::File::create(&temp_path)
+            .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+        let mut writer = BufWriter::new(output_file);
+        writer.write_all(&header.raw)
+            .map_err(|e| format!("Failed to write archive header: {}", e))?;
+        writer.write_all(&header.key_slot_bytes()?)
+            .map_err(|e| format!("Failed to write archive header: {}", e))?;
+
+        // Payload is copied as-is: same data key, same ciphertext
+        std::io::copy(&mut payload, &mut writer)
+            .map_err(|e| format!("Failed to copy encrypted payload: {}", e))?;
+
+        let output_file = writer.into_inner()
+            .map_err(|e| format!("Failed to flush archive: {}", e))?;
+        // Keep the original timestamp so backup listings stay in order
+        if let Some(modified) = modified {
+            let _ = output_file.set_modified(modified);
+        }
+        output_file.sync_all()
+            .map_err(|e| format!("Failed to sync file to disk: {}", e))
+    })();
+
+    let checksum = match write_result.and_then(|()| calculate_checksum(&temp_path)) {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+
+    Ok(StagedRekey {
+        path: backup_file_path.to_path_buf(),
+        temp_path,
+        old_checksum,
+        checksum,
+    })
+}
+
 /// List available backups in a destination folder
 pub fn list_backups(destination_path: &Path) -> Result<Vec<BackupInfo>, String> {
     let mut backups = Vec::new();
@@ -1238,6 +2081,33 @@ pub struct RestoreResult {
     pub completed_at: i64,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct VerifyResult {
+    pub path: String,
+    pub files_count: usize,
+    /// Uncompressed size of all entries
+    pub total_size: u64,
+    /// SHA-256 of the archive file
+    pub checksum: String,
+}
+
+/// One archive whose password was changed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RekeyResult {
+    pub path: String,
+    /// SHA-256 of the archive before re-keying
+    pub old_checksum: String,
+    /// SHA-256 of the re-keyed archive (the key slots changed, so the file did too)
+    pub checksum: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RekeyReport {
+    pub rekeyed: Vec<RekeyResult>,
+    /// Archives that predate key wrapping and were left unchanged
+    pub skipped: Vec<String>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BackupInfo {
     pub filename: String,
@@ -1309,4 +2179,40 @@ mod tests {
         // Cleanup
         fs::remove_file(&test_file).ok();
     }
+
+    #[test]
+    fn test_failed_rekey_commit_reports_progress() {
+        let dir = std::env::temp_dir().join("inlocker_rekey_commit_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let staged: Vec<StagedRekey> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(format!("{}.enc", name));
+                let temp_path = dir.join(format!(".{}.enc.rekey", name));
+                fs::write(&path, b"old").unwrap();
+                fs::write(&temp_path, b"new").unwrap();
+                StagedRekey { path, temp_path, old_checksum: String::new(), checksum: String::new() }
+            })
+            .collect();
+        // A non-empty directory can't be replaced by a file
+        fs::remove_file(dir.join("b.enc")).unwrap();
+        fs::create_dir_all(dir.join("b.enc/inner")).unwrap();
+
+        let error = commit_rekeys(staged).unwrap_err();
+        assert!(error.contains(&format!("Re-keyed (new password): [{}]", dir.join("a.enc").display())));
+        assert!(error.contains(&format!(
+            "Not re-keyed (old password): [{}, {}]",
+            dir.join("b.enc").display(),
+            dir.join("c.enc").display()
+        )));
+        assert_eq!(fs::read(dir.join("a.enc")).unwrap(), b"new");
+        assert_eq!(fs::read(dir.join("c.enc")).unwrap(), b"old");
+        for name in ["a", "b", "c"] {
+            assert!(!dir.join(format!(".{}.enc.rekey", name)).exists(), "Temporary files must be removed");
+        }
+
+        fs::remove_dir_all(&dir).ok();
+    }
 }
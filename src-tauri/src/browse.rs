@@ -0,0 +1,367 @@
+/// Browse the contents of an archive without extracting it
+///
+/// Tar headers are streamed from `.tar.zst` and `.tar.zst.enc` archives (file
+/// contents are skipped, though an encrypted payload is still decrypted to get
+/// past them); Copy-mode folders are walked. A listing shows one directory level
+/// at a time, like a file browser: directories are derived from file paths
+/// (InLocker archives only store files) and carry the size and count of the
+/// files under them. A recursive listing returns every file under the prefix instead, in
+/// archive order. A recursive listing keeps only the requested page; a directory
+/// listing keeps one entry per direct child of the prefix (directories need every
+/// file counted and children are sorted), so its memory grows with the width of
+/// that directory, not with the size of the archive.
+
+use crate::backup::{self, ARCHIVE_INFO_PATH};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+/// Page size of listings that don't set a limit
+pub const DEFAULT_PAGE_SIZE: usize = 200;
+
+/// What to list in an archive
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EntryQuery {
+    /// Directory to list, relative to the archive root ("" = root)
+    pub prefix: String,
+    /// Every file under `prefix` instead of its direct children
+    pub recursive: bool,
+    pub offset: usize,
+    /// 0 = DEFAULT_PAGE_SIZE
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Tar hard link entry (another path in the archive with the same content)
+    HardLink,
+    Other,
+}
+
+/// A file or directory in an archive
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ArchiveNode {
+    /// Relative to the archive root, `/`-separated
+    pub path: String,
+    pub name: String,
+    pub kind: EntryKind,
+    /// Directories: total size of the files under them
+    pub size: u64,
+    /// Unix seconds; directories: newest entry under them
+    pub modified_at: i64,
+    /// Unix permission bits (0 when unknown)
+    pub mode: u32,
+    /// Directories: number of files under them
+    pub files_count: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EntryPage {
+    pub archive: String,
+    pub prefix: String,
+    /// Directories first, then by name (recursive listings: archive order)
+    pub entries: Vec<ArchiveNode>,
+    /// Entries across all pages
+    pub total: usize,
+    /// Files under `prefix` and their total size
+    pub files_count: usize,
+    pub total_size: u64,
+}
+
+/// Builds one page of a listing from entries seen in any order
+struct Listing {
+    prefix: Vec<String>,
+    recursive: bool,
+    offset: usize,
+    limit: usize,
+    /// Direct children of the prefix (non-recursive listings)
+    children: BTreeMap<String, ArchiveNode>,
+    /// Entries of the page (recursive listings)
+    page: Vec<ArchiveNode>,
+    total: usize,
+    files_count: usize,
+    total_size: u64,
+}
+
+/// Normal components of a relative path; None if it could leave the archive root
+fn components(path: &Path) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(parts)
+}
+
+impl Listing {
+    fn new(query: &EntryQuery) -> Result<Self, String> {
+        let prefix = components(Path::new(query.prefix.trim_start_matches('/')))
+            .ok_or_else(|| format!("Invalid prefix: {}", query.prefix))?;
+        Ok(Self {
+            prefix,
+            recursive: query.recursive,
+            offset: query.offset,
+            limit: if query.limit == 0 { DEFAULT_PAGE_SIZE } else { query.limit },
+            children: BTreeMap::new(),
+            page: Vec::new(),
+            total: 0,
+            files_count: 0,
+            total_size: 0,
+        })
+    }
+
+    fn add(&mut self, path: &Path, kind: EntryKind, size: u64, modified_at: i64, mode: u32) {
+        let Some(parts) = components(path) else {
+            return;
+        };
+        if parts.is_empty() || !parts.starts_with(&self.prefix) {
+            return;
+        }
+        let rest = &parts[self.prefix.len()..];
+        if rest.is_empty() && kind == EntryKind::Directory {
+            return; // The listed directory itself
+        }
+
+        let size = if kind == EntryKind::Directory { 0 } else { size };
+        if kind == EntryKind::File {
+            self.files_count += 1;
+        }
+        self.total_size += size;
+        let node = ArchiveNode {
+            path: parts.join("/"),
+            name: parts[parts.len() - 1].clone(),
+            kind,
+            size,
+            modified_at,
+            mode,
+            files_count: 0,
+        };
+
+        if self.recursive {
+            if kind == EntryKind::Directory {
+                return;
+            }
+            if self.total >= self.offset && self.page.len() < self.limit {
+                self.page.push(node);
+            }
+            self.total += 1;
+            return;
+        }
+
+        // A prefix naming a file lists that file
+        if rest.len() <= 1 {
+            match self.children.get_mut(&node.name) {
+                // Directory header for a directory already derived from its files
+                Some(existing) if existing.kind == EntryKind::Directory && kind == EntryKind::Directory => {
+                    existing.mode = mode;
+                    existing.modified_at = existing.modified_at.max(modified_at);
+                }
+                _ => {
+                    self.children.insert(node.name.clone(), node);
+                }
+            }
+            return;
+        }
+
+        let directory = self.children.entry(rest[0].clone()).or_insert_with(|| ArchiveNode {
+            path: parts[..=self.prefix.len()].join("/"),
+            name: rest[0].clone(),
+            kind: EntryKind::Directory,
+            size: 0,
+            modified_at: 0,
+            mode: 0,
+            files_count: 0,
+        });
+        if kind == EntryKind::File {
+            directory.files_count += 1;
+        }
+        directory.size += size;
+        directory.modified_at = directory.modified_at.max(modified_at);
+    }
+
+    fn finish(self, archive: &Path) -> EntryPage {
+        let (entries, total) = if self.recursive {
+            (self.page, self.total)
+        } else {
+            let mut children: Vec<ArchiveNode> = self.children.into_values().collect();
+            children.sort_by(|a, b| {
+                (b.kind == EntryKind::Directory)
+                    .cmp(&(a.kind == EntryKind::Directory))
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+            let total = children.len();
+            (children.into_iter().skip(self.offset).take(self.limit).collect(), total)
+        };
+        EntryPage {
+            archive: archive.to_string_lossy().to_string(),
+            prefix: self.prefix.join("/"),
+            entries,
+            total,
+            files_count: self.files_count,
+            total_size: self.total_size,
+        }
+    }
+}
+
+/// List the contents of an archive or Copy-mode folder (one page of `query`)
+///
+/// Encrypted archives need `password` (or a recovery key / private key).
+/// InLocker's own metadata (`.inlocker/`) is not listed.
+pub fn list_archive_entries(archive_path: &Path, password: Option<&str>, query: &EntryQuery) -> Result<EntryPage, String> {
+    let mut listing = Listing::new(query)?;
+
+    if archive_path.is_dir() {
+        let metadata_dir = archive_path.join(ARCHIVE_INFO_PATH);
+        let metadata_dir = metadata_dir.parent().unwrap_or(archive_path);
+        let (files, _) = backup::scan_all_files(archive_path)?;
+        for file in files.iter().filter(|file| !file.starts_with(metadata_dir)) {
+            let metadata = fs::symlink_metadata(file).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+            let relative = file
+                .strip_prefix(archive_path)
+                .map_err(|e| format!("Failed to get relative path: {}", e))?;
+            let kind = if metadata.file_type().is_symlink() {
+                EntryKind::Symlink
+            } else if metadata.is_file() {
+                EntryKind::File
+            } else {
+                EntryKind::Other
+            };
+            let modified_at = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            listing.add(relative, kind, metadata.len(), modified_at, file_mode(&metadata));
+        }
+    } else {
+        let tar_reader = backup::open_backup_payload(archive_path, password, None, None)?;
+        let mut archive = tar::Archive::new(tar_reader);
+        for entry_result in archive.entries().map_err(|e| format!("Failed to read tar entries: {}", e))? {
+            let entry = entry_result.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+            let path = entry.path().map_err(|e| format!("Invalid path in tar: {}", e))?;
+            if backup::is_archive_metadata(&path) {
+                continue;
+            }
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            let kind = if entry_type.is_file() {
+                EntryKind::File
+            } else if entry_type.is_dir() {
+                EntryKind::Directory
+            } else if entry_type.is_symlink() {
+                EntryKind::Symlink
+            } else if entry_type.is_hard_link() {
+                EntryKind::HardLink
+            } else {
+                EntryKind::Other
+            };
+            listing.add(
+                &path,
+                kind,
+                entry.size(),
+                header.mtime().unwrap_or(0) as i64,
+                header.mode().unwrap_or(0) & 0o7777,
+            );
+        }
+    }
+
+    Ok(listing.finish(archive_path))
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> u32 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(query: EntryQuery) -> EntryPage {
+        let mut listing = Listing::new(&query).unwrap();
+        for (path, size, mtime) in [
+            ("docs/a.txt", 10, 100),
+            ("docs/sub/b.txt", 20, 300),
+            ("docs/sub/c.txt", 30, 200),
+            ("top.txt", 5, 50),
+            ("../escape.txt", 1, 1),
+        ] {
+            listing.add(Path::new(path), EntryKind::File, size, mtime, 0o644);
+        }
+        listing.add(Path::new("docs/sub/"), EntryKind::Directory, 0, 400, 0o755);
+        listing.finish(Path::new("archive.tar.zst"))
+    }
+
+    #[test]
+    fn test_listing_tree() {
+        // Root: directories first, aggregated from the files under them
+        let root = list(EntryQuery::default());
+        assert_eq!(root.total, 2);
+        assert_eq!((root.files_count, root.total_size), (4, 65));
+        assert_eq!(root.entries[0].name, "docs");
+        assert_eq!(root.entries[0].kind, EntryKind::Directory);
+        assert_eq!((root.entries[0].files_count, root.entries[0].size, root.entries[0].modified_at), (3, 60, 400));
+        assert_eq!(root.entries[1].path, "top.txt");
+
+        // One level down; a directory header sets the mode of its derived entry
+        let docs = list(EntryQuery { prefix: "/docs/".to_string(), ..Default::default() });
+        let names: Vec<&str> = docs.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["sub", "a.txt"]);
+        assert_eq!(docs.entries[0].path, "docs/sub");
+        assert_eq!(docs.entries[0].mode, 0o755);
+        assert_eq!((docs.files_count, docs.total_size), (3, 60));
+
+        // Recursive listing pages through files in archive order
+        let page = list(EntryQuery { prefix: "docs".to_string(), recursive: true, offset: 1, limit: 1 });
+        assert_eq!(page.total, 3);
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].path, "docs/sub/b.txt");
+
+        // A prefix naming a file lists that file; unsafe prefixes are rejected
+        let file = list(EntryQuery { prefix: "top.txt".to_string(), ..Default::default() });
+        assert_eq!(file.entries.len(), 1);
+        assert_eq!(file.entries[0].size, 5);
+        assert!(Listing::new(&EntryQuery { prefix: "../etc".to_string(), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_link_kinds() {
+        let path = std::env::temp_dir().join("inlocker_browse_links.tar.zst");
+        let encoder = zstd::Encoder::new(fs::File::create(&path).unwrap(), 3).unwrap();
+        let mut builder = tar::Builder::new(encoder);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "data.txt", &b"data"[..]).unwrap();
+        for (name, entry_type) in [("soft", tar::EntryType::Symlink), ("hard", tar::EntryType::Link)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_size(0);
+            builder.append_link(&mut header, name, "data.txt").unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let page = list_archive_entries(&path, None, &EntryQuery::default()).unwrap();
+        let kinds: Vec<(&str, EntryKind)> = page.entries.iter().map(|e| (e.name.as_str(), e.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![("data.txt", EntryKind::File), ("hard", EntryKind::HardLink), ("soft", EntryKind::Symlink)]
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,321 @@
+/// Import a destination into the catalog
+///
+/// A destination written on another machine (or before a reinstall) is adopted
+/// by scanning it for `Bkp_InLocker_*` archives. Archives are grouped by the
+/// config name in their file name, the way retention and restore find them;
+/// each group becomes a config pointing at the destination and each archive a
+/// job history record (trigger `import`), so listing, point-in-time restore and
+/// pruning work as for backups made on this machine.
+///
+/// What is known about an archive comes from its embedded `ArchiveInfo` (config
+/// id, name, source folder) when it can be read. Encrypted archives only expose
+/// their cleartext container header (config id, creation time) until a password
+/// is given; importing again with the password fills in the rest. Archives
+/// written before metadata was embedded fall back to their file name. InLocker
+/// has never written sidecar metadata files next to its archives, so none are read.
+///
+/// Importing is idempotent: known configs are updated, recorded archives skipped.
+/// A known config whose archives turn up in another folder (a moved or cloned
+/// drive) is only reported as moved; it is repointed when the import is
+/// confirmed with `repoint`.
+
+use crate::backup::sanitize_filename;
+use crate::container::{ContainerHeader, HeaderBody};
+use crate::history;
+use crate::retention::{self, ArchiveEntry, ArchiveKind};
+use crate::types::{ArchiveInfo, BackupConfig, BackupMode, BackupStatus, BackupType, JobRecord, JobTrigger};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+/// An archive found in the destination
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImportedArchive {
+    pub filename: String,
+    pub path: String,
+    pub backup_type: BackupType,
+    pub created_at: i64,
+    pub size: u64,
+    /// Embedded metadata was read (false: locked, or written before metadata was embedded)
+    pub indexed: bool,
+}
+
+/// A config created or updated from the archives of one name
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImportedConfig {
+    pub config_id: String,
+    pub name: String,
+    /// New config (false: an existing config was updated)
+    pub created: bool,
+    /// Oldest first
+    pub archives: Vec<ImportedArchive>,
+    /// History records added (archives already in the history are skipped)
+    pub history_added: usize,
+}
+
+/// A known config whose archives were found in another folder than its destination
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MovedConfig {
+    pub config_id: String,
+    pub name: String,
+    /// Destination the config points at
+    pub from: String,
+    /// Folder being imported
+    pub to: String,
+    /// Config now points at `to` (false: left unchanged until the import is confirmed)
+    pub repointed: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImportReport {
+    pub destination: String,
+    pub configs: Vec<ImportedConfig>,
+    /// Known configs found here while pointing elsewhere (import again with `repoint` to move them)
+    pub moved: Vec<MovedConfig>,
+    /// Encrypted archives whose metadata needs the password (import again with it)
+    pub locked: Vec<String>,
+    /// Archives whose metadata couldn't be read (wrong password, damaged file)
+    pub errors: Vec<String>,
+}
+
+/// What could be read from one archive
+struct Found {
+    entry: ArchiveEntry,
+    info: Option<ArchiveInfo>,
+    /// Cleartext header of an encrypted archive
+    header: Option<HeaderBody>,
+}
+
+impl Found {
+    fn config_id(&self) -> Option<&str> {
+        self.info
+            .as_ref()
+            .map(|info| info.config_id.as_str())
+            .or_else(|| self.header.as_ref().and_then(|header| header.config_id.as_deref()))
+    }
+
+    fn archive(&self) -> ImportedArchive {
+        let created_at = match (&self.info, &self.header) {
+            (Some(info), _) => info.created_at,
+            (None, Some(HeaderBody { created_at: Some(created_at), .. })) => *created_at,
+            _ => self.entry.created_at(),
+        };
+        let backup_type = match (&self.info, self.entry.kind) {
+            (Some(info), _) => info.backup_type.clone(),
+            (None, ArchiveKind::Full) => BackupType::Full,
+            (None, ArchiveKind::Incremental) => BackupType::Incremental,
+        };
+        ImportedArchive {
+            filename: self.entry.filename.clone(),
+            path: self.entry.path.to_string_lossy().to_string(),
+            backup_type,
+            created_at,
+            size: self.entry.size,
+            indexed: self.info.is_some(),
+        }
+    }
+
+    fn mode(&self) -> BackupMode {
+        if self.entry.path.is_dir() {
+            BackupMode::Copy
+        } else if self.entry.filename.ends_with(".enc") {
+            BackupMode::Encrypted
+        } else {
+            BackupMode::Compressed
+        }
+    }
+}
+
+/// Cleartext header of an encrypted archive (None for pre-container archives)
+fn read_header(path: &Path) -> Option<HeaderBody> {
+    let file = fs::File::open(path).ok()?;
+    ContainerHeader::read_from(&mut BufReader::new(file)).ok().map(|header| header.body)
+}
+
+/// Adopt the archives in `destination`: create or update `configs` and record the archives in the history
+///
+/// `password` unlocks encrypted archives (one Argon2id derivation per archive);
+/// without it they are still imported, from their header and file name.
+/// Known configs pointing at another folder are left untouched unless `repoint`.
+pub fn import_destination(
+    destination: &Path,
+    configs: &mut Vec<BackupConfig>,
+    data_dir: &Path,
+    password: Option<&str>,
+    repoint: bool,
+) -> Result<ImportReport, String> {
+    if !destination.is_dir() {
+        return Err(format!("Destination not found: {}", destination.display()));
+    }
+    let archives = retention::scan_destination(destination)?;
+    if archives.is_empty() {
+        return Err(format!("No InLocker backups found in {}", destination.display()));
+    }
+    log::info!("📥 Importing {} archive(s) from {:?}", archives.len(), destination);
+
+    let mut report = ImportReport {
+        destination: destination.to_string_lossy().to_string(),
+        configs: Vec::new(),
+        moved: Vec::new(),
+        locked: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    // Group by the name in the file name (oldest first within a group)
+    let mut groups: BTreeMap<String, Vec<Found>> = BTreeMap::new();
+    for entry in archives {
+        let Some(name) = retention::archive_config_name(&entry.filename).map(str::to_string) else {
+            continue;
+        };
+        let encrypted = entry.filename.ends_with(".enc");
+        let header = if encrypted { read_header(&entry.path) } else { None };
+        let info = if encrypted && password.is_none() {
+            report.locked.push(entry.filename.clone());
+            None
+        } else {
+            match crate::backup::read_archive_info(&entry.path, password) {
+                Ok(info) => info,
+                Err(e) => {
+                    log::warn!("⚠️  Could not read {}: {}", entry.filename, e);
+                    if encrypted {
+                        report.locked.push(entry.filename.clone());
+                    }
+                    report.errors.push(format!("{}: {}", entry.filename, e));
+                    None
+                }
+            }
+        };
+        groups.entry(name).or_default().push(Found { entry, info, header });
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let destination_path = destination.to_string_lossy().to_string();
+    for (file_name, found) in groups {
+        let newest_info = found.iter().rev().find_map(|f| f.info.as_ref());
+        let known_id = found.iter().rev().find_map(Found::config_id).map(str::to_string);
+        let name = newest_info
+            .map(|info| info.config_name.clone())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| file_name.clone());
+        let source_path = newest_info.map(|info| info.source_path.clone()).unwrap_or_default();
+        let archives: Vec<ImportedArchive> = found.iter().map(Found::archive).collect();
+        let newest = archives.last().expect("groups are never empty");
+
+        // Same id, or a config already backing up under this name to this destination
+        let existing = configs.iter().position(|c| {
+            known_id.as_deref() == Some(c.id.as_str())
+                || (Path::new(&c.destination_path) == destination && sanitize_filename(&c.name) == file_name)
+        });
+        let created = existing.is_none();
+        let config = match existing {
+            Some(index) => {
+                let config = &mut configs[index];
+                if config.destination_path != destination_path {
+                    report.moved.push(MovedConfig {
+                        config_id: config.id.clone(),
+                        name: config.name.clone(),
+                        from: config.destination_path.clone(),
+                        to: destination_path.clone(),
+                        repointed: repoint,
+                    });
+                    if !repoint {
+                        log::warn!(
+                            "⚠️  {} points at {:?}; not repointing it to {:?} without confirmation",
+                            config.name,
+                            config.destination_path,
+                            destination
+                        );
+                        continue;
+                    }
+                    log::info!("📁 {} moved to {:?}", config.name, destination);
+                    config.destination_path = destination_path.clone();
+                }
+                // Fill in what an earlier import without the password took from the file name
+                if config.source_path.is_empty() {
+                    config.source_path = source_path;
+                }
+                if config.name == file_name {
+                    config.name = name;
+                }
+                config.last_backup_at = config.last_backup_at.max(Some(newest.created_at));
+                config.updated_at = now;
+                config
+            }
+            None => {
+                configs.push(BackupConfig {
+                    id: known_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                    name,
+                    source_path,
+                    destination_path: destination_path.clone(),
+                    schedule: None,
+                    enabled: true,
+                    mode: found.last().expect("groups are never empty").mode(),
+                    encryption_password: None,
+                    backup_type: newest.backup_type.clone(),
+                    created_at: now,
+                    updated_at: now,
+                    last_backup_at: Some(newest.created_at),
+                    last_backup_original_size: None,
+                    last_backup_compressed_size: Some(newest.size),
+                    last_backup_files_count: None,
+                    last_backup_checksum: None,
+                    last_scheduled_run_at: None,
+                    last_run_attempts: Vec::new(),
+                    kdf_strength: None,
+                    recovery: None,
+                    recipients: Vec::new(),
+                    password_source: None,
+                    watch: None,
+                    retention: None,
+                });
+                configs.last_mut().expect("config was just added")
+            }
+        };
+
+        // By file name: the destination may be mounted elsewhere than when it was recorded
+        let recorded: HashSet<String> = history::recorded_backup_paths(data_dir, &config.id)?
+            .iter()
+            .filter_map(|path| Path::new(path).file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+        let mut history_added = 0;
+        for archive in archives.iter().filter(|archive| !recorded.contains(&archive.filename)) {
+            history::append(data_dir, &JobRecord {
+                job_id: retention::archive_id(&archive.filename).to_string(),
+                config_id: config.id.clone(),
+                trigger: JobTrigger::Import,
+                status: BackupStatus::Completed,
+                backup_type: archive.backup_type.clone(),
+                started_at: archive.created_at,
+                completed_at: archive.created_at,
+                duration_secs: 0,
+                original_size: None,
+                compressed_size: Some(archive.size),
+                files_count: None,
+                changed_files_count: None,
+                error_message: None,
+                backup_path: Some(archive.path.clone()),
+                checksum: None,
+            })?;
+            history_added += 1;
+        }
+
+        log::info!(
+            "✅ {} {} ({} archive(s), {} new in history)",
+            if created { "Imported" } else { "Updated" },
+            config.name,
+            archives.len(),
+            history_added
+        );
+        report.configs.push(ImportedConfig {
+            config_id: config.id.clone(),
+            name: config.name.clone(),
+            created,
+            archives,
+            history_added,
+        });
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,453 @@
+/// Headless command line interface
+///
+/// `inlocker <command> ...` runs directly on the backup module without starting
+/// Tauri or opening any window, so it works over SSH and in CI. Each command
+/// prints one JSON document on stdout (logs go to stderr) and exits with
+/// `EXIT_OK`, `EXIT_FAILURE` or `EXIT_USAGE`.
+///
+/// Passwords are never taken from the command line (they would show up in the
+/// process list): encrypted configs use their password source, everything else
+/// reads `INLOCKER_PASSWORD`.
+///
+/// `inlocker --backup <id>` (used by launchd/systemd agents) is not handled here:
+/// it still runs in the app with the scheduled-progress window.
+
+use crate::backup;
+use crate::catalog;
+use crate::chain;
+use crate::history;
+use crate::retention;
+use crate::secrets;
+use crate::types::{AppPreferences, BackupConfig, BackupJob, BackupMode, BackupType, JobRecord, JobTrigger};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+
+/// Environment variable holding the password of encrypted archives
+pub const PASSWORD_ENV: &str = "INLOCKER_PASSWORD";
+
+/// Environment variable overriding the app data directory (configs, manifests)
+pub const DATA_DIR_ENV: &str = "INLOCKER_DATA_DIR";
+
+/// Bundle identifier (tauri.conf.json); Tauri names the app data directory after it
+const APP_IDENTIFIER: &str = "com.inlocker.backup";
+
+const USAGE: &str = "\
+Usage: inlocker <command> [arguments]
+
+Commands:
+  configs                                   List backup configurations
+  backup <config_id>                        Run a backup of a configuration
+  list <config_id>                          List the archives of a configuration
+  restore <archive> <destination> [--checksum <sha256>]
+                                            Restore an archive into a folder
+  restore-at <config_id> <time> <destination>
+                                            Restore a configuration as it was at <time>
+                                            (Unix seconds or local YYYY-MM-DDTHH:MM[:SS])
+  verify <archive> [--checksum <sha256>]    Check an archive without extracting it
+  prune <config_id> [--dry-run]             Delete archives outside the retention policy
+  import <destination> [--repoint]          Add the backups found in a folder to the configurations
+                                            (--repoint moves known configs found there to it)
+  help                                      Show this help
+
+Encrypted archives read their password from INLOCKER_PASSWORD (backups of
+configs with a password source fetch it from there instead).
+Output is JSON on stdout. Exit codes: 0 success, 1 failure, 2 usage error.";
+
+/// A headless command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Configs,
+    Backup { config_id: String },
+    List { config_id: String },
+    Restore { archive: PathBuf, destination: PathBuf, checksum: Option<String> },
+    RestoreAt { config_id: String, timestamp: i64, destination: PathBuf },
+    Verify { archive: PathBuf, checksum: Option<String> },
+    Prune { config_id: String, dry_run: bool },
+    Import { destination: PathBuf, repoint: bool },
+    Help,
+}
+
+/// Parse the process arguments (including the program name)
+///
+/// Returns None when the arguments aren't a headless command, so the app starts
+/// as usual (no arguments, `--backup <id>`, or flags added by the OS).
+pub fn parse(args: &[String]) -> Option<Result<Command, String>> {
+    let name = args.get(1)?.as_str();
+    let rest = &args[2..];
+
+    let command = match name {
+        "configs" => expect_args(name, rest, 0).map(|_| Command::Configs),
+        "backup" => expect_args(name, rest, 1).map(|a| Command::Backup { config_id: a[0].clone() }),
+        "list" => expect_args(name, rest, 1).map(|a| Command::List { config_id: a[0].clone() }),
+        "restore" => split_checksum(rest).and_then(|(positional, checksum)| {
+            expect_args(name, &positional, 2).map(|a| Command::Restore {
+                archive: PathBuf::from(&a[0]),
+                destination: PathBuf::from(&a[1]),
+                checksum,
+            })
+        }),
+        "restore-at" => expect_args(name, rest, 3).and_then(|a| {
+            Ok(Command::RestoreAt {
+                config_id: a[0].clone(),
+                timestamp: parse_time(&a[1])?,
+                destination: PathBuf::from(&a[2]),
+            })
+        }),
+        "verify" => split_checksum(rest).and_then(|(positional, checksum)| {
+            expect_args(name, &positional, 1).map(|a| Command::Verify { archive: PathBuf::from(&a[0]), checksum })
+        }),
+        "prune" => {
+            let dry_run = rest.iter().any(|a| a == "--dry-run");
+            let positional: Vec<String> = rest.iter().filter(|a| *a != "--dry-run").cloned().collect();
+            match positional.iter().find(|a| a.starts_with("--")) {
+                Some(flag) => Err(format!("Unknown option: {}", flag)),
+                None => expect_args(name, &positional, 1)
+                    .map(|a| Command::Prune { config_id: a[0].clone(), dry_run }),
+            }
+        }
+        "import" => {
+            let repoint = rest.iter().any(|a| a == "--repoint");
+            let positional: Vec<String> = rest.iter().filter(|a| *a != "--repoint").cloned().collect();
+            match positional.iter().find(|a| a.starts_with("--")) {
+                Some(flag) => Err(format!("Unknown option: {}", flag)),
+                None => expect_args(name, &positional, 1)
+                    .map(|a| Command::Import { destination: PathBuf::from(&a[0]), repoint }),
+            }
+        }
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// Check the number of positional arguments of a command
+fn expect_args<'a>(command: &str, args: &'a [String], count: usize) -> Result<&'a [String], String> {
+    if args.len() == count {
+        Ok(args)
+    } else {
+        Err(format!("'{}' expects {} argument(s), got {}", command, count, args.len()))
+    }
+}
+
+/// Parse a point in time: Unix seconds or a local date and time
+fn parse_time(value: &str) -> Result<i64, String> {
+    if let Ok(timestamp) = value.parse::<i64>() {
+        return Ok(timestamp);
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|local| local.and_local_timezone(chrono::Local).earliest())
+        .map(|time| time.timestamp())
+        .ok_or_else(|| format!("Invalid time: {} (use Unix seconds or YYYY-MM-DDTHH:MM[:SS])", value))
+}
+
+/// Separate an optional `--checksum <sha256>` from the positional arguments
+fn split_checksum(args: &[String]) -> Result<(Vec<String>, Option<String>), String> {
+    let mut positional = Vec::new();
+    let mut checksum = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--checksum" => checksum = Some(iter.next().ok_or("--checksum requires a value")?.clone()),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    Ok((positional, checksum))
+}
+
+/// Run a parse result and return the process exit code
+pub fn run(parsed: Result<Command, String>) -> i32 {
+    let command = match parsed {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let result = match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            return EXIT_OK;
+        }
+        Command::Configs => load_configs().and_then(to_json),
+        Command::Backup { config_id } => run_backup(&config_id).and_then(to_json),
+        Command::List { config_id } => find_config(&config_id)
+            .and_then(|config| backup::list_backups(Path::new(&config.destination_path)))
+            .and_then(to_json),
+        Command::Restore { archive, destination, checksum } => {
+            let password = password_from_env();
+            backup::restore_backup(&archive, &destination, checksum, password.as_ref().map(|p| p.as_str()), None, None)
+                .and_then(to_json)
+        }
+        Command::RestoreAt { config_id, timestamp, destination } => {
+            let password = password_from_env();
+            find_config(&config_id)
+                .and_then(|config| {
+                    chain::restore_point_in_time(
+                        &config,
+                        timestamp,
+                        &destination,
+                        password.as_ref().map(|p| p.as_str()),
+                        None,
+                        None,
+                    )
+                })
+                .and_then(to_json)
+        }
+        Command::Verify { archive, checksum } => {
+            let password = password_from_env();
+            backup::verify_archive(&archive, checksum.as_deref(), password.as_ref().map(|p| p.as_str()))
+                .and_then(to_json)
+        }
+        Command::Prune { config_id, dry_run } => run_prune(&config_id, dry_run).and_then(to_json),
+        Command::Import { destination, repoint } => run_import(&destination, repoint).and_then(to_json),
+    };
+
+    match result {
+        Ok(json) => {
+            println!("{}", json);
+            EXIT_OK
+        }
+        Err(e) => {
+            log::error!("❌ {}", e);
+            println!("{}", serde_json::json!({ "error": e }));
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: T) -> Result<String, String> {
+    serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize output: {}", e))
+}
+
+fn password_from_env() -> Option<Zeroizing<String>> {
+    std::env::var(PASSWORD_ENV).ok().filter(|p| !p.is_empty()).map(Zeroizing::new)
+}
+
+/// App data directory, resolved the way Tauri does for the app (`INLOCKER_DATA_DIR` overrides)
+pub fn app_data_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let data_dir = if cfg!(target_os = "macos") {
+        home_dir()?.join("Library/Application Support")
+    } else if cfg!(target_os = "windows") {
+        PathBuf::from(std::env::var("APPDATA").map_err(|e| format!("Failed to get APPDATA: {}", e))?)
+    } else {
+        match std::env::var("XDG_DATA_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home_dir()?.join(".local/share"),
+        }
+    };
+    Ok(data_dir.join(APP_IDENTIFIER))
+}
+
+fn home_dir() -> Result<PathBuf, String> {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .map_err(|e| format!("Failed to get HOME: {}", e))
+}
+
+fn configs_path() -> Result<PathBuf, String> {
+    Ok(app_data_dir()?.join("configs.json"))
+}
+
+fn load_configs() -> Result<Vec<BackupConfig>, String> {
+    let path = configs_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = fs::read_to_string(&path).map_err(|e| format!("Failed to read configs: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse configs: {}", e))
+}
+
+fn save_configs(configs: &[BackupConfig]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(configs).map_err(|e| format!("Failed to serialize configs: {}", e))?;
+    fs::write(configs_path()?, json).map_err(|e| format!("Failed to write configs: {}", e))
+}
+
+fn find_config(config_id: &str) -> Result<BackupConfig, String> {
+    load_configs()?
+        .into_iter()
+        .find(|c| c.id == config_id)
+        .ok_or_else(|| format!("Config not found: {}", config_id))
+}
+
+/// Back up a config and record the run in the job history and on the config
+fn run_backup(config_id: &str) -> Result<BackupJob, String> {
+    let config = find_config(config_id)?;
+    let data_dir = app_data_dir()?;
+    log::info!("🚀 Headless backup of {}", config.name);
+
+    let started_at = chrono::Utc::now().timestamp();
+    let result = perform_backup(&config, &data_dir);
+    let record = match &result {
+        Ok(job) => JobRecord::completed(&config.id, JobTrigger::Cli, job),
+        Err(e) => JobRecord::failed(&config.id, JobTrigger::Cli, &config.backup_type, started_at, e, false),
+    };
+    if let Err(e) = history::append(&data_dir, &record) {
+        log::warn!("⚠️  Failed to record job {} in history: {}", record.job_id, e);
+    }
+    let job = result?;
+
+    // Record the result so the app shows it (configs may have changed during the backup)
+    let mut configs = load_configs()?;
+    if let Some(cfg) = configs.iter_mut().find(|c| c.id == config.id) {
+        cfg.last_backup_at = Some(job.completed_at.unwrap_or_else(|| chrono::Utc::now().timestamp()));
+        cfg.last_backup_original_size = job.original_size;
+        cfg.last_backup_compressed_size = job.compressed_size;
+        cfg.last_backup_files_count = job.files_count;
+        cfg.last_backup_checksum = job.checksum.clone();
+        save_configs(&configs)?;
+    }
+
+    Ok(job)
+}
+
+/// Back up a config like a scheduled run does: previous manifest, backup, new manifest
+fn perform_backup(config: &BackupConfig, data_dir: &Path) -> Result<BackupJob, String> {
+    let password = match (&config.mode, &config.password_source) {
+        (BackupMode::Encrypted, Some(source)) => {
+            log::info!("🔐 Fetching password from {}", source.describe());
+            Some(secrets::resolve_secret(source)?)
+        }
+        (BackupMode::Encrypted, None) => password_from_env(),
+        _ => None,
+    };
+    let password = password.as_ref().map(|p| p.as_str());
+    backup::check_incremental_supported(&config.mode, &config.backup_type, password)?;
+
+    let manifest_path = data_dir.join(format!("manifest_{}.json", config.id));
+    let previous_manifest = if manifest_path.exists() && config.backup_type == BackupType::Incremental {
+        backup::load_manifest(&manifest_path, password)
+            .map_err(|e| log::warn!("Previous manifest unavailable, running a full backup: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    let options = backup::BackupOptions {
+        kdf_strength: config.kdf_strength.unwrap_or_else(|| {
+            fs::read_to_string(data_dir.join("preferences.json"))
+                .ok()
+                .and_then(|json| serde_json::from_str::<AppPreferences>(&json).ok())
+                .unwrap_or_default()
+                .kdf_strength
+        }),
+        recovery: config.recovery.clone(),
+        recipients: config.recipients.clone(),
+    };
+
+    let source_path = Path::new(&config.source_path);
+    let mut job = backup::compress_folder_with_options(
+        &config.id,
+        &config.name,
+        source_path,
+        Path::new(&config.destination_path),
+        &config.backup_type,
+        &config.mode,
+        previous_manifest.as_ref(),
+        None,
+        password,
+        None,
+        &options,
+    )?;
+    job.config_id = config.id.clone();
+
+    if let Err(e) = backup::update_manifest(&manifest_path, &config.id, source_path, &config.mode, password, &options) {
+        log::error!("Failed to save manifest: {}", e);
+    }
+
+    retention::prune_after_backup(config);
+
+    Ok(job)
+}
+
+/// Apply a config's retention policy (`dry_run` only reports)
+fn run_prune(config_id: &str, dry_run: bool) -> Result<retention::PruneReport, String> {
+    let config = find_config(config_id)?;
+    let policy = config
+        .retention
+        .as_ref()
+        .ok_or("No retention policy configured for this backup")?;
+    retention::prune(&config, policy, dry_run)
+}
+
+/// Adopt the backups in a destination and save the updated configs
+fn run_import(destination: &Path, repoint: bool) -> Result<catalog::ImportReport, String> {
+    let mut configs = load_configs()?;
+    let data_dir = app_data_dir()?;
+    fs::create_dir_all(&data_dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let password = password_from_env();
+    let password = password.as_ref().map(|p| p.as_str());
+    let report = catalog::import_destination(destination, &mut configs, &data_dir, password, repoint)?;
+    save_configs(&configs)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        std::iter::once("inlocker").chain(list.iter().copied()).map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&args(&["configs"])), Some(Ok(Command::Configs)));
+        assert_eq!(parse(&args(&["backup", "abc"])), Some(Ok(Command::Backup { config_id: "abc".into() })));
+        assert_eq!(
+            parse(&args(&["restore", "a.tar.zst", "/tmp/out", "--checksum", "ff"])),
+            Some(Ok(Command::Restore {
+                archive: "a.tar.zst".into(),
+                destination: "/tmp/out".into(),
+                checksum: Some("ff".into()),
+            }))
+        );
+        assert_eq!(
+            parse(&args(&["verify", "a.tar.zst"])),
+            Some(Ok(Command::Verify { archive: "a.tar.zst".into(), checksum: None }))
+        );
+
+        assert_eq!(
+            parse(&args(&["prune", "abc", "--dry-run"])),
+            Some(Ok(Command::Prune { config_id: "abc".into(), dry_run: true }))
+        );
+
+        assert_eq!(
+            parse(&args(&["restore-at", "abc", "1717200000", "/tmp/out"])),
+            Some(Ok(Command::RestoreAt { config_id: "abc".into(), timestamp: 1_717_200_000, destination: "/tmp/out".into() }))
+        );
+        assert!(matches!(parse(&args(&["restore-at", "abc", "2024-06-01T02:30", "/tmp/out"])), Some(Ok(_))));
+        assert!(matches!(parse(&args(&["restore-at", "abc", "yesterday", "/tmp/out"])), Some(Err(_))));
+
+        assert_eq!(
+            parse(&args(&["import", "/Volumes/Backup"])),
+            Some(Ok(Command::Import { destination: "/Volumes/Backup".into(), repoint: false }))
+        );
+        assert_eq!(
+            parse(&args(&["import", "--repoint", "/Volumes/Backup"])),
+            Some(Ok(Command::Import { destination: "/Volumes/Backup".into(), repoint: true }))
+        );
+
+        assert!(matches!(parse(&args(&["backup"])), Some(Err(_))));
+        assert!(matches!(parse(&args(&["verify", "a", "--force"])), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_gui_arguments_are_not_commands() {
+        assert_eq!(parse(&args(&[])), None);
+        assert_eq!(parse(&args(&["--backup", "abc"])), None);
+        assert_eq!(parse(&args(&["-psn_0_12345"])), None);
+    }
+}
@@ -1,7 +1,17 @@
 use crate::backup;
-use crate::launchd;
+use crate::browse::{self, EntryPage, EntryQuery};
+use crate::catalog::{self, ImportReport};
+use crate::chain;
+use crate::history;
+use crate::retention::{self, PruneReport};
+use crate::cron::CronSchedule;
+use crate::schedule_backend::platform_backend;
 use crate::scheduler::SchedulerState;
-use crate::types::{AppPreferences, BackupConfig, BackupManifest, BackupResult, BackupType, ScheduleDiagnostics};
+use crate::selection::RestoreSelection;
+use crate::types::{
+    AppPreferences, BackupConfig, BackupJob, BackupMode, BackupResult, BackupType, HistoryPage, HistoryQuery,
+    JobRecord, JobTrigger, KdfStrength, RecipientKeyPair, RunAttempt, SavedConfig, ScheduleDiagnostics,
+};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -14,6 +24,8 @@ pub struct AppState {
     pub configs: Mutex<Vec<BackupConfig>>,
     /// Cancellation flags for running backups: config_id -> cancel_flag
     pub cancel_flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    /// File-system watchers of configs with change-triggered backups: config_id -> watcher
+    pub watchers: Mutex<HashMap<String, crate::watcher::ConfigWatcher>>,
 }
 
 impl Default for AppState {
@@ -21,6 +33,7 @@ impl Default for AppState {
         Self {
             configs: Mutex::new(Vec::new()),
             cancel_flags: Mutex::new(HashMap::new()),
+            watchers: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -53,6 +66,81 @@ fn get_preferences_path(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(app_data_dir.join("preferences.json"))
 }
 
+/// Recompute `schedule.next_run` (Unix seconds) from the cron expression in local time
+///
+/// Disabled or missing schedules get no next run; an invalid expression is an error.
+pub fn refresh_next_run(config: &mut BackupConfig) -> Result<(), String> {
+    if let Some(schedule) = config.schedule.as_mut() {
+        schedule.next_run = if schedule.enabled {
+            CronSchedule::parse(&schedule.cron_expression)
+                .map_err(|e| format!("Invalid schedule: {}", e))?
+                .next_run(&chrono::Local::now())
+                .map(|run| run.timestamp())
+        } else {
+            None
+        };
+    }
+    Ok(())
+}
+
+/// Record a scheduled or catch-up backup (its attempts, and its result when
+/// `job` is set) and persist the configs
+///
+/// Also works headless (agent-triggered runs), where the UI hasn't loaded the
+/// configs into AppState yet: they are read from disk first.
+pub fn record_scheduled_run(
+    app: &AppHandle,
+    config_id: &str,
+    job: Option<&BackupJob>,
+    attempts: Vec<RunAttempt>,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
+    let config_path = get_config_path(app)?;
+
+    if !configs.iter().any(|c| c.id == config_id) && config_path.exists() {
+        let json = fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read configs: {}", e))?;
+        *configs = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse configs: {}", e))?;
+    }
+
+    let cfg = configs
+        .iter_mut()
+        .find(|c| c.id == config_id)
+        .ok_or("Config not found")?;
+    cfg.last_run_attempts = attempts;
+    if let Some(job) = job {
+        let completed_at = job.completed_at.unwrap_or_else(|| chrono::Utc::now().timestamp());
+        cfg.last_scheduled_run_at = Some(completed_at);
+        cfg.last_backup_at = Some(completed_at);
+        cfg.last_backup_original_size = job.original_size;
+        cfg.last_backup_compressed_size = job.compressed_size;
+        cfg.last_backup_files_count = job.files_count;
+        cfg.last_backup_checksum = job.checksum.clone();
+    }
+    refresh_next_run(cfg)?;
+
+    let json = serde_json::to_string_pretty(&*configs)
+        .map_err(|e| format!("Failed to serialize configs: {}", e))?;
+    fs::write(&config_path, json)
+        .map_err(|e| format!("Failed to write configs: {}", e))
+}
+
+/// KDF strength for a config: per-config override first, then the app preference
+pub fn resolve_kdf_strength(app: &AppHandle, config: &BackupConfig) -> KdfStrength {
+    if let Some(strength) = config.kdf_strength {
+        return strength;
+    }
+
+    get_preferences_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<AppPreferences>(&json).ok())
+        .map(|prefs| prefs.kdf_strength)
+        .unwrap_or_default()
+}
+
 /// Open folder picker dialog and return selected path
 #[tauri::command]
 pub async fn select_folder(app: AppHandle) -> Result<Option<String>, String> {
@@ -79,14 +167,46 @@ pub async fn select_file(app: AppHandle) -> Result<Option<String>, String> {
 }
 
 /// Save a backup configuration
+///
+/// When a config is created as (or switched to) Encrypted with its password,
+/// its recovery key is generated and returned once in `recovery_key`.
 #[tauri::command]
 pub async fn save_config(
     app: AppHandle,
     state: State<'_, AppState>,
     config: BackupConfig,
-) -> Result<BackupConfig, String> {
+) -> Result<SavedConfig, String> {
+    for recipient in &config.recipients {
+        crate::crypto::parse_public_key(recipient).map_err(|e| format!("Invalid recipient key: {}", e))?;
+    }
+
+    let mut config = config;
+    refresh_next_run(&mut config)?;
+
     let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
 
+    // The recovery escrow is managed by the backend; keep it if the UI didn't send it
+    if config.recovery.is_none() {
+        config.recovery = configs.iter().find(|c| c.id == config.id).and_then(|c| c.recovery.clone());
+    }
+
+    let mut recovery_key = None;
+    if let (BackupMode::Encrypted, None, Some(password)) =
+        (&config.mode, &config.recovery, config.encryption_password.as_deref())
+    {
+        crate::crypto::validate_password_strength(password)?;
+        let key = crate::crypto::generate_recovery_key();
+        config.recovery = Some(crate::crypto::setup_recovery(
+            &key,
+            password,
+            resolve_kdf_strength(&app, &config),
+            config.id.as_bytes(),
+            chrono::Utc::now().timestamp(),
+        )?);
+        log::info!("🔑 Recovery key created for config {}", config.id);
+        recovery_key = Some(key.as_str().to_string());
+    }
+
     // Check if config already exists
     if let Some(existing) = configs.iter_mut().find(|c| c.id == config.id) {
         *existing = config.clone();
@@ -101,7 +221,11 @@ pub async fn save_config(
     fs::write(&config_path, json)
         .map_err(|e| format!("Failed to write configs: {}", e))?;
 
-    Ok(config)
+    let all_configs = configs.clone();
+    drop(configs);
+    crate::watcher::sync_watchers(&app, &all_configs)?;
+
+    Ok(SavedConfig { config, recovery_key })
 }
 
 /// Load all backup configurations
@@ -116,12 +240,22 @@ pub async fn load_configs(
     if config_path.exists() {
         let json = fs::read_to_string(&config_path)
             .map_err(|e| format!("Failed to read configs: {}", e))?;
-        let loaded_configs: Vec<BackupConfig> = serde_json::from_str(&json)
+        let mut loaded_configs: Vec<BackupConfig> = serde_json::from_str(&json)
             .map_err(|e| format!("Failed to parse configs: {}", e))?;
 
+        // Stored next runs go stale once they pass (scheduled runs happen outside the app)
+        for config in loaded_configs.iter_mut() {
+            if let Err(e) = refresh_next_run(config) {
+                log::warn!("⚠️  Config {}: {}", config.id, e);
+            }
+        }
+
         // Update state with loaded configs
-        let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
-        *configs = loaded_configs.clone();
+        {
+            let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
+            *configs = loaded_configs.clone();
+        }
+        crate::watcher::sync_watchers(&app, &loaded_configs)?;
 
         Ok(loaded_configs)
     } else {
@@ -138,7 +272,7 @@ pub async fn delete_config(
     scheduler_state: State<'_, SchedulerState>,
     config_id: String,
 ) -> Result<bool, String> {
-    let (was_deleted, config_name) = {
+    let (was_deleted, config_name, remaining_configs) = {
         let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
 
         // Save config name before deletion for wrapper script cleanup
@@ -158,16 +292,20 @@ pub async fn delete_config(
                 .map_err(|e| format!("Failed to write configs: {}", e))?;
         }
 
-        (deleted, config_name)
+        (deleted, config_name, configs.clone())
     }; // Lock is released here
 
     if was_deleted {
-        // Clean up scheduler and launchd
+        crate::watcher::sync_watchers(&app, &remaining_configs)?;
+
+        // Clean up scheduler and OS schedule
         // Unregister from in-app scheduler (ignore errors if not scheduled)
         let _ = scheduler_state.unregister_schedule(&config_id).await;
 
-        // Uninstall launchd agent (ignore errors if not installed)
-        let _ = launchd::uninstall_launch_agent(&config_id, config_name.as_deref());
+        // Uninstall launchd agent / systemd timer (ignore errors if not installed)
+        if let Ok(backend) = platform_backend() {
+            let _ = backend.uninstall(&config_id, config_name.as_deref());
+        }
 
         log::info!("Deleted config and cleaned up schedules: {}", config_id);
     }
@@ -183,34 +321,78 @@ pub async fn run_backup_now(
     config_id: String,
     password: Option<String>,
 ) -> Result<BackupResult, String> {
-    // Create cancellation flag for this backup
-    let cancel_flag = Arc::new(AtomicBool::new(false));
-    {
-        let mut flags = state.cancel_flags.lock().map_err(|e| e.to_string())?;
-        flags.insert(config_id.clone(), Arc::clone(&cancel_flag));
-    }
+    run_backup_with_type(app, state, config_id, password, None, JobTrigger::Manual).await
+}
+
+/// Run a backup, optionally overriding the config's backup type
+///
+/// Shared by `run_backup_now`, the in-app scheduler and change-triggered backups
+/// (see watcher.rs); every run is recorded in the job history under `trigger`.
+/// Only one backup per config runs at a time: a second request fails while
+/// the first still owns its cancel flag.
+pub async fn run_backup_with_type(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config_id: String,
+    password: Option<String>,
+    backup_type: Option<BackupType>,
+    trigger: JobTrigger,
+) -> Result<BackupResult, String> {
+    let started_at = chrono::Utc::now().timestamp();
 
     // Get the config
     let configs = state.configs.lock().map_err(|e| e.to_string())?;
-    let config = configs
+    let mut config = configs
         .iter()
         .find(|c| c.id == config_id)
         .ok_or("Config not found")?
         .clone();
     drop(configs); // Release lock
+    if let Some(backup_type) = backup_type {
+        config.backup_type = backup_type;
+    }
 
     // Get paths first
     let source_path = Path::new(&config.source_path);
     let dest_path = Path::new(&config.destination_path);
+    let manifest_path = get_manifest_path(&app, &config_id)?;
+
+    // Use password from parameter (provided by UI prompt), else the config's secret provider
+    // Password is NEVER saved in config for security reasons
+    let password = match (password, &config.password_source) {
+        (Some(p), _) => Some(zeroize::Zeroizing::new(p)),
+        (None, Some(source)) if config.mode == BackupMode::Encrypted => match crate::secrets::resolve_secret(source) {
+            Ok(secret) => Some(secret),
+            Err(e) => {
+                record_history(&app, &JobRecord::failed(&config_id, trigger, &config.backup_type, started_at, &e, false));
+                return Err(e);
+            }
+        },
+        (None, _) => None,
+    };
+    let password_ref = password.as_ref().map(|p| p.as_str());
+    if let Err(e) = backup::check_incremental_supported(&config.mode, &config.backup_type, password_ref) {
+        record_history(&app, &JobRecord::failed(&config_id, trigger, &config.backup_type, started_at, &e, false));
+        return Err(e);
+    }
+
+    // Create cancellation flag for this backup (nothing below may return early until it is removed)
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    {
+        let mut flags = state.cancel_flags.lock().map_err(|e| e.to_string())?;
+        if flags.contains_key(&config_id) {
+            return Err("A backup is already running for this configuration".to_string());
+        }
+        flags.insert(config_id.clone(), Arc::clone(&cancel_flag));
+    }
 
     // Load previous manifest for incremental backup
     // BUT only if physical backup files actually exist on disk
-    let manifest_path = get_manifest_path(&app, &config_id)?;
     let previous_manifest = if manifest_path.exists() && config.backup_type == BackupType::Incremental {
-        // First load manifest
-        let loaded_manifest = fs::read_to_string(&manifest_path)
-            .ok()
-            .and_then(|j| serde_json::from_str::<BackupManifest>(&j).ok());
+        // First load manifest (encrypted configs need the password to read it)
+        let loaded_manifest = backup::load_manifest(&manifest_path, password_ref)
+            .map_err(|e| log::warn!("⚠️  {}", e))
+            .ok();
 
         if let Some(manifest) = loaded_manifest {
             // Verify physical backup exists and matches manifest
@@ -238,12 +420,14 @@ pub async fn run_backup_now(
         None
     };
 
-    // Use password from parameter (provided by UI prompt)
-    // Password is NEVER saved in config for security reasons
-    let password_ref = password.as_deref();
+    let options = backup::BackupOptions {
+        kdf_strength: resolve_kdf_strength(&app, &config),
+        recovery: config.recovery.clone(),
+        recipients: config.recipients.clone(),
+    };
 
     // Perform backup with cancellation support
-    let backup_result = backup::compress_folder(
+    let backup_result = backup::compress_folder_with_options(
         &config_id,
         &config.name,
         source_path,
@@ -254,6 +438,7 @@ pub async fn run_backup_now(
         Some(&app),
         password_ref,
         Some(Arc::clone(&cancel_flag)),
+        &options,
     );
 
     // Clean up cancellation flag
@@ -265,6 +450,7 @@ pub async fn run_backup_now(
     match backup_result {
         Ok(mut job) => {
             job.config_id = config_id.clone();
+            record_history(&app, &JobRecord::completed(&config_id, trigger, &job));
 
             // FIRST: Emit completed event for progress window auto-close
             // This must happen BEFORE the slow manifest scan to avoid UI appearing stuck
@@ -319,30 +505,26 @@ pub async fn run_backup_now(
             let manifest_config_id = config_id.clone();
             let manifest_source = source_path.to_path_buf();
             let manifest_dest = manifest_path.clone();
+            let manifest_mode = config.mode.clone();
+            let manifest_password = password.clone();
+            let manifest_options = options.clone();
             tokio::spawn(async move {
                 log::info!("📋 Building manifest in background for {}", manifest_config_id);
-                match backup::scan_all_files(&manifest_source) {
-                    Ok((all_files, _)) => {
-                        match backup::build_manifest(&manifest_config_id, &all_files, &manifest_source) {
-                            Ok(new_manifest) => {
-                                match serde_json::to_string_pretty(&new_manifest) {
-                                    Ok(manifest_json) => {
-                                        if let Err(e) = fs::write(&manifest_dest, manifest_json) {
-                                            log::error!("Failed to save manifest: {}", e);
-                                        } else {
-                                            log::info!("✅ Manifest saved in background");
-                                        }
-                                    }
-                                    Err(e) => log::error!("Failed to serialize manifest: {}", e),
-                                }
-                            }
-                            Err(e) => log::error!("Failed to build manifest: {}", e),
-                        }
-                    }
-                    Err(e) => log::error!("Failed to scan files for manifest: {}", e),
+                match backup::update_manifest(
+                    &manifest_dest,
+                    &manifest_config_id,
+                    &manifest_source,
+                    &manifest_mode,
+                    manifest_password.as_ref().map(|p| p.as_str()),
+                    &manifest_options,
+                ) {
+                    Ok(()) => log::info!("✅ Manifest saved in background"),
+                    Err(e) => log::error!("Failed to save manifest: {}", e),
                 }
             });
 
+            retention::prune_after_backup(&config);
+
             Ok(BackupResult {
                 success: true,
                 message: format!(
@@ -355,14 +537,62 @@ pub async fn run_backup_now(
                 job: Some(job),
             })
         }
-        Err(e) => Ok(BackupResult {
-            success: false,
-            message: format!("Backup failed: {}", e),
-            job: None,
-        }),
+        Err(e) => {
+            let cancelled = cancel_flag.load(std::sync::atomic::Ordering::SeqCst);
+            record_history(&app, &JobRecord::failed(&config_id, trigger, &config.backup_type, started_at, &e, cancelled));
+            Ok(BackupResult {
+                success: false,
+                message: format!("Backup failed: {}", e),
+                job: None,
+            })
+        }
+    }
+}
+
+/// Append a run to the job history (a history write failure never fails the backup)
+pub fn record_history(app: &AppHandle, record: &JobRecord) {
+    let result = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+        .and_then(|dir| history::append(&dir, record));
+    if let Err(e) = result {
+        log::warn!("⚠️  Failed to record job {} in history: {}", record.job_id, e);
     }
 }
 
+/// Apply a config's retention policy to its destination (`dry_run` only reports)
+#[tauri::command]
+pub async fn prune_backups(
+    state: State<'_, AppState>,
+    config_id: String,
+    dry_run: bool,
+) -> Result<PruneReport, String> {
+    let config = state
+        .configs
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .find(|c| c.id == config_id)
+        .cloned()
+        .ok_or("Config not found")?;
+    let policy = config
+        .retention
+        .as_ref()
+        .ok_or("No retention policy configured for this backup")?;
+    retention::prune(&config, policy, dry_run)
+}
+
+/// Query the job history of one or all configs (newest first, paged)
+#[tauri::command]
+pub async fn query_job_history(app: AppHandle, query: HistoryQuery) -> Result<HistoryPage, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    history::query(&app_data_dir, &query)
+}
+
 /// Get the path to the manifest file for a config
 fn get_manifest_path(app: &AppHandle, config_id: &str) -> Result<PathBuf, String> {
     let app_data_dir = app
@@ -379,7 +609,7 @@ fn get_manifest_path(app: &AppHandle, config_id: &str) -> Result<PathBuf, String
 /// Register a scheduled backup
 #[tauri::command]
 pub async fn register_schedule(
-    _app: AppHandle,
+    app: AppHandle,
     state: State<'_, AppState>,
     scheduler_state: State<'_, SchedulerState>,
     config_id: String,
@@ -400,26 +630,71 @@ pub async fn register_schedule(
         .as_ref()
         .ok_or("No schedule configured for this backup")?;
 
-    // NOTE: In-app scheduler removed - using only launchd for system-level scheduling
-    // This provides independent scheduling that works even when the app is closed
-    let _ = scheduler_state; // Suppress unused warning
-
-    // Install launchd agent for system-level scheduling
+    // Prefer the OS service manager: it runs schedules even when the app is closed.
+    // The in-app scheduler is used when the config opts out or no agent can be installed;
+    // only one of the two is ever registered for a config.
     let cron_expr = &schedule.cron_expression;
+    let os_install = if schedule.in_app {
+        if let Ok(backend) = platform_backend() {
+            let _ = backend.uninstall(&config_id, Some(&config.name));
+        }
+        Err("in-app scheduling selected".to_string())
+    } else {
+        platform_backend().and_then(|backend| {
+            // Get app executable path (handles both dev and production)
+            let app_path = backend.executable_path()?;
+            backend.install(&config_id, &config.name, cron_expr, &app_path)?;
+            Ok(backend.name())
+        })
+    };
 
-    // Get app executable path (handles both dev and production)
-    let app_path = launchd::get_executable_path()?;
+    let scheduled_by = match os_install {
+        Ok(backend_name) => {
+            scheduler_state.unregister_schedule(&config_id).await?;
+            backend_name
+        }
+        Err(reason) => {
+            if !schedule.in_app {
+                log::warn!("⚠️  OS scheduling unavailable for config {} ({}); using the in-app scheduler", config_id, reason);
+            }
+            scheduler_state.register_schedule(app.clone(), config.clone()).await?;
+            "in-app scheduler"
+        }
+    };
 
-    launchd::install_launch_agent(&config_id, &config.name, cron_expr, &app_path)?;
+    // Persist the next fire time so the UI and diagnostics can show it
+    {
+        let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
+        if let Some(cfg) = configs.iter_mut().find(|c| c.id == config_id) {
+            refresh_next_run(cfg)?;
+        }
+        let config_path = get_config_path(&app)?;
+        let json = serde_json::to_string_pretty(&*configs)
+            .map_err(|e| format!("Failed to serialize configs: {}", e))?;
+        fs::write(&config_path, json)
+            .map_err(|e| format!("Failed to write configs: {}", e))?;
+    }
 
     log::info!(
-        "Registered schedule for config {} (launchd)",
-        config_id
+        "Registered schedule for config {} ({})",
+        config_id,
+        scheduled_by
     );
 
     Ok(true)
 }
 
+/// Upcoming fire times (Unix seconds) of a cron expression, in local time
+#[tauri::command]
+pub async fn preview_schedule(cron_expression: String, count: Option<usize>) -> Result<Vec<i64>, String> {
+    let schedule = CronSchedule::parse(&cron_expression)?;
+    Ok(schedule
+        .next_runs(&chrono::Local::now(), count.unwrap_or(5).min(50))
+        .iter()
+        .map(|run| run.timestamp())
+        .collect())
+}
+
 /// Unregister a scheduled backup
 #[tauri::command]
 pub async fn unregister_schedule(
@@ -427,7 +702,7 @@ pub async fn unregister_schedule(
     state: State<'_, AppState>,
     config_id: String,
 ) -> Result<bool, String> {
-    let _ = scheduler_state; // Suppress unused warning
+    scheduler_state.unregister_schedule(&config_id).await?;
 
     // Get config name for wrapper script deletion
     let config_name = {
@@ -435,12 +710,14 @@ pub async fn unregister_schedule(
         configs.iter().find(|c| c.id == config_id).map(|c| c.name.clone())
     };
 
-    // Uninstall launchd agent
-    launchd::uninstall_launch_agent(&config_id, config_name.as_deref())?;
+    // Uninstall launchd agent / systemd timer
+    let backend = platform_backend()?;
+    backend.uninstall(&config_id, config_name.as_deref())?;
 
     log::info!(
-        "Unregistered schedule for config {} (launchd)",
-        config_id
+        "Unregistered schedule for config {} ({})",
+        config_id,
+        backend.name()
     );
 
     Ok(true)
@@ -452,13 +729,20 @@ pub async fn check_schedule_status(
     scheduler_state: State<'_, SchedulerState>,
     config_id: String,
 ) -> Result<bool, String> {
-    let _ = scheduler_state; // Suppress unused warning
-
-    // Use launchd to check if agent is loaded
-    let is_loaded = launchd::is_agent_loaded(&config_id).unwrap_or(false);
+    // Scheduled by the OS service manager or by the in-app fallback
+    let is_loaded = crate::scheduler::os_agent_active(&config_id)
+        || scheduler_state.is_scheduled(&config_id).await;
     Ok(is_loaded)
 }
 
+/// State of the jobs run by the in-app scheduler
+#[tauri::command]
+pub async fn list_scheduled_jobs(
+    scheduler_state: State<'_, SchedulerState>,
+) -> Result<Vec<crate::types::ScheduledJobInfo>, String> {
+    Ok(scheduler_state.jobs().await)
+}
+
 /// List available backups for a configuration
 #[tauri::command]
 pub async fn list_available_backups(config_id: String, state: State<'_, AppState>) -> Result<Vec<backup::BackupInfo>, String> {
@@ -477,6 +761,9 @@ pub async fn list_available_backups(config_id: String, state: State<'_, AppState
 }
 
 /// Restore a backup to a specified location
+///
+/// `selection` restores only some files or folders, optionally under other
+/// names (see selection.rs); without it the whole backup is restored.
 #[tauri::command]
 pub async fn restore_backup(
     app: AppHandle,
@@ -485,6 +772,7 @@ pub async fn restore_backup(
     restore_destination: String,
     expected_checksum: Option<String>,
     password: Option<String>,
+    selection: Option<RestoreSelection>,
 ) -> Result<backup::RestoreResult, String> {
     let backup_path = Path::new(&backup_file_path);
     let restore_path = Path::new(&restore_destination);
@@ -501,13 +789,14 @@ pub async fn restore_backup(
         flags.insert(format!("restore-{}", backup_file_path), Arc::clone(&cancel_flag));
     }
 
-    let result = backup::restore_backup(
+    let result = backup::restore_backup_with_selection(
         backup_path,
         restore_path,
         expected_checksum,
         password.as_deref(),
         Some(&app),
         Some(Arc::clone(&cancel_flag)),
+        &selection.unwrap_or_default(),
     );
 
     // Clean up cancellation flag
@@ -519,6 +808,259 @@ pub async fn restore_backup(
     result
 }
 
+/// Restore a config's source as it was at `timestamp` (Unix seconds)
+///
+/// Applies the newest archive at or before that time together with the full
+/// backup and incrementals it builds on. Cancel with `cancel_restore`, passing
+/// the config id.
+#[tauri::command]
+pub async fn restore_point_in_time(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config_id: String,
+    timestamp: i64,
+    restore_destination: String,
+    password: Option<String>,
+) -> Result<chain::ChainRestoreResult, String> {
+    let config = {
+        let configs = state.configs.lock().map_err(|e| e.to_string())?;
+        configs
+            .iter()
+            .find(|c| c.id == config_id)
+            .ok_or("Config not found")?
+            .clone()
+    };
+
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    {
+        let mut flags = state.cancel_flags.lock().map_err(|e| e.to_string())?;
+        flags.insert(format!("restore-{}", config_id), Arc::clone(&cancel_flag));
+    }
+
+    let result = chain::restore_point_in_time(
+        &config,
+        timestamp,
+        Path::new(&restore_destination),
+        password.as_deref(),
+        Some(&app),
+        Some(Arc::clone(&cancel_flag)),
+    );
+
+    {
+        let mut flags = state.cancel_flags.lock().map_err(|e| e.to_string())?;
+        flags.remove(&format!("restore-{}", config_id));
+    }
+
+    result
+}
+
+/// List the contents of an archive or Copy-mode folder without extracting it
+///
+/// One directory level (or, with `query.recursive`, every file under
+/// `query.prefix`) per call, one page at a time.
+#[tauri::command]
+pub async fn list_archive_entries(
+    archive_path: String,
+    password: Option<String>,
+    query: EntryQuery,
+) -> Result<EntryPage, String> {
+    browse::list_archive_entries(Path::new(&archive_path), password.as_deref(), &query)
+}
+
+/// Adopt the backups in a destination folder (written on another Mac, or before a reinstall)
+///
+/// Creates or updates one config per backup name found and records the archives
+/// in the job history. Encrypted archives are indexed once `password` is given;
+/// importing again with it fills in what their headers don't tell.
+#[tauri::command]
+pub async fn import_destination(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    destination_path: String,
+    password: Option<String>,
+    repoint: Option<bool>,
+) -> Result<ImportReport, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let config_path = get_config_path(&app)?;
+    let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
+
+    // Configs may not be loaded yet; never overwrite the saved ones with an empty list
+    if configs.is_empty() && config_path.exists() {
+        let json = fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read configs: {}", e))?;
+        *configs = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse configs: {}", e))?;
+    }
+
+    let report = catalog::import_destination(
+        Path::new(&destination_path),
+        &mut configs,
+        &app_data_dir,
+        password.as_deref(),
+        repoint.unwrap_or(false),
+    )?;
+
+    let json = serde_json::to_string_pretty(&*configs)
+        .map_err(|e| format!("Failed to serialize configs: {}", e))?;
+    fs::write(&config_path, json)
+        .map_err(|e| format!("Failed to write configs: {}", e))?;
+
+    Ok(report)
+}
+
+/// Change the password of encrypted backups without re-compressing them
+///
+/// Re-keys `backup_file_path` if given, otherwise every encrypted archive in the
+/// config's destination along with its manifest and recovery escrow (a single
+/// archive leaves those on the old password, which new backups keep using).
+/// Only the wrapped data key in each header is replaced.
+/// `old_password` may be the recovery key.
+#[tauri::command]
+pub async fn rekey_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config_id: String,
+    backup_file_path: Option<String>,
+    old_password: String,
+    new_password: String,
+) -> Result<backup::RekeyReport, String> {
+    crate::crypto::validate_password_strength(&new_password)?;
+
+    let config = {
+        let configs = state.configs.lock().map_err(|e| e.to_string())?;
+        configs
+            .iter()
+            .find(|c| c.id == config_id)
+            .ok_or("Config not found")?
+            .clone()
+    };
+    let kdf_strength = resolve_kdf_strength(&app, &config);
+
+    let manifest_path = get_manifest_path(&app, &config_id)?;
+    let (report, recovery) = backup::rekey_config(
+        &config,
+        backup_file_path.as_deref().map(Path::new),
+        &manifest_path,
+        &old_password,
+        &new_password,
+        kdf_strength,
+    )?;
+
+    // Persist the new escrow; re-keying changes the archive bytes, so keep the stored checksum in sync
+    {
+        let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
+        if let Some(cfg) = configs.iter_mut().find(|c| c.id == config_id) {
+            if recovery.is_some() {
+                cfg.recovery = recovery;
+            }
+            if let Some(result) = report
+                .rekeyed
+                .iter()
+                .find(|r| cfg.last_backup_checksum.as_deref() == Some(r.old_checksum.as_str()))
+            {
+                cfg.last_backup_checksum = Some(result.checksum.clone());
+            }
+        }
+
+        let config_path = get_config_path(&app)?;
+        let json = serde_json::to_string_pretty(&*configs)
+            .map_err(|e| format!("Failed to serialize configs: {}", e))?;
+        fs::write(&config_path, json)
+            .map_err(|e| format!("Failed to write configs: {}", e))?;
+    }
+
+    log::info!("Re-keyed {} backups for config {}", report.rekeyed.len(), config_id);
+    Ok(report)
+}
+
+/// Generate the recovery key for an encrypted configuration
+///
+/// Future archives get a second key slot that the recovery key opens, so a
+/// forgotten password no longer means lost backups. The key is returned once
+/// for the user to write down or export; only an escrowed form is persisted.
+/// Calling this again replaces the previous recovery key for new archives.
+#[tauri::command]
+pub async fn create_recovery_key(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config_id: String,
+    password: String,
+) -> Result<String, String> {
+    let config = {
+        let configs = state.configs.lock().map_err(|e| e.to_string())?;
+        configs
+            .iter()
+            .find(|c| c.id == config_id)
+            .ok_or("Config not found")?
+            .clone()
+    };
+
+    if config.mode != BackupMode::Encrypted {
+        return Err("Recovery keys are only available for encrypted backups".to_string());
+    }
+
+    let recovery_key = crate::crypto::generate_recovery_key();
+    let recovery = crate::crypto::setup_recovery(
+        &recovery_key,
+        &password,
+        resolve_kdf_strength(&app, &config),
+        config_id.as_bytes(),
+        chrono::Utc::now().timestamp(),
+    )?;
+
+    {
+        let mut configs = state.configs.lock().map_err(|e| e.to_string())?;
+        if let Some(cfg) = configs.iter_mut().find(|c| c.id == config_id) {
+            cfg.recovery = Some(recovery);
+        }
+
+        let config_path = get_config_path(&app)?;
+        let json = serde_json::to_string_pretty(&*configs)
+            .map_err(|e| format!("Failed to serialize configs: {}", e))?;
+        fs::write(&config_path, json)
+            .map_err(|e| format!("Failed to write configs: {}", e))?;
+    }
+
+    log::info!("Recovery key created for config {}", config_id);
+    Ok(recovery_key.as_str().to_string())
+}
+
+/// Generate an X25519 key pair for recipient (password-less) encryption
+///
+/// Returns the public key to add to a config's recipients and the private key,
+/// which is shown once: keep it offline, it is what restores those backups.
+#[tauri::command]
+pub async fn generate_recipient_keypair() -> Result<RecipientKeyPair, String> {
+    let (public_key, private_key) = crate::crypto::generate_keypair();
+    Ok(RecipientKeyPair {
+        public_key,
+        private_key: private_key.as_str().to_string(),
+    })
+}
+
+/// Check an imported recipient key and return its public key
+///
+/// Accepts a public key, or a private key (whose public key is derived and
+/// returned, the private key itself is not stored).
+#[tauri::command]
+pub async fn validate_recipient_key(key: String) -> Result<String, String> {
+    let key = key.trim();
+    if key.starts_with(crate::crypto::PRIVATE_KEY_PREFIX) {
+        return crate::crypto::public_key_of(key);
+    }
+    crate::crypto::parse_public_key(key)?;
+    Ok(key.to_string())
+}
+
+/// Check that a secret provider returns a password (the password itself is not returned)
+#[tauri::command]
+pub async fn test_secret_source(source: crate::secrets::SecretSource) -> Result<(), String> {
+    crate::secrets::resolve_secret(&source).map(|_| ())
+}
+
 /// Cancel a running backup
 #[tauri::command]
 pub async fn cancel_backup(
@@ -652,19 +1194,20 @@ pub fn is_scheduled_mode() -> bool {
     args.len() >= 3 && args[1] == "--backup"
 }
 
-/// Open the schedule logs directory in Finder
+/// Open the schedule logs directory in Finder (or the Linux file manager)
 #[tauri::command]
 pub async fn open_schedule_logs(config_id: String) -> Result<(), String> {
-    let log_path = launchd::get_log_path(&config_id)?;
+    let log_path = platform_backend()?.log_path(&config_id)?;
     let log_dir = log_path
         .parent()
         .ok_or("Failed to get logs directory")?;
 
-    // Open in Finder using 'open' command
-    let output = std::process::Command::new("open")
+    // Open in Finder using 'open' command (xdg-open on Linux)
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let output = std::process::Command::new(opener)
         .arg(log_dir)
         .output()
-        .map_err(|e| format!("Failed to open Finder: {}", e))?;
+        .map_err(|e| format!("Failed to open file manager: {}", e))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -750,11 +1293,60 @@ pub async fn diagnose_schedule(
 
     let has_schedule = config.schedule.is_some();
 
-    // Check plist file
-    let plist_path = match launchd::get_plist_path(&config_id) {
+    // Next execution, evaluated now (the stored next_run may be stale)
+    let next_execution = match config.schedule.as_ref().filter(|s| s.enabled) {
+        Some(schedule) => match CronSchedule::parse(&schedule.cron_expression) {
+            Ok(cron) => {
+                let next = cron.next_run(&chrono::Local::now());
+                if next.is_none() {
+                    warnings.push("Schedule never fires (impossible date)".to_string());
+                }
+                next.map(|run| run.format("%Y-%m-%d %H:%M:%S %:z").to_string())
+            }
+            Err(e) => {
+                errors.push(format!("Invalid cron expression: {}", e));
+                None
+            }
+        },
+        None => None,
+    };
+
+    // No OS scheduler here: only the in-app scheduler can run the schedule
+    let backend = match platform_backend() {
+        Ok(backend) => backend,
+        Err(e) => {
+            let message = format!("{} (in-app scheduling only, while the app is open)", e);
+            if has_schedule && !config.schedule.as_ref().is_some_and(|s| s.in_app) {
+                errors.push(message);
+            } else {
+                warnings.push(message);
+            }
+            let diagnostics = ScheduleDiagnostics {
+                config_id: config_id.clone(),
+                backend: "unsupported".to_string(),
+                has_schedule,
+                plist_exists: false,
+                plist_path: None,
+                agent_loaded: false,
+                agent_label: None,
+                executable_path: None,
+                executable_exists: false,
+                logs_path: None,
+                logs_exist: false,
+                next_execution,
+                errors,
+                warnings,
+            };
+            log::info!("Diagnostics results: {:?}", diagnostics);
+            return Ok(diagnostics);
+        }
+    };
+
+    // Check schedule file (launchd plist / systemd timer)
+    let plist_path = match backend.schedule_file_path(&config_id) {
         Ok(path) => Some(path),
         Err(e) => {
-            errors.push(format!("Failed to get plist path: {}", e));
+            errors.push(format!("Failed to get {} schedule file path: {}", backend.name(), e));
             None
         }
     };
@@ -762,14 +1354,14 @@ pub async fn diagnose_schedule(
     let plist_exists = plist_path.as_ref().map(|p| p.exists()).unwrap_or(false);
 
     if !plist_exists && has_schedule {
-        errors.push("Schedule configured but plist file not found".to_string());
+        errors.push(format!("Schedule configured but {} schedule file not found", backend.name()));
     }
 
     // Check if agent is loaded
-    let agent_loaded = match launchd::is_agent_loaded(&config_id) {
+    let agent_loaded = match backend.is_loaded(&config_id) {
         Ok(loaded) => {
             if !loaded && has_schedule {
-                errors.push("Agent not loaded in launchctl".to_string());
+                errors.push(format!("Schedule not loaded in {}", backend.name()));
             }
             loaded
         }
@@ -780,13 +1372,13 @@ pub async fn diagnose_schedule(
     };
 
     let agent_label = if has_schedule {
-        Some(format!("com.inlocker.backup.{}", config_id))
+        Some(backend.job_label(&config_id))
     } else {
         None
     };
 
     // Check executable path
-    let executable_path = match launchd::get_executable_path() {
+    let executable_path = match backend.executable_path() {
         Ok(path) => Some(path),
         Err(e) => {
             errors.push(format!("Failed to get executable path: {}", e));
@@ -804,7 +1396,7 @@ pub async fn diagnose_schedule(
     }
 
     // Check logs
-    let logs_path = match launchd::get_log_path(&config_id) {
+    let logs_path = match backend.log_path(&config_id) {
         Ok(path) => Some(path.to_string_lossy().to_string()),
         Err(e) => {
             warnings.push(format!("Failed to get log path: {}", e));
@@ -814,19 +1406,9 @@ pub async fn diagnose_schedule(
 
     let logs_exist = logs_path.as_ref().map(|p| PathBuf::from(p).exists()).unwrap_or(false);
 
-    // Next execution (simplified for now)
-    let next_execution = config
-        .schedule
-        .as_ref()
-        .and_then(|s| s.next_run)
-        .map(|ts| {
-            let datetime = chrono::DateTime::from_timestamp(ts, 0)
-                .unwrap_or_else(|| chrono::Utc::now().into());
-            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-        });
-
     let diagnostics = ScheduleDiagnostics {
         config_id: config_id.clone(),
+        backend: backend.name().to_string(),
         has_schedule,
         plist_exists,
         plist_path: plist_path.map(|p| p.to_string_lossy().to_string()),
@@ -0,0 +1,513 @@
+/// Archive Container Format - versioned, self-describing header for encrypted archives
+///
+/// Layout of a `.tar.zst.enc` archive (format version 3):
+///
+/// ```text
+/// offset  size  field
+/// 0       8     magic "INLOCKR\0"
+/// 8       2     format version (u16 LE)
+/// 10      1     cipher id      (1 = AES-256-GCM STREAM)
+/// 11      1     KDF id         (1 = Argon2id)
+/// 12      1     compression id (1 = zstd)
+/// 13      1     flags          (bit 0 = encrypted, bit 1 = incremental)
+/// 14      4     header JSON length (u32 LE)
+/// 18      n     header JSON (nonce prefix, chunk size, config id, creation time)
+/// 18+n    4     key slot area length (u32 LE)
+/// 22+n    m     key slots JSON (data key wrapped per secret, see `crypto::KeySlot`)
+/// 22+n+m  ...   payload (encrypted chunks)
+/// ```
+///
+/// The header (bytes 0..18+n, exactly as stored) is authenticated as AEAD
+/// associated data of every payload chunk and of every key slot, so editing any
+/// header field makes decryption fail. The key slot area is deliberately
+/// outside the associated data: changing a password rewrites only the slots.
+/// In version 3 the payload key is random, so the header `encryption` block only
+/// supplies the nonce prefix and chunk size (its salt is empty); Argon2id
+/// parameters live in each key slot.
+///
+/// Format version 2 has no key slot area; its payload key is derived directly
+/// from the password using the parameters in the header JSON.
+///
+/// Format version 1 is the original unversioned layout
+/// `[u32 len][EncryptionMetadata JSON][ciphertext]`; it has no magic bytes and is
+/// still recognised on restore.
+
+use crate::crypto::{EncryptionMetadata, KeySlot};
+use std::io::{Cursor, Read};
+
+/// Magic bytes identifying an InLocker container
+pub const MAGIC: &[u8; 8] = b"INLOCKR\0";
+
+/// Current container format version (written by this build)
+pub const FORMAT_VERSION: u16 = 3;
+
+/// First container version with a key slot area (wrapped data key)
+pub const KEY_SLOTS_FORMAT_VERSION: u16 = 3;
+
+/// Oldest container version with a magic header
+const MIN_CONTAINER_VERSION: u16 = 2;
+
+/// Implicit version of archives written before the container header existed
+pub const LEGACY_FORMAT_VERSION: u16 = 1;
+
+/// Size of the fixed (binary) part of the header
+const FIXED_HEADER_LEN: usize = 18;
+
+/// Upper bound for the header JSON (guards against bogus length prefixes)
+const MAX_HEADER_JSON_LEN: usize = 64 * 1024;
+
+/// Bytes inspected to detect the archive format (covers the tar "ustar" magic at offset 257)
+const DETECT_PREFIX_LEN: usize = 512;
+
+/// zstd frame magic number (little-endian 0xFD2FB528)
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Flag: payload is encrypted
+pub const FLAG_ENCRYPTED: u8 = 0b0000_0001;
+/// Flag: archive holds an incremental backup
+pub const FLAG_INCREMENTAL: u8 = 0b0000_0010;
+/// Flags understood by this build (any other bit set is rejected)
+const KNOWN_FLAGS: u8 = FLAG_ENCRYPTED | FLAG_INCREMENTAL;
+
+/// Cipher identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherId {
+    None = 0,
+    /// AES-256-GCM in the STREAM construction (see `crypto::StreamEncryptor`)
+    Aes256GcmStream = 1,
+}
+
+/// Key derivation function identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfId {
+    None = 0,
+    Argon2id = 1,
+}
+
+/// Compression identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionId {
+    None = 0,
+    Zstd = 1,
+}
+
+impl TryFrom<u8> for CipherId {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, String> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Aes256GcmStream),
+            other => Err(format!("Unsupported cipher id {} in archive header", other)),
+        }
+    }
+}
+
+impl TryFrom<u8> for KdfId {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, String> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Argon2id),
+            other => Err(format!("Unsupported key derivation id {} in archive header", other)),
+        }
+    }
+}
+
+impl TryFrom<u8> for CompressionId {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, String> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Zstd),
+            other => Err(format!("Unsupported compression id {} in archive header", other)),
+        }
+    }
+}
+
+/// Variable part of the header (serialized as JSON)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HeaderBody {
+    /// Encryption parameters (salt, nonce prefix, Argon2 params, chunk size)
+    #[serde(default)]
+    pub encryption: Option<EncryptionMetadata>,
+    /// Backup configuration that produced the archive
+    #[serde(default)]
+    pub config_id: Option<String>,
+    /// Unix timestamp when the archive was created
+    #[serde(default)]
+    pub created_at: Option<i64>,
+}
+
+/// Parsed container header
+#[derive(Debug, Clone)]
+pub struct ContainerHeader {
+    pub version: u16,
+    pub cipher: CipherId,
+    pub kdf: KdfId,
+    pub compression: CompressionId,
+    pub flags: u8,
+    pub body: HeaderBody,
+    /// Exact header bytes as read from disk (empty for headers built in memory).
+    /// Used as associated data when decrypting the payload.
+    pub raw: Vec<u8>,
+    /// Wrapped data keys (format version 3+, not part of `raw`)
+    pub key_slots: Vec<KeySlot>,
+}
+
+impl ContainerHeader {
+    /// Header for a zstd-compressed, STREAM-encrypted archive
+    pub fn encrypted(
+        metadata: EncryptionMetadata,
+        incremental: bool,
+        config_id: &str,
+        created_at: i64,
+    ) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            cipher: CipherId::Aes256GcmStream,
+            kdf: KdfId::Argon2id,
+            compression: CompressionId::Zstd,
+            flags: FLAG_ENCRYPTED | if incremental { FLAG_INCREMENTAL } else { 0 },
+            body: HeaderBody {
+                encryption: Some(metadata),
+                config_id: Some(config_id.to_string()),
+                created_at: Some(created_at),
+            },
+            raw: Vec::new(),
+            key_slots: Vec::new(),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        self.flags & FLAG_INCREMENTAL != 0
+    }
+
+    /// Whether the payload key is wrapped in key slots (instead of derived from the password)
+    pub fn has_key_slots(&self) -> bool {
+        self.version >= KEY_SLOTS_FORMAT_VERSION
+    }
+
+    /// Serialize header (fixed part + JSON body), i.e. the authenticated part
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let json = serde_json::to_vec(&self.body)
+            .map_err(|e| format!("Failed to serialize archive header: {}", e))?;
+        if json.len() > MAX_HEADER_JSON_LEN {
+            return Err("Archive header too large".to_string());
+        }
+
+        let mut bytes = Vec::with_capacity(FIXED_HEADER_LEN + json.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.push(self.cipher as u8);
+        bytes.push(self.kdf as u8);
+        bytes.push(self.compression as u8);
+        bytes.push(self.flags);
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&json);
+        Ok(bytes)
+    }
+
+    /// Serialize the key slot area (length prefix + JSON) that follows the header
+    pub fn key_slot_bytes(&self) -> Result<Vec<u8>, String> {
+        let json = serde_json::to_vec(&self.key_slots)
+            .map_err(|e| format!("Failed to serialize key slots: {}", e))?;
+        if json.len() > MAX_HEADER_JSON_LEN {
+            return Err("Key slot area too large".to_string());
+        }
+
+        let mut bytes = Vec::with_capacity(4 + json.len());
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&json);
+        Ok(bytes)
+    }
+
+    /// Parse a header (including magic) from the start of `reader`
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, String> {
+        let mut fixed = [0u8; FIXED_HEADER_LEN];
+        reader.read_exact(&mut fixed)
+            .map_err(|_| "Invalid archive: header truncated".to_string())?;
+
+        if &fixed[..8] != MAGIC {
+            return Err("Invalid archive: missing InLocker header".to_string());
+        }
+
+        // Check the version first so newer archives get a clear message
+        // instead of a confusing error about unknown ids
+        let version = u16::from_le_bytes([fixed[8], fixed[9]]);
+        if !(MIN_CONTAINER_VERSION..=FORMAT_VERSION).contains(&version) {
+            return Err(format!(
+                "Unsupported archive format version {} (this version of InLocker reads versions {} to {}). Please update InLocker to restore this backup.",
+                version, LEGACY_FORMAT_VERSION, FORMAT_VERSION
+            ));
+        }
+
+        let cipher = CipherId::try_from(fixed[10])?;
+        let kdf = KdfId::try_from(fixed[11])?;
+        let compression = CompressionId::try_from(fixed[12])?;
+        let flags = fixed[13];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(format!(
+                "Unsupported archive flags 0x{:02x}. Please update InLocker to restore this backup.",
+                flags
+            ));
+        }
+
+        let json_len = u32::from_le_bytes([fixed[14], fixed[15], fixed[16], fixed[17]]) as usize;
+        if json_len > MAX_HEADER_JSON_LEN {
+            return Err("Invalid archive: header too large".to_string());
+        }
+        let mut json = vec![0u8; json_len];
+        reader.read_exact(&mut json)
+            .map_err(|_| "Invalid archive: header truncated".to_string())?;
+        let body: HeaderBody = serde_json::from_slice(&json)
+            .map_err(|e| format!("Failed to parse archive header: {}", e))?;
+
+        let mut raw = Vec::with_capacity(FIXED_HEADER_LEN + json_len);
+        raw.extend_from_slice(&fixed);
+        raw.extend_from_slice(&json);
+
+        let mut header = Self { version, cipher, kdf, compression, flags, body, raw, key_slots: Vec::new() };
+
+        if header.has_key_slots() {
+            let mut len_bytes = [0u8; 4];
+            reader.read_exact(&mut len_bytes)
+                .map_err(|_| "Invalid archive: key slots truncated".to_string())?;
+            let slots_len = u32::from_le_bytes(len_bytes) as usize;
+            if slots_len > MAX_HEADER_JSON_LEN {
+                return Err("Invalid archive: key slot area too large".to_string());
+            }
+            let mut slots_json = vec![0u8; slots_len];
+            reader.read_exact(&mut slots_json)
+                .map_err(|_| "Invalid archive: key slots truncated".to_string())?;
+            header.key_slots = serde_json::from_slice(&slots_json)
+                .map_err(|e| format!("Failed to parse key slots: {}", e))?;
+        }
+
+        // Encrypted payloads must name a cipher, a KDF and carry their parameters
+        if header.is_encrypted()
+            && (header.cipher == CipherId::None
+                || header.kdf == KdfId::None
+                || header.body.encryption.is_none()
+                || (header.has_key_slots() && header.key_slots.is_empty()))
+        {
+            return Err("Invalid archive: encrypted flag set but encryption parameters missing".to_string());
+        }
+
+        Ok(header)
+    }
+}
+
+/// Archive format detected from file contents
+#[derive(Debug, Clone)]
+pub enum ArchiveFormat {
+    /// Versioned InLocker container (format version 2+)
+    Container(ContainerHeader),
+    /// Original unversioned encrypted layout: `[u32 len][metadata JSON][ciphertext]`
+    LegacyEncrypted(EncryptionMetadata),
+    /// Plain zstd-compressed tar (`.tar.zst`)
+    Zstd,
+    /// Uncompressed tar
+    Tar,
+}
+
+/// Detect the archive format from its first bytes
+///
+/// Returns the format and a reader positioned at the start of the payload
+/// (after any header). The file extension is not consulted.
+pub fn open_archive<R: Read>(mut reader: R) -> Result<(ArchiveFormat, impl Read), String> {
+    let mut prefix = Vec::with_capacity(DETECT_PREFIX_LEN);
+    (&mut reader)
+        .take(DETECT_PREFIX_LEN as u64)
+        .read_to_end(&mut prefix)
+        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+
+    let format_hint = detect_prefix(&prefix);
+    let mut reader = Cursor::new(prefix).chain(reader);
+
+    let format = match format_hint {
+        Some(PrefixKind::Container) => ArchiveFormat::Container(ContainerHeader::read_from(&mut reader)?),
+        Some(PrefixKind::LegacyEncrypted) => ArchiveFormat::LegacyEncrypted(read_legacy_metadata(&mut reader)?),
+        Some(PrefixKind::Zstd) => ArchiveFormat::Zstd,
+        Some(PrefixKind::Tar) => ArchiveFormat::Tar,
+        None => return Err("Unrecognized backup file format".to_string()),
+    };
+
+    Ok((format, reader))
+}
+
+enum PrefixKind {
+    Container,
+    LegacyEncrypted,
+    Zstd,
+    Tar,
+}
+
+fn detect_prefix(prefix: &[u8]) -> Option<PrefixKind> {
+    if prefix.starts_with(MAGIC) {
+        return Some(PrefixKind::Container);
+    }
+    if prefix.starts_with(&ZSTD_MAGIC) {
+        return Some(PrefixKind::Zstd);
+    }
+    // Legacy encrypted: plausible little-endian length followed by a JSON object
+    if prefix.len() > 4 && prefix[4] == b'{' {
+        let len = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        if len > 0 && len <= MAX_HEADER_JSON_LEN {
+            return Some(PrefixKind::LegacyEncrypted);
+        }
+    }
+    // POSIX tar: "ustar" magic at offset 257
+    if prefix.len() >= 262 && &prefix[257..262] == b"ustar" {
+        return Some(PrefixKind::Tar);
+    }
+    None
+}
+
+/// Read the `[4-byte length][metadata JSON]` block of a legacy (version 1) encrypted archive
+fn read_legacy_metadata<R: Read>(reader: &mut R) -> Result<EncryptionMetadata, String> {
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes)
+        .map_err(|_| "Invalid encrypted file: too short".to_string())?;
+
+    let metadata_len = u32::from_le_bytes(len_bytes) as usize;
+    if metadata_len > MAX_HEADER_JSON_LEN {
+        return Err("Invalid encrypted file: metadata too large".to_string());
+    }
+
+    let mut metadata_json = vec![0u8; metadata_len];
+    reader.read_exact(&mut metadata_json)
+        .map_err(|_| "Invalid encrypted file: metadata truncated".to_string())?;
+
+    serde_json::from_slice(&metadata_json)
+        .map_err(|e| format!("Failed to parse encryption metadata: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_header() -> ContainerHeader {
+        let mut header = ContainerHeader::encrypted(crate::crypto::new_stream_metadata(), true, "config-1", 1_700_000_000);
+        header.key_slots.push(KeySlot {
+            kind: crate::crypto::KeySlotKind::Password,
+            kdf: EncryptionMetadata::default(),
+            wrapped_key: "d3JhcHBlZA==".to_string(),
+            ephemeral_key: None,
+        });
+        header
+    }
+
+    fn sample_archive(header: &ContainerHeader) -> Vec<u8> {
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(&header.key_slot_bytes().unwrap());
+        bytes
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = sample_header();
+        let mut bytes = sample_archive(&header);
+        bytes.extend_from_slice(b"payload");
+
+        let (format, mut payload) = open_archive(&bytes[..]).unwrap();
+        let parsed = match format {
+            ArchiveFormat::Container(h) => h,
+            other => panic!("Expected container, got {:?}", other),
+        };
+
+        assert_eq!(parsed.version, FORMAT_VERSION);
+        assert_eq!(parsed.cipher, CipherId::Aes256GcmStream);
+        assert_eq!(parsed.kdf, KdfId::Argon2id);
+        assert_eq!(parsed.compression, CompressionId::Zstd);
+        assert!(parsed.is_encrypted());
+        assert!(parsed.is_incremental());
+        assert_eq!(parsed.body.config_id.as_deref(), Some("config-1"));
+        assert_eq!(parsed.body.created_at, Some(1_700_000_000));
+        assert_eq!(parsed.raw, header.to_bytes().unwrap());
+        assert_eq!(parsed.key_slots.len(), 1);
+        assert_eq!(parsed.key_slots[0].wrapped_key, "d3JhcHBlZA==");
+        assert_eq!(
+            parsed.body.encryption.unwrap().salt,
+            header.body.encryption.unwrap().salt
+        );
+
+        let mut rest = Vec::new();
+        payload.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"payload");
+    }
+
+    #[test]
+    fn test_version_2_header_has_no_key_slots() {
+        let mut header = sample_header();
+        header.version = 2;
+        header.key_slots.clear();
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(b"payload");
+
+        let (format, mut payload) = open_archive(&bytes[..]).unwrap();
+        match format {
+            ArchiveFormat::Container(h) => {
+                assert_eq!(h.version, 2);
+                assert!(!h.has_key_slots());
+            }
+            other => panic!("Expected container, got {:?}", other),
+        }
+
+        let mut rest = Vec::new();
+        payload.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"payload");
+    }
+
+    #[test]
+    fn test_encrypted_without_key_slots_rejected() {
+        let mut header = sample_header();
+        header.key_slots.clear();
+
+        let err = open_archive(&sample_archive(&header)[..]).err().unwrap();
+        assert!(err.contains("encryption parameters missing"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_version_rejected() {
+        let mut bytes = sample_archive(&sample_header());
+        bytes[8..10].copy_from_slice(&99u16.to_le_bytes());
+
+        let err = open_archive(&bytes[..]).err().unwrap();
+        assert!(err.contains("Unsupported archive format version 99"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_flags_rejected() {
+        let mut bytes = sample_archive(&sample_header());
+        bytes[13] |= 0b1000_0000;
+
+        let err = open_archive(&bytes[..]).err().unwrap();
+        assert!(err.contains("Unsupported archive flags"), "{}", err);
+    }
+
+    #[test]
+    fn test_detect_legacy_and_plain_formats() {
+        // Legacy encrypted layout
+        let metadata = serde_json::to_vec(&crate::crypto::EncryptionMetadata::default()).unwrap();
+        let mut legacy = (metadata.len() as u32).to_le_bytes().to_vec();
+        legacy.extend_from_slice(&metadata);
+        legacy.extend_from_slice(b"ciphertext");
+        let (format, _) = open_archive(&legacy[..]).unwrap();
+        assert!(matches!(format, ArchiveFormat::LegacyEncrypted(_)));
+
+        // zstd frame
+        let compressed = zstd::encode_all(&b"data"[..], 3).unwrap();
+        let (format, _) = open_archive(&compressed[..]).unwrap();
+        assert!(matches!(format, ArchiveFormat::Zstd));
+
+        // Garbage
+        assert!(open_archive(&b"not an archive"[..]).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+/// Cron expression parsing
+///
+/// Supports the standard 5-field grammar ("minute hour day month weekday"):
+/// `*`, values, ranges (`1-5`), steps (`*/15`, `0-30/10`, `5/20`), lists of
+/// any of these (`1-5,10,20-30/2`), month and weekday names (`jan`, `mon-fri`),
+/// weekday 7 as Sunday, and the `@hourly`/`@daily`/`@weekly`/`@monthly`/`@yearly` macros.
+///
+/// Day-of-month and weekday follow the usual cron rule: when both are
+/// restricted, a time matches if EITHER matches (`0 9 1 * mon` = the 1st and every Monday).
+///
+/// Fire times are evaluated on local wall-clock time. Across DST changes a
+/// time inside a skipped hour fires once at the end of the gap (02:30 on a
+/// spring-forward night runs at 03:00), and a time inside a repeated hour fires
+/// only once, at its first occurrence.
+
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead to search for fire times (covers "29 feb" schedules across leap years)
+const MAX_SEARCH_DAYS: i64 = 366 * 8 + 2;
+
+/// Longest DST gap to skip over when resolving a nonexistent local time
+const MAX_GAP_MINUTES: i64 = 24 * 60;
+
+/// Allowed values of one cron field
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronField {
+    /// Sorted, deduplicated values
+    values: Vec<u32>,
+    /// Field was written starting with `*` (relevant for the day/weekday rule)
+    star: bool,
+    /// Field covers its whole range
+    full: bool,
+}
+
+impl CronField {
+    /// Sorted values this field allows
+    pub fn values(&self) -> &[u32] {
+        &self.values
+    }
+
+    /// Whether every value of the range is allowed (no restriction)
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn matches(&self, value: u32) -> bool {
+        self.values.binary_search(&value).is_ok()
+    }
+}
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    pub minutes: CronField,
+    pub hours: CronField,
+    pub days: CronField,
+    pub months: CronField,
+    /// 0 = Sunday ... 6 = Saturday
+    pub weekdays: CronField,
+}
+
+impl CronSchedule {
+    /// Parse a 5-field cron expression or an `@` macro
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let expanded = match expr.to_ascii_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            "@reboot" => return Err("@reboot is not supported for scheduled backups".to_string()),
+            other if other.starts_with('@') => return Err(format!("Unknown cron macro: {}", expr)),
+            _ => expr,
+        };
+
+        let parts: Vec<&str> = expanded.split_whitespace().collect();
+        if parts.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression: expected 5 fields, got {}",
+                parts.len()
+            ));
+        }
+
+        Ok(Self {
+            minutes: parse_field(parts[0], 0, 59, &[]).map_err(|e| format!("Invalid minute field: {}", e))?,
+            hours: parse_field(parts[1], 0, 23, &[]).map_err(|e| format!("Invalid hour field: {}", e))?,
+            days: parse_field(parts[2], 1, 31, &[]).map_err(|e| format!("Invalid day field: {}", e))?,
+            months: parse_field(parts[3], 1, 12, &MONTH_NAMES).map_err(|e| format!("Invalid month field: {}", e))?,
+            weekdays: parse_field(parts[4], 0, 7, &WEEKDAY_NAMES).map_err(|e| format!("Invalid weekday field: {}", e))?,
+        })
+    }
+
+    /// Whether day-of-month and weekday are combined with OR (both restricted)
+    pub fn days_or_weekdays(&self) -> bool {
+        !self.days.star && !self.weekdays.star
+    }
+
+    /// Whether the schedule fires at this local date and time (weekday 0 = Sunday)
+    pub fn matches(&self, minute: u32, hour: u32, day: u32, month: u32, weekday: u32) -> bool {
+        self.minutes.matches(minute)
+            && self.hours.matches(hour)
+            && self.months.matches(month)
+            && self.day_matches(day, weekday)
+    }
+
+    fn day_matches(&self, day: u32, weekday: u32) -> bool {
+        if self.days_or_weekdays() {
+            self.days.matches(day) || self.weekdays.matches(weekday)
+        } else {
+            self.days.matches(day) && self.weekdays.matches(weekday)
+        }
+    }
+
+    /// Whether the schedule fires on this calendar date (any time of day)
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        self.months.matches(date.month()) && self.day_matches(date.day(), date.weekday().num_days_from_sunday())
+    }
+
+    /// Next `count` fire times strictly after `after`, in `after`'s time zone
+    ///
+    /// Fewer are returned if the schedule can never fire (e.g. "0 0 31 2 *").
+    pub fn next_runs<Tz: TimeZone>(&self, after: &DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local().date();
+        let mut runs: Vec<DateTime<Tz>> = Vec::with_capacity(count);
+
+        // Walk wall-clock candidates in order; DST resolution can map several of them
+        // to the same instant (gap) or to an instant before `after` (first day, fold)
+        for date in start.iter_days().take(MAX_SEARCH_DAYS as usize) {
+            if runs.len() >= count {
+                break;
+            }
+            if !self.matches_date(date) {
+                continue;
+            }
+            for &hour in self.hours.values() {
+                for &minute in self.minutes.values() {
+                    let Some(local) = date.and_hms_opt(hour, minute, 0) else { continue };
+                    let Some(run) = resolve_local(&tz, local) else { continue };
+                    let is_new = run > *after && runs.last().is_none_or(|last| run > *last);
+                    if is_new && runs.len() < count {
+                        runs.push(run);
+                    }
+                }
+            }
+        }
+
+        runs
+    }
+
+    /// Next fire time strictly after `after`
+    pub fn next_run<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.next_runs(after, 1).into_iter().next()
+    }
+
+    /// Whether the schedule fires in `(after, until]`
+    pub fn fired_between<Tz: TimeZone>(&self, after: &DateTime<Tz>, until: &DateTime<Tz>) -> bool {
+        after < until && self.next_run(after).is_some_and(|run| run <= *until)
+    }
+}
+
+/// Map a wall-clock time to an instant: the first occurrence of a repeated
+/// time, or the end of the gap for a time skipped by a DST change
+fn resolve_local<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(run) => Some(run),
+        LocalResult::Ambiguous(first, _) => Some(first),
+        LocalResult::None => (1..=MAX_GAP_MINUTES)
+            .find_map(|offset| tz.from_local_datetime(&(local + Duration::minutes(offset))).earliest()),
+    }
+}
+
+/// Parse one field: a comma-separated list of `*`, values or ranges, each with an optional `/step`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<CronField, String> {
+    let mut values = Vec::new();
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be at least 1".to_string());
+                }
+                (range, Some(step))
+            }
+            None => (item, None),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max, names)?, parse_value(end, min, max, names)?)
+        } else {
+            let value = parse_value(range, min, max, names)?;
+            // "5/20" means "from 5 to the end, every 20"
+            (value, if step.is_some() { max } else { value })
+        };
+
+        if start > end {
+            return Err(format!("invalid range '{}'", range));
+        }
+        values.extend((start..=end).step_by(step.unwrap_or(1) as usize));
+    }
+
+    // Weekday 7 is Sunday
+    if names.len() == WEEKDAY_NAMES.len() {
+        for value in values.iter_mut().filter(|v| **v == 7) {
+            *value = 0;
+        }
+    }
+    values.sort_unstable();
+    values.dedup();
+
+    let range_len = if names.len() == WEEKDAY_NAMES.len() { 7 } else { (max - min + 1) as usize };
+    Ok(CronField {
+        full: values.len() == range_len,
+        star: field.starts_with('*'),
+        values,
+    })
+}
+
+/// Parse a number or a name (`jan`, `mon`), checking it against the field range
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let lower = value.to_ascii_lowercase();
+    let parsed = match names.iter().position(|name| *name == lower) {
+        // Month names start at 1, weekday names at 0 (Sunday)
+        Some(index) => index as u32 + min,
+        None => value.parse().map_err(|_| format!("invalid value '{}'", value))?,
+    };
+
+    if parsed < min || parsed > max {
+        return Err(format!("value {} out of range {}-{}", parsed, min, max));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use chrono_tz::America::New_York;
+
+    #[test]
+    fn test_steps_ranges_and_lists() {
+        let schedule = CronSchedule::parse("0-30/10,45 */6 5/10 * *").unwrap();
+        assert_eq!(schedule.minutes.values(), &[0, 10, 20, 30, 45]);
+        assert_eq!(schedule.hours.values(), &[0, 6, 12, 18]);
+        assert_eq!(schedule.days.values(), &[5, 15, 25]);
+        assert!(schedule.months.is_full());
+        assert_eq!(CronSchedule::parse("*/15 * * * *").unwrap().minutes.values(), &[0, 15, 30, 45]);
+    }
+
+    #[test]
+    fn test_names_and_sunday_as_seven() {
+        let schedule = CronSchedule::parse("0 9 * JAN,jul-aug mon-fri").unwrap();
+        assert_eq!(schedule.months.values(), &[1, 7, 8]);
+        assert_eq!(schedule.weekdays.values(), &[1, 2, 3, 4, 5]);
+
+        let sundays = CronSchedule::parse("0 9 * * 7").unwrap();
+        assert_eq!(sundays.weekdays.values(), &[0]);
+        assert!(CronSchedule::parse("0 9 * * 0-7").unwrap().weekdays.is_full());
+    }
+
+    #[test]
+    fn test_macros() {
+        assert_eq!(CronSchedule::parse("@daily").unwrap(), CronSchedule::parse("0 0 * * *").unwrap());
+        assert_eq!(CronSchedule::parse("@weekly").unwrap(), CronSchedule::parse("0 0 * * 0").unwrap());
+        assert!(CronSchedule::parse("@reboot").is_err());
+        assert!(CronSchedule::parse("@often").is_err());
+    }
+
+    #[test]
+    fn test_day_and_weekday_combined_with_or() {
+        // The 1st of the month and every Monday (2024-04-01 is a Monday, 2024-04-08 too)
+        let schedule = CronSchedule::parse("0 9 1 * mon").unwrap();
+        assert!(schedule.matches(0, 9, 1, 4, 1));
+        assert!(schedule.matches(0, 9, 8, 4, 1));
+        assert!(schedule.matches(0, 9, 1, 5, 3));
+        assert!(!schedule.matches(0, 9, 2, 4, 2));
+
+        // With a wildcard day, only the weekday restricts
+        let weekdays = CronSchedule::parse("0 9 * * 1-5").unwrap();
+        assert!(weekdays.matches(0, 9, 17, 6, 3));
+        assert!(!weekdays.matches(0, 9, 16, 6, 0));
+    }
+
+    #[test]
+    fn test_invalid_expressions_rejected() {
+        for expr in ["", "0 9 * *", "60 * * * *", "0 24 * * *", "0 9 0 * *", "0 9 * 13 *", "0 9 * * 8", "*/0 * * * *", "30-10 * * * *", "0 9 * foo *"] {
+            assert!(CronSchedule::parse(expr).is_err(), "'{}' should be rejected", expr);
+        }
+    }
+
+    #[test]
+    fn test_next_runs_in_order() {
+        let schedule = CronSchedule::parse("0 9 * * mon-fri").unwrap();
+        // Friday 2024-06-14 10:00 UTC
+        let after = Utc.with_ymd_and_hms(2024, 6, 14, 10, 0, 0).unwrap();
+        let runs = schedule.next_runs(&after, 3);
+        assert_eq!(runs, vec![
+            Utc.with_ymd_and_hms(2024, 6, 17, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 18, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 19, 9, 0, 0).unwrap(),
+        ]);
+
+        // Strictly after: a fire time equal to `after` is not returned
+        assert_eq!(schedule.next_run(&runs[0]), Some(runs[1]));
+    }
+
+    #[test]
+    fn test_rare_and_impossible_schedules() {
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let leap_day = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap_day.next_run(&after), Some(Utc.with_ymd_and_hms(2028, 2, 29, 0, 0, 0).unwrap()));
+
+        let never = CronSchedule::parse("0 0 31 feb *").unwrap();
+        assert!(never.next_runs(&after, 3).is_empty());
+    }
+
+    #[test]
+    fn test_skipped_hour_fires_once_after_the_gap() {
+        // 2024-03-10: New York clocks jump from 02:00 EST to 03:00 EDT
+        let after = New_York.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap();
+        let runs = CronSchedule::parse("30 2 * * *").unwrap().next_runs(&after, 2);
+        assert_eq!(runs[0], Utc.with_ymd_and_hms(2024, 3, 10, 7, 0, 0).unwrap());
+        assert_eq!(runs[1], New_York.with_ymd_and_hms(2024, 3, 11, 2, 30, 0).unwrap());
+
+        // All the skipped quarter-hours collapse into one run at 03:00
+        let after = New_York.with_ymd_and_hms(2024, 3, 10, 1, 50, 0).unwrap();
+        let runs = CronSchedule::parse("*/15 * * * *").unwrap().next_runs(&after, 3);
+        let local: Vec<String> = runs.iter().map(|r| r.format("%H:%M %Z").to_string()).collect();
+        assert_eq!(local, vec!["03:00 EDT", "03:15 EDT", "03:30 EDT"]);
+    }
+
+    #[test]
+    fn test_repeated_hour_fires_once() {
+        // 2024-11-03: New York clocks go back from 02:00 EDT to 01:00 EST
+        let after = New_York.with_ymd_and_hms(2024, 11, 2, 12, 0, 0).unwrap();
+        let runs = CronSchedule::parse("30 1 * * *").unwrap().next_runs(&after, 2);
+        assert_eq!(runs[0], Utc.with_ymd_and_hms(2024, 11, 3, 5, 30, 0).unwrap(), "First (EDT) occurrence");
+        assert_eq!(runs[1], Utc.with_ymd_and_hms(2024, 11, 4, 6, 30, 0).unwrap(), "Then the next day (EST)");
+    }
+}
@@ -2,7 +2,10 @@
 ///
 /// This module provides secure encryption/decryption for backup files using:
 /// - AES-256-GCM (AEAD cipher with authentication)
+/// - STREAM construction for chunked, constant-memory encryption of large archives
 /// - Argon2id for key derivation (RFC 9106 parameters)
+/// - Key wrapping: a random data key encrypts the payload, password-derived keys wrap it
+/// - X25519 recipients: the data key can also be wrapped to public keys (no password needed to back up)
 /// - Secure random IV generation
 /// - Memory zeroization for keys
 ///
@@ -11,10 +14,54 @@
 /// - RFC 9106 (Argon2id parameters)
 /// - OWASP ASVS L2 requirements
 
+use crate::types::KdfStrength;
 use argon2::{Argon2, ParamsBuilder, Version};
 use rand::{rngs::OsRng, RngCore};
-use ring::aead::{Aad, BoundKey, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey, AES_256_GCM};
-use zeroize::Zeroize;
+use ring::aead::{
+    Aad, BoundKey, LessSafeKey, Nonce, NonceSequence, OpeningKey, SealingKey, UnboundKey, AES_256_GCM,
+};
+use ring::hkdf;
+use std::io::{Read, Write};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Plaintext bytes per STREAM chunk (each sealed chunk carries an extra 16-byte tag)
+pub const STREAM_CHUNK_SIZE: u32 = 1024 * 1024; // 1 MB
+
+/// AES-GCM authentication tag length
+const TAG_LEN: usize = 16;
+
+/// STREAM nonce prefix length: 12-byte nonce = [7-byte prefix][4-byte counter][1-byte last flag]
+const NONCE_PREFIX_LEN: usize = 7;
+
+/// Argon2id bounds accepted when deriving keys. Parameters come from the archive
+/// header, so they are validated before use: the upper bounds stop a crafted
+/// header from exhausting memory/CPU, the lower bounds refuse trivially weak keys.
+pub const MIN_MEMORY_COST: u32 = 8 * 1024; // 8 MB
+pub const MAX_MEMORY_COST: u32 = 1024 * 1024; // 1 GB
+pub const MIN_ITERATIONS: u32 = 1;
+pub const MAX_ITERATIONS: u32 = 10;
+pub const MIN_PARALLELISM: u32 = 1;
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2 version 1.3 (the only version we write or accept)
+const ARGON2_VERSION: u32 = 0x13;
+
+/// Length of a data key (AES-256)
+pub const DATA_KEY_LEN: usize = 32;
+
+/// Random bytes in a recovery key (160 bits, 32 base32 characters)
+const RECOVERY_KEY_BYTES: usize = 20;
+
+/// Domain separation label for the recovery key check value
+const RECOVERY_CHECK_LABEL: &[u8] = b"inlocker-recovery-key-check";
+
+/// HKDF info string for X25519 recipient wrapping keys
+const X25519_WRAP_INFO: &[u8] = b"inlocker-x25519-v1";
+
+/// Text prefixes of exported X25519 keys
+pub const PUBLIC_KEY_PREFIX: &str = "inlocker-pk-";
+pub const PRIVATE_KEY_PREFIX: &str = "INLOCKER-SK-";
 
 /// Encryption parameters (stored with encrypted data)
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -31,6 +78,9 @@ pub struct EncryptionMetadata {
     pub iterations: u32,
     /// Parallelism factor
     pub parallelism: u32,
+    /// Plaintext bytes per STREAM chunk (0 = legacy single-shot AES-GCM)
+    #[serde(default)]
+    pub chunk_size: u32,
 }
 
 impl Default for EncryptionMetadata {
@@ -39,14 +89,72 @@ impl Default for EncryptionMetadata {
         Self {
             salt: String::new(),
             nonce: String::new(),
-            version: 0x13, // Argon2 version 1.3
+            version: ARGON2_VERSION, // Argon2 version 1.3
             memory_cost: 65536, // 64 MB
             iterations: 3,
             parallelism: 4,
+            chunk_size: 0,
         }
     }
 }
 
+impl KdfStrength {
+    /// Argon2id (memory cost in KB, iterations, parallelism) for this strength
+    pub fn params(&self) -> (u32, u32, u32) {
+        match self {
+            // RFC 9106 second recommended option, scaled for interactive use
+            KdfStrength::Standard => (64 * 1024, 3, 4), // 64 MB
+            KdfStrength::High => (256 * 1024, 4, 4),    // 256 MB
+            KdfStrength::Maximum => (1024 * 1024, 4, 4), // 1 GB
+        }
+    }
+}
+
+impl EncryptionMetadata {
+    /// Replace the Argon2id parameters with those of `strength`
+    pub fn with_kdf_strength(mut self, strength: KdfStrength) -> Self {
+        let (memory_cost, iterations, parallelism) = strength.params();
+        self.memory_cost = memory_cost;
+        self.iterations = iterations;
+        self.parallelism = parallelism;
+        self
+    }
+}
+
+/// Which secret opens a key slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySlotKind {
+    /// The backup password
+    #[default]
+    Password,
+    /// The offline recovery key (see `generate_recovery_key`)
+    Recovery,
+    /// An X25519 private key (the slot is wrapped to its public key)
+    X25519,
+}
+
+/// Data key wrapped with a password-derived key (one key slot of an archive header)
+///
+/// The payload is encrypted with a random data key; each slot stores that key
+/// encrypted with AES-256-GCM under an Argon2id key derived from one secret
+/// (or, for X25519 slots, an HKDF key agreed with an ephemeral public key).
+/// Changing the password only replaces the slot, never the payload.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct KeySlot {
+    /// Secret that opens this slot
+    #[serde(default)]
+    pub kind: KeySlotKind,
+    /// Argon2id salt and parameters, plus the AES-GCM nonce used for wrapping
+    #[serde(flatten)]
+    pub kdf: EncryptionMetadata,
+    /// Encrypted data key including the authentication tag (base64 encoded)
+    pub wrapped_key: String,
+    /// Ephemeral X25519 public key (base64 encoded, X25519 slots only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ephemeral_key: Option<String>,
+}
+
 /// Nonce sequence for AES-GCM (single-use)
 struct OneNonceSequence {
     nonce: Option<Nonce>,
@@ -72,11 +180,50 @@ impl NonceSequence for OneNonceSequence {
 /// - Parallelism: 4
 /// - Output: 32 bytes (256 bits)
 pub fn derive_key(password: &str, salt: &[u8]) -> Result<Vec<u8>, String> {
-    // Configure Argon2id parameters (RFC 9106)
+    derive_key_with_params(password, salt, &EncryptionMetadata::default())
+}
+
+/// Check that Argon2id parameters are within the accepted bounds
+pub fn validate_kdf_params(metadata: &EncryptionMetadata) -> Result<(), String> {
+    if metadata.version != ARGON2_VERSION {
+        return Err(format!("Unsupported Argon2 version 0x{:x}", metadata.version));
+    }
+    if !(MIN_MEMORY_COST..=MAX_MEMORY_COST).contains(&metadata.memory_cost) {
+        return Err(format!(
+            "Argon2 memory cost {} KB out of range ({} to {} KB)",
+            metadata.memory_cost, MIN_MEMORY_COST, MAX_MEMORY_COST
+        ));
+    }
+    if !(MIN_ITERATIONS..=MAX_ITERATIONS).contains(&metadata.iterations) {
+        return Err(format!(
+            "Argon2 iterations {} out of range ({} to {})",
+            metadata.iterations, MIN_ITERATIONS, MAX_ITERATIONS
+        ));
+    }
+    if !(MIN_PARALLELISM..=MAX_PARALLELISM).contains(&metadata.parallelism) {
+        return Err(format!(
+            "Argon2 parallelism {} out of range ({} to {})",
+            metadata.parallelism, MIN_PARALLELISM, MAX_PARALLELISM
+        ));
+    }
+    Ok(())
+}
+
+/// Derive 256-bit encryption key using the Argon2id parameters recorded in `metadata`
+///
+/// Parameters are validated first (see `validate_kdf_params`), so this is safe to
+/// call with metadata read from an untrusted archive header.
+pub fn derive_key_with_params(
+    password: &str,
+    salt: &[u8],
+    metadata: &EncryptionMetadata,
+) -> Result<Vec<u8>, String> {
+    validate_kdf_params(metadata)?;
+
     let params = ParamsBuilder::new()
-        .m_cost(65536) // 64 MB
-        .t_cost(3)     // 3 iterations
-        .p_cost(4)     // 4 parallel threads
+        .m_cost(metadata.memory_cost)
+        .t_cost(metadata.iterations)
+        .p_cost(metadata.parallelism)
         .output_len(32) // 256 bits
         .build()
         .map_err(|e| format!("Failed to build Argon2 params: {}", e))?;
@@ -119,6 +266,18 @@ pub fn generate_nonce() -> Vec<u8> {
 /// # Returns
 /// Tuple of (ciphertext, encryption_metadata)
 pub fn encrypt(plaintext: &[u8], password: &str) -> Result<(Vec<u8>, EncryptionMetadata), String> {
+    encrypt_with_aad(plaintext, password, &[])
+}
+
+/// Encrypt data using AES-256-GCM (in-memory), authenticating `aad` alongside it
+///
+/// The associated data is not encrypted or stored, but decryption fails unless
+/// exactly the same bytes are supplied to `decrypt_with_aad`.
+pub fn encrypt_with_aad(
+    plaintext: &[u8],
+    password: &str,
+    aad: &[u8],
+) -> Result<(Vec<u8>, EncryptionMetadata), String> {
     // Generate salt and nonce
     let salt = generate_salt();
     let nonce_bytes = generate_nonce();
@@ -143,7 +302,7 @@ pub fn encrypt(plaintext: &[u8], password: &str) -> Result<(Vec<u8>, EncryptionM
     // Encrypt in-place
     let mut ciphertext = plaintext.to_vec();
     sealing_key
-        .seal_in_place_append_tag(Aad::empty(), &mut ciphertext)
+        .seal_in_place_append_tag(Aad::from(aad), &mut ciphertext)
         .map_err(|_| "Encryption failed".to_string())?;
 
     // Create metadata
@@ -154,16 +313,663 @@ pub fn encrypt(plaintext: &[u8], password: &str) -> Result<(Vec<u8>, EncryptionM
         memory_cost: 65536,
         iterations: 3,
         parallelism: 4,
+        chunk_size: 0,
     };
 
     Ok((ciphertext, metadata))
 }
 
-/// Encrypt data using AES-256-GCM with streaming (chunked processing)
+/// Create metadata for a new STREAM-encrypted payload (fresh salt and nonce prefix)
+pub fn new_stream_metadata() -> EncryptionMetadata {
+    let mut nonce_prefix = vec![0u8; NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut nonce_prefix);
+
+    EncryptionMetadata {
+        salt: base64::encode(&generate_salt()),
+        nonce: base64::encode(&nonce_prefix),
+        chunk_size: STREAM_CHUNK_SIZE,
+        ..EncryptionMetadata::default()
+    }
+}
+
+/// Build the nonce for one STREAM chunk
+///
+/// Layout: [7-byte random prefix][4-byte big-endian chunk counter][1-byte last-chunk flag]
+/// The counter makes reordered chunks fail authentication, the flag makes truncation detectable.
+fn stream_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u32, last: bool) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Decode the nonce prefix of a STREAM payload
+fn stream_nonce_prefix(metadata: &EncryptionMetadata) -> Result<[u8; NONCE_PREFIX_LEN], String> {
+    if metadata.chunk_size == 0 {
+        return Err("Metadata does not describe a chunked (STREAM) payload".to_string());
+    }
+
+    let prefix_bytes = base64::decode(&metadata.nonce)
+        .map_err(|e| format!("Invalid nonce: {}", e))?;
+    prefix_bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| "Invalid nonce prefix length".to_string())
+}
+
+/// Derive the AES key and decode the nonce prefix for a STREAM payload
+fn stream_key(password: &str, metadata: &EncryptionMetadata) -> Result<(LessSafeKey, [u8; NONCE_PREFIX_LEN]), String> {
+    let prefix = stream_nonce_prefix(metadata)?;
+    let salt = base64::decode(&metadata.salt)
+        .map_err(|e| format!("Invalid salt: {}", e))?;
+
+    let key_bytes = Zeroizing::new(derive_key_with_params(password, &salt, metadata)?);
+    Ok((aes_key(&key_bytes)?, prefix))
+}
+
+/// Build the AES key and decode the nonce prefix for a STREAM payload encrypted with a data key
+fn stream_data_key(data_key: &[u8], metadata: &EncryptionMetadata) -> Result<(LessSafeKey, [u8; NONCE_PREFIX_LEN]), String> {
+    let prefix = stream_nonce_prefix(metadata)?;
+    Ok((aes_key(data_key)?, prefix))
+}
+
+/// AES-256-GCM key from raw key bytes
+fn aes_key(key_bytes: &[u8]) -> Result<LessSafeKey, String> {
+    let unbound_key = UnboundKey::new(&AES_256_GCM, key_bytes)
+        .map_err(|_| "Failed to create encryption key".to_string())?;
+    Ok(LessSafeKey::new(unbound_key))
+}
+
+/// Generate a random data key for encrypting one payload
+pub fn generate_data_key() -> Zeroizing<Vec<u8>> {
+    let mut key = Zeroizing::new(vec![0u8; DATA_KEY_LEN]);
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+/// Wrap `data_key` under a key derived from `password` with the Argon2id cost of `strength`
+///
+/// `aad` (typically the serialized archive header) is authenticated with the
+/// wrapped key, so a slot only opens in the archive it was created for.
+pub fn wrap_data_key(
+    data_key: &[u8],
+    password: &str,
+    strength: KdfStrength,
+    aad: &[u8],
+) -> Result<KeySlot, String> {
+    let salt = generate_salt();
+    let nonce_bytes = generate_nonce();
+    let kdf = EncryptionMetadata {
+        salt: base64::encode(&salt),
+        nonce: base64::encode(&nonce_bytes),
+        ..EncryptionMetadata::default()
+    }
+    .with_kdf_strength(strength);
+
+    let wrapping_key = Zeroizing::new(derive_key_with_params(password, &salt, &kdf)?);
+    let nonce = Nonce::try_assume_unique_for_key(&nonce_bytes)
+        .map_err(|_| "Invalid nonce".to_string())?;
+
+    let mut wrapped = data_key.to_vec();
+    aes_key(&wrapping_key)?
+        .seal_in_place_append_tag(nonce, Aad::from(aad), &mut wrapped)
+        .map_err(|_| "Failed to wrap data key".to_string())?;
+
+    Ok(KeySlot {
+        kind: KeySlotKind::Password,
+        kdf,
+        wrapped_key: base64::encode(&wrapped),
+        ephemeral_key: None,
+    })
+}
+
+/// Wrap `data_key` directly under `wrapping_key`, recording `kdf` (salt and parameters) in the slot
+fn seal_key_slot(
+    data_key: &[u8],
+    wrapping_key: &[u8],
+    kind: KeySlotKind,
+    kdf: &EncryptionMetadata,
+    aad: &[u8],
+) -> Result<KeySlot, String> {
+    let nonce_bytes = generate_nonce();
+    let nonce = Nonce::try_assume_unique_for_key(&nonce_bytes)
+        .map_err(|_| "Invalid nonce".to_string())?;
+
+    let mut wrapped = data_key.to_vec();
+    aes_key(wrapping_key)?
+        .seal_in_place_append_tag(nonce, Aad::from(aad), &mut wrapped)
+        .map_err(|_| "Failed to wrap data key".to_string())?;
+
+    Ok(KeySlot {
+        kind,
+        kdf: EncryptionMetadata {
+            nonce: base64::encode(&nonce_bytes),
+            ..kdf.clone()
+        },
+        wrapped_key: base64::encode(&wrapped),
+        ephemeral_key: None,
+    })
+}
+
+/// Unwrap the data key of a single slot (fails on a wrong password)
+///
+/// Recovery slots are opened with the normalized form of `password`, so a
+/// recovery key may be typed with or without separators and in any case.
+fn open_key_slot(slot: &KeySlot, password: &str, aad: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let nonce_bytes = base64::decode(&slot.kdf.nonce)
+        .map_err(|e| format!("Invalid nonce: {}", e))?;
+    let mut wrapped = Zeroizing::new(
+        base64::decode(&slot.wrapped_key).map_err(|e| format!("Invalid wrapped key: {}", e))?,
+    );
+
+    let wrapping_key = match slot.kind {
+        KeySlotKind::Password | KeySlotKind::Recovery => {
+            let secret = match slot.kind {
+                KeySlotKind::Recovery => normalize_recovery_key(password),
+                _ => Zeroizing::new(password.to_string()),
+            };
+            let salt = base64::decode(&slot.kdf.salt)
+                .map_err(|e| format!("Invalid salt: {}", e))?;
+            Zeroizing::new(derive_key_with_params(&secret, &salt, &slot.kdf)?)
+        }
+        KeySlotKind::X25519 => {
+            let identity = parse_private_key(password)?;
+            let ephemeral = base64::decode(slot.ephemeral_key.as_deref().unwrap_or_default())
+                .map_err(|e| format!("Invalid ephemeral key: {}", e));
+            let ephemeral = PublicKey::from(key_bytes(ephemeral)?);
+            let shared = identity.diffie_hellman(&ephemeral);
+            x25519_wrapping_key(shared.as_bytes(), &ephemeral, &PublicKey::from(&identity))?
+        }
+    };
+    let nonce = Nonce::try_assume_unique_for_key(&nonce_bytes)
+        .map_err(|_| "Invalid nonce".to_string())?;
+
+    let data_key = aes_key(&wrapping_key)?
+        .open_in_place(nonce, Aad::from(aad), &mut wrapped)
+        .map_err(|_| "Decryption failed - wrong password or corrupted data".to_string())?;
+    if data_key.len() != DATA_KEY_LEN {
+        return Err("Invalid wrapped key length".to_string());
+    }
+
+    Ok(Zeroizing::new(data_key.to_vec()))
+}
+
+/// Unwrap the data key with `password` (or a recovery key or X25519 private key), trying every key slot
+///
+/// Returns the data key and the index of the slot that opened. All slot
+/// parameters are validated before any key derivation starts.
+pub fn unwrap_data_key(
+    slots: &[KeySlot],
+    password: &str,
+    aad: &[u8],
+) -> Result<(Zeroizing<Vec<u8>>, usize), String> {
+    if slots.is_empty() {
+        return Err("Archive has no key slots".to_string());
+    }
+    for slot in slots.iter().filter(|s| s.kind != KeySlotKind::X25519) {
+        validate_kdf_params(&slot.kdf)?;
+    }
+
+    for (index, slot) in slots.iter().enumerate() {
+        if let Ok(data_key) = open_key_slot(slot, password, aad) {
+            return Ok((data_key, index));
+        }
+    }
+
+    Err("Decryption failed - wrong password or corrupted data".to_string())
+}
+
+/// Replace the password slot with one for `new_password`
+///
+/// `old_secret` may be the current password or the recovery key (to reset a
+/// forgotten password). The data key itself is unchanged, so the payload does
+/// not need to be re-encrypted. Recovery slots are kept as they are.
+pub fn rekey_slots(
+    slots: &[KeySlot],
+    old_secret: &str,
+    new_password: &str,
+    strength: KdfStrength,
+    aad: &[u8],
+) -> Result<Vec<KeySlot>, String> {
+    let (data_key, index) = unwrap_data_key(slots, old_secret, aad)?;
+    let new_slot = wrap_data_key(&data_key, new_password, strength, aad)?;
+
+    let mut rekeyed = slots.to_vec();
+    if rekeyed[index].kind == KeySlotKind::Password {
+        rekeyed[index] = new_slot;
+    } else {
+        rekeyed.retain(|slot| slot.kind != KeySlotKind::Password);
+        rekeyed.insert(0, new_slot);
+    }
+    Ok(rekeyed)
+}
+
+/// Generate a new X25519 key pair, returned as (public key, private key) text
+pub fn generate_keypair() -> (String, Zeroizing<String>) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    (
+        format!("{}{}", PUBLIC_KEY_PREFIX, hex::encode(public.as_bytes())),
+        Zeroizing::new(format!("{}{}", PRIVATE_KEY_PREFIX, hex::encode_upper(secret.to_bytes()))),
+    )
+}
+
+/// Convert decoded key bytes into a 32-byte X25519 key
+fn key_bytes(bytes: Result<Vec<u8>, String>) -> Result<[u8; 32], String> {
+    let bytes = Zeroizing::new(bytes?);
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| "Invalid key length".to_string())
+}
+
+/// Parse an exported public key (`inlocker-pk-<hex>`)
+pub fn parse_public_key(text: &str) -> Result<PublicKey, String> {
+    let hex_part = text
+        .trim()
+        .strip_prefix(PUBLIC_KEY_PREFIX)
+        .ok_or_else(|| format!("Public key must start with '{}'", PUBLIC_KEY_PREFIX))?;
+    let bytes = hex::decode(hex_part).map_err(|_| "Invalid public key encoding".to_string());
+    Ok(PublicKey::from(key_bytes(bytes)?))
+}
+
+/// Parse an exported private key (`INLOCKER-SK-<hex>`)
+pub fn parse_private_key(text: &str) -> Result<StaticSecret, String> {
+    let hex_part = text
+        .trim()
+        .strip_prefix(PRIVATE_KEY_PREFIX)
+        .ok_or_else(|| "Not an InLocker private key".to_string())?;
+    let bytes = hex::decode(hex_part).map_err(|_| "Invalid private key encoding".to_string());
+    Ok(StaticSecret::from(key_bytes(bytes)?))
+}
+
+/// Public key (export form) matching a private key
+pub fn public_key_of(private_key: &str) -> Result<String, String> {
+    let public = PublicKey::from(&parse_private_key(private_key)?);
+    Ok(format!("{}{}", PUBLIC_KEY_PREFIX, hex::encode(public.as_bytes())))
+}
+
+/// HKDF-SHA256 wrapping key for an X25519 slot, bound to both public keys
+fn x25519_wrapping_key(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> Result<Zeroizing<Vec<u8>>, String> {
+    if shared.iter().all(|&b| b == 0) {
+        return Err("Invalid X25519 public key".to_string());
+    }
+
+    let mut salt = Vec::with_capacity(64);
+    salt.extend_from_slice(ephemeral.as_bytes());
+    salt.extend_from_slice(recipient.as_bytes());
+
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &salt).extract(shared);
+    let okm = prk
+        .expand(&[X25519_WRAP_INFO], hkdf::HKDF_SHA256)
+        .map_err(|_| "Key expansion failed".to_string())?;
+    let mut key = Zeroizing::new(vec![0u8; DATA_KEY_LEN]);
+    okm.fill(&mut key).map_err(|_| "Key expansion failed".to_string())?;
+    Ok(key)
+}
+
+/// Wrap `data_key` to an X25519 public key (age-style: fresh ephemeral key per slot)
+///
+/// Only the public key is needed here, so unattended backups never handle a
+/// secret; the matching private key is required to restore.
+pub fn wrap_data_key_to_recipient(data_key: &[u8], recipient: &str, aad: &[u8]) -> Result<KeySlot, String> {
+    let recipient = parse_public_key(recipient)?;
+    let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+    let shared = ephemeral_secret.diffie_hellman(&recipient);
+    let wrapping_key = x25519_wrapping_key(shared.as_bytes(), &ephemeral, &recipient)?;
+
+    let kdf = EncryptionMetadata::default();
+    let mut slot = seal_key_slot(data_key, &wrapping_key, KeySlotKind::X25519, &kdf, aad)?;
+    slot.ephemeral_key = Some(base64::encode(ephemeral.as_bytes()));
+    Ok(slot)
+}
+
+/// Recovery key set up for a backup configuration (the key itself is never stored)
+///
+/// Archives get a recovery slot wrapped under `Argon2id(recovery key, kdf.salt)`.
+/// That wrapping key is kept here encrypted under the backup password, so new
+/// archives can add a recovery slot while only the password is entered.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecoveryEscrow {
+    /// Argon2id salt and parameters that turn the recovery key into its wrapping key
+    pub kdf: EncryptionMetadata,
+    /// SHA-256 check value of the wrapping key (detects a mistyped recovery key)
+    pub key_check: String,
+    /// Recovery wrapping key, encrypted under the backup password
+    pub escrow: KeySlot,
+    /// Unix timestamp when the recovery key was generated
+    pub created_at: i64,
+}
+
+/// Generate a new recovery key, formatted for writing down (e.g. `ABCD-EFGH-...`)
+pub fn generate_recovery_key() -> Zeroizing<String> {
+    let mut bytes = Zeroizing::new(vec![0u8; RECOVERY_KEY_BYTES]);
+    OsRng.fill_bytes(&mut bytes);
+
+    let encoded = Zeroizing::new(base32::encode(&bytes));
+    let groups: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(4)
+        .map(|c| std::str::from_utf8(c).unwrap())
+        .collect();
+    Zeroizing::new(groups.join("-"))
+}
+
+/// Canonical form of a recovery key: separators and whitespace removed, upper case
+pub fn normalize_recovery_key(recovery_key: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        recovery_key
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_uppercase())
+            .collect(),
+    )
+}
+
+/// Derive the recovery wrapping key and its check value
+fn recovery_wrapping_key(recovery_key: &str, kdf: &EncryptionMetadata) -> Result<(Zeroizing<Vec<u8>>, String), String> {
+    let salt = base64::decode(&kdf.salt)
+        .map_err(|e| format!("Invalid salt: {}", e))?;
+    let key = Zeroizing::new(derive_key_with_params(&normalize_recovery_key(recovery_key), &salt, kdf)?);
+
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    context.update(RECOVERY_CHECK_LABEL);
+    context.update(&key);
+    let check = base64::encode(context.finish().as_ref());
+
+    Ok((key, check))
+}
+
+/// Set up `recovery_key` for a configuration whose archives use `password`
+///
+/// `context` (typically the config id) is authenticated with the escrowed key.
+pub fn setup_recovery(
+    recovery_key: &str,
+    password: &str,
+    strength: KdfStrength,
+    context: &[u8],
+    created_at: i64,
+) -> Result<RecoveryEscrow, String> {
+    // The recovery key is high-entropy, so the standard cost is plenty
+    let kdf = EncryptionMetadata {
+        salt: base64::encode(&generate_salt()),
+        ..EncryptionMetadata::default()
+    };
+    let (wrapping_key, key_check) = recovery_wrapping_key(recovery_key, &kdf)?;
+
+    Ok(RecoveryEscrow {
+        kdf,
+        key_check,
+        escrow: wrap_data_key(&wrapping_key, password, strength, context)?,
+        created_at,
+    })
+}
+
+/// Build the recovery slot for a new archive's data key
+///
+/// Needs the backup password to open the escrowed recovery wrapping key.
+pub fn recovery_key_slot(
+    data_key: &[u8],
+    recovery: &RecoveryEscrow,
+    password: &str,
+    context: &[u8],
+    aad: &[u8],
+) -> Result<KeySlot, String> {
+    let (wrapping_key, _) = unwrap_data_key(std::slice::from_ref(&recovery.escrow), password, context)
+        .map_err(|_| "Password does not match the one used to set up the recovery key".to_string())?;
+    seal_key_slot(data_key, &wrapping_key, KeySlotKind::Recovery, &recovery.kdf, aad)
+}
+
+/// Re-wrap the escrowed recovery wrapping key under `new_password`
+///
+/// `old_secret` may be the previous password or the recovery key itself.
+pub fn rekey_recovery_escrow(
+    recovery: &RecoveryEscrow,
+    old_secret: &str,
+    new_password: &str,
+    strength: KdfStrength,
+    context: &[u8],
+) -> Result<RecoveryEscrow, String> {
+    let wrapping_key = match unwrap_data_key(std::slice::from_ref(&recovery.escrow), old_secret, context) {
+        Ok((key, _)) => key,
+        Err(_) => {
+            let (key, check) = recovery_wrapping_key(old_secret, &recovery.kdf)?;
+            if check != recovery.key_check {
+                return Err("Decryption failed - wrong password or recovery key".to_string());
+            }
+            key
+        }
+    };
+
+    Ok(RecoveryEscrow {
+        escrow: wrap_data_key(&wrapping_key, new_password, strength, context)?,
+        ..recovery.clone()
+    })
+}
+
+/// Streaming encryptor (STREAM construction over AES-256-GCM)
+///
+/// Buffers at most one chunk of plaintext, so memory use is constant regardless
+/// of payload size. Every chunk except the last is exactly `chunk_size` bytes of
+/// plaintext; the last chunk is always shorter (possibly empty) and is sealed with
+/// the last-chunk flag set. Call `finish()` to seal the final chunk.
+///
+/// `aad` (typically the serialized archive header) is authenticated with every chunk.
+pub struct StreamEncryptor<W: Write> {
+    inner: W,
+    key: LessSafeKey,
+    aad: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+    /// Create an encryptor writing sealed chunks to `inner`, keyed from `password`
+    pub fn new(inner: W, password: &str, metadata: &EncryptionMetadata, aad: &[u8]) -> Result<Self, String> {
+        let (key, nonce_prefix) = stream_key(password, metadata)?;
+        Ok(Self::from_key(inner, key, nonce_prefix, metadata, aad))
+    }
+
+    /// Create an encryptor keyed directly with a data key (see `wrap_data_key`)
+    pub fn with_data_key(inner: W, data_key: &[u8], metadata: &EncryptionMetadata, aad: &[u8]) -> Result<Self, String> {
+        let (key, nonce_prefix) = stream_data_key(data_key, metadata)?;
+        Ok(Self::from_key(inner, key, nonce_prefix, metadata, aad))
+    }
+
+    fn from_key(
+        inner: W,
+        key: LessSafeKey,
+        nonce_prefix: [u8; NONCE_PREFIX_LEN],
+        metadata: &EncryptionMetadata,
+        aad: &[u8],
+    ) -> Self {
+        let chunk_size = metadata.chunk_size as usize;
+
+        Self {
+            inner,
+            key,
+            aad: aad.to_vec(),
+            nonce_prefix,
+            counter: 0,
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size + TAG_LEN),
+        }
+    }
+
+    /// Seal the buffered plaintext as one chunk and write it out
+    fn seal_chunk(&mut self, last: bool) -> std::io::Result<()> {
+        let nonce = stream_nonce(&self.nonce_prefix, self.counter, last);
+        self.key
+            .seal_in_place_append_tag(nonce, Aad::from(self.aad.as_slice()), &mut self.buffer)
+            .map_err(|_| std::io::Error::other("Encryption failed"))?;
+        self.inner.write_all(&self.buffer)?;
+        self.buffer.clear();
+
+        self.counter = self.counter.checked_add(1).ok_or_else(|| {
+            std::io::Error::other("Encrypted stream too large (chunk counter overflow)")
+        })?;
+        Ok(())
+    }
+
+    /// Seal the final chunk, flush, and return the underlying writer
+    pub fn finish(mut self) -> Result<W, String> {
+        self.seal_chunk(true)
+            .map_err(|e| format!("Failed to write final encrypted chunk: {}", e))?;
+        self.inner
+            .flush()
+            .map_err(|e| format!("Failed to flush encrypted data: {}", e))?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for StreamEncryptor<W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let space = self.chunk_size - self.buffer.len();
+        let take = space.min(data.len());
+        self.buffer.extend_from_slice(&data[..take]);
+
+        // A full chunk is never the last one: finish() always seals a shorter (maybe empty) chunk
+        if self.buffer.len() == self.chunk_size {
+            self.seal_chunk(false)?;
+        }
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Streaming decryptor (inverse of `StreamEncryptor`)
+///
+/// Chunks are authenticated before any of their plaintext is returned. Reading
+/// fails if chunks were reordered, modified, dropped, if the stream ends
+/// before the last-chunk flag was seen (truncation), or if `aad` differs from
+/// the associated data used at encryption (e.g. an edited archive header).
+pub struct StreamDecryptor<R: Read> {
+    inner: R,
+    key: LessSafeKey,
+    aad: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+    /// Create a decryptor reading sealed chunks from `inner`, keyed from `password`
+    ///
+    /// The first chunk is authenticated eagerly so a wrong password fails here
+    /// instead of midway through extraction.
+    pub fn new(inner: R, password: &str, metadata: &EncryptionMetadata, aad: &[u8]) -> Result<Self, String> {
+        let (key, nonce_prefix) = stream_key(password, metadata)?;
+        Self::from_key(inner, key, nonce_prefix, metadata, aad)
+    }
+
+    /// Create a decryptor keyed directly with an unwrapped data key
+    pub fn with_data_key(inner: R, data_key: &[u8], metadata: &EncryptionMetadata, aad: &[u8]) -> Result<Self, String> {
+        let (key, nonce_prefix) = stream_data_key(data_key, metadata)?;
+        Self::from_key(inner, key, nonce_prefix, metadata, aad)
+    }
+
+    fn from_key(
+        inner: R,
+        key: LessSafeKey,
+        nonce_prefix: [u8; NONCE_PREFIX_LEN],
+        metadata: &EncryptionMetadata,
+        aad: &[u8],
+    ) -> Result<Self, String> {
+        let chunk_size = metadata.chunk_size as usize;
+
+        let mut decryptor = Self {
+            inner,
+            key,
+            aad: aad.to_vec(),
+            nonce_prefix,
+            counter: 0,
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size + TAG_LEN),
+            position: 0,
+            finished: false,
+        };
+        decryptor.open_next_chunk().map_err(|e| e.to_string())?;
+
+        Ok(decryptor)
+    }
+
+    /// Read and authenticate the next sealed chunk into the buffer
+    fn open_next_chunk(&mut self) -> std::io::Result<()> {
+        let sealed_len = self.chunk_size + TAG_LEN;
+        self.buffer.resize(sealed_len, 0);
+
+        let mut filled = 0;
+        while filled < sealed_len {
+            match self.inner.read(&mut self.buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        self.buffer.truncate(filled);
+
+        // Only the final chunk may be shorter than a full sealed chunk
+        let last = filled < sealed_len;
+        if filled < TAG_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Decryption failed - encrypted stream is truncated",
+            ));
+        }
+
+        let nonce = stream_nonce(&self.nonce_prefix, self.counter, last);
+        let plaintext_len = self
+            .key
+            .open_in_place(nonce, Aad::from(self.aad.as_slice()), &mut self.buffer)
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Decryption failed - wrong password or corrupted data",
+                )
+            })?
+            .len();
+        self.buffer.truncate(plaintext_len);
+        self.position = 0;
+
+        self.counter = self.counter.checked_add(1).ok_or_else(|| {
+            std::io::Error::other("Encrypted stream too large (chunk counter overflow)")
+        })?;
+        self.finished = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StreamDecryptor<R> {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.open_next_chunk()?;
+        }
+
+        let available = &self.buffer[self.position..];
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Encrypt a stream using chunked AES-256-GCM (STREAM construction)
 ///
-/// This function processes data in 1MB chunks to reduce memory usage.
-/// While AES-GCM doesn't natively support streaming, this approach minimizes
-/// peak memory consumption by reading/encrypting in manageable chunks.
+/// Memory use is bounded by one chunk (1 MB), independent of input size.
 ///
 /// # Arguments
 /// * `input_reader` - Source data (e.g., File)
@@ -171,40 +977,41 @@ pub fn encrypt(plaintext: &[u8], password: &str) -> Result<(Vec<u8>, EncryptionM
 /// * `password` - User password
 ///
 /// # Returns
-/// Encryption metadata (salt, nonce, params)
-pub fn encrypt_streaming<R: std::io::Read, W: std::io::Write>(
+/// Encryption metadata (salt, nonce prefix, params, chunk size)
+pub fn encrypt_streaming<R: Read, W: Write>(
     mut input_reader: R,
     output_writer: W,
     password: &str,
 ) -> Result<EncryptionMetadata, String> {
-    // For AES-GCM, we need to read all data first since it's not a streaming cipher
-    // However, we can minimize memory by reading in chunks and accumulating
-    const CHUNK_SIZE: usize = 1024 * 1024; // 1 MB chunks
-    let mut plaintext = Vec::new();
-
-    loop {
-        let mut chunk = vec![0u8; CHUNK_SIZE];
-        let bytes_read = input_reader.read(&mut chunk)
-            .map_err(|e| format!("Failed to read input data: {}", e))?;
-
-        if bytes_read == 0 {
-            break;
-        }
+    let metadata = new_stream_metadata();
+    let mut encryptor = StreamEncryptor::new(output_writer, password, &metadata, &[])?;
 
-        plaintext.extend_from_slice(&chunk[..bytes_read]);
-    }
+    std::io::copy(&mut input_reader, &mut encryptor)
+        .map_err(|e| format!("Failed to encrypt data: {}", e))?;
+    encryptor.finish()?;
+
+    Ok(metadata)
+}
 
-    // Encrypt the accumulated data
-    let (ciphertext, metadata) = encrypt(&plaintext, password)?;
+/// Decrypt a stream produced by `encrypt_streaming`
+///
+/// # Returns
+/// Number of plaintext bytes written
+pub fn decrypt_streaming<R: Read, W: Write>(
+    input_reader: R,
+    mut output_writer: W,
+    password: &str,
+    metadata: &EncryptionMetadata,
+) -> Result<u64, String> {
+    let mut decryptor = StreamDecryptor::new(input_reader, password, metadata, &[])?;
 
-    // Write encrypted data to output (streaming write)
-    let mut writer = output_writer;
-    writer.write_all(&ciphertext)
-        .map_err(|e| format!("Failed to write encrypted data: {}", e))?;
-    writer.flush()
-        .map_err(|e| format!("Failed to flush encrypted data: {}", e))?;
+    let written = std::io::copy(&mut decryptor, &mut output_writer)
+        .map_err(|e| format!("{}", e))?;
+    output_writer
+        .flush()
+        .map_err(|e| format!("Failed to flush decrypted data: {}", e))?;
 
-    Ok(metadata)
+    Ok(written)
 }
 
 /// Decrypt data using AES-256-GCM
@@ -220,6 +1027,16 @@ pub fn decrypt(
     ciphertext: &[u8],
     password: &str,
     metadata: &EncryptionMetadata,
+) -> Result<Vec<u8>, String> {
+    decrypt_with_aad(ciphertext, password, metadata, &[])
+}
+
+/// Decrypt data using AES-256-GCM, verifying the associated data used at encryption
+pub fn decrypt_with_aad(
+    ciphertext: &[u8],
+    password: &str,
+    metadata: &EncryptionMetadata,
+    aad: &[u8],
 ) -> Result<Vec<u8>, String> {
     // Decode salt and nonce
     let salt = base64::decode(&metadata.salt)
@@ -227,8 +1044,8 @@ pub fn decrypt(
     let nonce_bytes = base64::decode(&metadata.nonce)
         .map_err(|e| format!("Invalid nonce: {}", e))?;
 
-    // Derive key from password
-    let mut key_bytes = derive_key(password, &salt)?;
+    // Derive key from password (using the parameters recorded at encryption)
+    let mut key_bytes = derive_key_with_params(password, &salt, metadata)?;
 
     // Create AES-256-GCM key
     let unbound_key = UnboundKey::new(&AES_256_GCM, &key_bytes)
@@ -247,7 +1064,7 @@ pub fn decrypt(
     // Decrypt in-place
     let mut plaintext = ciphertext.to_vec();
     let plaintext_len = opening_key
-        .open_in_place(Aad::empty(), &mut plaintext)
+        .open_in_place(Aad::from(aad), &mut plaintext)
         .map_err(|_| {
             "Decryption failed - wrong password or corrupted data".to_string()
         })?
@@ -260,20 +1077,28 @@ pub fn decrypt(
 
 /// Encrypt a file
 ///
-/// Reads plaintext from `input_path`, encrypts it, and writes:
-/// 1. Metadata JSON (salt, nonce, params) to `output_path.meta`
-/// 2. Encrypted data to `output_path`
+/// Streams plaintext from `input_path` through chunked AES-256-GCM and writes:
+/// 1. Metadata JSON (salt, nonce prefix, params) to `output_path.meta`
+/// 2. Encrypted chunks to `output_path`
 pub fn encrypt_file(
     input_path: &std::path::Path,
     output_path: &std::path::Path,
     password: &str,
 ) -> Result<EncryptionMetadata, String> {
-    // Read plaintext
-    let plaintext = std::fs::read(input_path)
+    let input = std::fs::File::open(input_path)
         .map_err(|e| format!("Failed to read input file: {}", e))?;
+    let output = std::fs::File::create(output_path)
+        .map_err(|e| format!("Failed to write encrypted file: {}", e))?;
 
-    // Encrypt
-    let (ciphertext, metadata) = encrypt(&plaintext, password)?;
+    // Encrypt (constant memory)
+    let metadata = encrypt_streaming(
+        std::io::BufReader::new(input),
+        std::io::BufWriter::new(output),
+        password,
+    )
+    .inspect_err(|_| {
+        let _ = std::fs::remove_file(output_path);
+    })?;
 
     // Write metadata
     let metadata_path = output_path.with_extension("meta");
@@ -282,10 +1107,6 @@ pub fn encrypt_file(
     std::fs::write(&metadata_path, metadata_json)
         .map_err(|e| format!("Failed to write metadata: {}", e))?;
 
-    // Write ciphertext
-    std::fs::write(output_path, ciphertext)
-        .map_err(|e| format!("Failed to write encrypted file: {}", e))?;
-
     log::info!("✅ File encrypted: {} → {}", input_path.display(), output_path.display());
     log::info!("   Metadata: {}", metadata_path.display());
 
@@ -295,7 +1116,8 @@ pub fn encrypt_file(
 /// Decrypt a file
 ///
 /// Reads metadata from `input_path.meta` and encrypted data from `input_path`,
-/// decrypts, and writes plaintext to `output_path`.
+/// decrypts, and writes plaintext to `output_path`. Files written before chunked
+/// encryption (`chunk_size == 0`) are decrypted in memory as before.
 pub fn decrypt_file(
     input_path: &std::path::Path,
     output_path: &std::path::Path,
@@ -308,16 +1130,30 @@ pub fn decrypt_file(
     let metadata: EncryptionMetadata = serde_json::from_str(&metadata_json)
         .map_err(|e| format!("Failed to parse metadata: {}", e))?;
 
-    // Read ciphertext
-    let ciphertext = std::fs::read(input_path)
-        .map_err(|e| format!("Failed to read encrypted file: {}", e))?;
-
-    // Decrypt
-    let plaintext = decrypt(&ciphertext, password, &metadata)?;
-
-    // Write plaintext
-    std::fs::write(output_path, plaintext)
-        .map_err(|e| format!("Failed to write decrypted file: {}", e))?;
+    if metadata.chunk_size == 0 {
+        // Legacy single-shot format
+        let ciphertext = std::fs::read(input_path)
+            .map_err(|e| format!("Failed to read encrypted file: {}", e))?;
+        let plaintext = decrypt(&ciphertext, password, &metadata)?;
+        std::fs::write(output_path, plaintext)
+            .map_err(|e| format!("Failed to write decrypted file: {}", e))?;
+    } else {
+        let input = std::fs::File::open(input_path)
+            .map_err(|e| format!("Failed to read encrypted file: {}", e))?;
+        let output = std::fs::File::create(output_path)
+            .map_err(|e| format!("Failed to write decrypted file: {}", e))?;
+
+        // Never leave partially decrypted (unauthenticated tail) output behind
+        if let Err(e) = decrypt_streaming(
+            std::io::BufReader::new(input),
+            std::io::BufWriter::new(output),
+            password,
+            &metadata,
+        ) {
+            let _ = std::fs::remove_file(output_path);
+            return Err(e);
+        }
+    }
 
     log::info!("✅ File decrypted: {} → {}", input_path.display(), output_path.display());
 
@@ -355,6 +1191,29 @@ pub fn validate_password_strength(password: &str) -> Result<(), String> {
     Ok(())
 }
 
+// Module for base32 encoding (RFC 4648 alphabet, no padding) used for recovery keys
+mod base32 {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    pub fn encode(data: &[u8]) -> String {
+        let mut s = String::new();
+        let mut buffer = 0u32;
+        let mut bits = 0;
+        for &byte in data {
+            buffer = (buffer << 8) | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                s.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            s.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+        }
+        s
+    }
+}
+
 // Module for base64 encoding/decoding
 mod base64 {
     pub fn encode(data: &[u8]) -> String {
@@ -0,0 +1,220 @@
+/// Backup job history
+///
+/// Every backup run (manual, scheduled, change-triggered or CLI; completed,
+/// failed or cancelled) is appended as one JSON line to
+/// `<app data>/history/<config_id>.jsonl`. Records are never rewritten: a crash
+/// mid-write costs at most the last line, and unreadable lines are skipped.
+///
+/// Paths are passed in, so the app and the headless CLI share this module.
+
+use crate::types::{BackupJob, BackupStatus, BackupType, HistoryPage, HistoryQuery, JobRecord, JobTrigger};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Page size of queries that don't set a limit
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+impl JobRecord {
+    /// Record of a run that produced a backup
+    pub fn completed(config_id: &str, trigger: JobTrigger, job: &BackupJob) -> Self {
+        let completed_at = job.completed_at.unwrap_or_else(|| chrono::Utc::now().timestamp());
+        Self {
+            job_id: job.id.clone(),
+            config_id: config_id.to_string(),
+            trigger,
+            status: job.status.clone(),
+            backup_type: job.backup_type.clone(),
+            started_at: job.started_at,
+            completed_at,
+            duration_secs: completed_at - job.started_at,
+            original_size: job.original_size,
+            compressed_size: job.compressed_size,
+            files_count: job.files_count,
+            changed_files_count: job.changed_files_count,
+            error_message: None,
+            backup_path: job.backup_path.clone(),
+            checksum: job.checksum.clone(),
+        }
+    }
+
+    /// Record of a run that failed (or was cancelled) after starting at `started_at`
+    pub fn failed(
+        config_id: &str,
+        trigger: JobTrigger,
+        backup_type: &BackupType,
+        started_at: i64,
+        error: &str,
+        cancelled: bool,
+    ) -> Self {
+        let completed_at = chrono::Utc::now().timestamp();
+        Self {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            config_id: config_id.to_string(),
+            trigger,
+            status: if cancelled { BackupStatus::Cancelled } else { BackupStatus::Failed },
+            backup_type: backup_type.clone(),
+            started_at,
+            completed_at,
+            duration_secs: completed_at - started_at,
+            original_size: None,
+            compressed_size: None,
+            files_count: None,
+            changed_files_count: None,
+            error_message: Some(error.to_string()),
+            backup_path: None,
+            checksum: None,
+        }
+    }
+}
+
+/// Directory holding the history files
+fn history_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("history")
+}
+
+/// History file of a config (ids are UUIDs; anything else is made filename-safe)
+fn history_path(data_dir: &Path, config_id: &str) -> PathBuf {
+    let name: String = config_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    history_dir(data_dir).join(format!("{}.jsonl", name))
+}
+
+/// Append a run to its config's history
+pub fn append(data_dir: &Path, record: &JobRecord) -> Result<(), String> {
+    let dir = history_dir(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create history directory: {}", e))?;
+
+    let line = serde_json::to_string(record).map_err(|e| format!("Failed to serialize job record: {}", e))?;
+    append_line(&history_path(data_dir, &record.config_id), &line)
+        .map_err(|e| format!("Failed to write job history: {}", e))
+}
+
+fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+
+    // Start a new line after a torn write so this record stays readable
+    let mut bytes = Vec::with_capacity(line.len() + 2);
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            bytes.push(b'\n');
+        }
+    }
+    bytes.extend_from_slice(line.as_bytes());
+    bytes.push(b'\n');
+
+    // One write per record: appends of whole lines don't interleave
+    file.write_all(&bytes)
+}
+
+/// Read all runs of one config (or of every config)
+fn read_records(data_dir: &Path, config_id: Option<&str>) -> Result<Vec<JobRecord>, String> {
+    let files = match config_id {
+        Some(id) => vec![history_path(data_dir, id)],
+        None => match fs::read_dir(history_dir(data_dir)) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+
+    let mut records = Vec::new();
+    for file in files {
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Failed to read job history: {}", e)),
+        };
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<JobRecord>(line) {
+                Ok(record) => records.push(record),
+                Err(e) => log::warn!("⚠️  Skipping unreadable history line in {:?}: {}", file, e),
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Archive paths recorded in a config's history (an import skips them)
+pub fn recorded_backup_paths(data_dir: &Path, config_id: &str) -> Result<HashSet<String>, String> {
+    Ok(read_records(data_dir, Some(config_id))?
+        .into_iter()
+        .filter_map(|record| record.backup_path)
+        .collect())
+}
+
+/// Runs matching `query`, newest first, one page at a time
+pub fn query(data_dir: &Path, query: &HistoryQuery) -> Result<HistoryPage, String> {
+    let mut records: Vec<JobRecord> = read_records(data_dir, query.config_id.as_deref())?
+        .into_iter()
+        .filter(|r| query.config_id.as_ref().is_none_or(|id| &r.config_id == id))
+        .filter(|r| query.status.as_ref().is_none_or(|status| &r.status == status))
+        .filter(|r| query.trigger.is_none_or(|trigger| r.trigger == trigger))
+        .filter(|r| query.since.is_none_or(|since| r.started_at >= since))
+        .filter(|r| query.until.is_none_or(|until| r.started_at < until))
+        .collect();
+    records.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+
+    let total = records.len();
+    let limit = if query.limit == 0 { DEFAULT_PAGE_SIZE } else { query.limit };
+    let records = records.into_iter().skip(query.offset).take(limit).collect();
+
+    Ok(HistoryPage { records, total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(config_id: &str, started_at: i64, status: BackupStatus, trigger: JobTrigger) -> JobRecord {
+        let mut record = JobRecord::failed(config_id, trigger, &BackupType::Full, started_at, "boom", false);
+        record.status = status;
+        record
+    }
+
+    #[test]
+    fn test_append_and_query() {
+        let dir = std::env::temp_dir().join(format!("inlocker_history_{}", uuid::Uuid::new_v4()));
+
+        for (i, status) in [BackupStatus::Completed, BackupStatus::Failed, BackupStatus::Completed].into_iter().enumerate() {
+            append(&dir, &record("a", 100 + i as i64, status, JobTrigger::Scheduled)).unwrap();
+        }
+        append(&dir, &record("b", 200, BackupStatus::Cancelled, JobTrigger::Manual)).unwrap();
+
+        // Newest first, across configs
+        let all = query(&dir, &HistoryQuery::default()).unwrap();
+        assert_eq!(all.total, 4);
+        assert_eq!(all.records.iter().map(|r| r.started_at).collect::<Vec<_>>(), vec![200, 102, 101, 100]);
+
+        // Filters and paging
+        let page = query(
+            &dir,
+            &HistoryQuery { config_id: Some("a".into()), status: Some(BackupStatus::Completed), limit: 1, offset: 1, ..Default::default() },
+        )
+        .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.records.len(), 1);
+        assert_eq!(page.records[0].started_at, 100);
+
+        let manual = query(&dir, &HistoryQuery { trigger: Some(JobTrigger::Manual), ..Default::default() }).unwrap();
+        assert_eq!(manual.total, 1);
+        assert_eq!(manual.records[0].status, BackupStatus::Cancelled);
+
+        // A torn last line (crash mid-write) doesn't hide the rest
+        let mut file = OpenOptions::new().append(true).open(history_path(&dir, "a")).unwrap();
+        file.write_all(b"{\"job_id\":\"trunc").unwrap();
+        append(&dir, &record("a", 103, BackupStatus::Completed, JobTrigger::Scheduled)).unwrap();
+        assert_eq!(query(&dir, &HistoryQuery { config_id: Some("a".into()), ..Default::default() }).unwrap().total, 4);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
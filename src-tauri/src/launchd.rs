@@ -1,3 +1,4 @@
+use crate::cron::{CronField, CronSchedule};
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
@@ -118,7 +119,7 @@ pub fn get_plist_path(config_id: &str) -> Result<PathBuf, String> {
 }
 
 /// Get the label for a launch agent
-fn get_agent_label(config_id: &str) -> String {
+pub fn get_agent_label(config_id: &str) -> String {
     format!("com.inlocker.backup.{}", config_id)
 }
 
@@ -181,143 +182,68 @@ pub fn is_agent_loaded(config_id: &str) -> Result<bool, String> {
     Ok(stdout.contains(&label))
 }
 
+/// Upper bound on generated StartCalendarInterval dicts (e.g. "0-58 * 1-30 * *" would need thousands)
+const MAX_CALENDAR_INTERVALS: usize = 5000;
+
 /// Parse cron expression and convert to launchd StartCalendarInterval
-/// Cron format: "minute hour day month weekday"
+/// Cron format: "minute hour day month weekday" (full grammar, see cron.rs)
 /// NOTE: launchd uses LOCAL timezone, no conversion needed
-/// Returns Vec of calendar intervals (can have multiple for complex schedules)
+/// Returns Vec of calendar intervals: the full cross-product of the restricted fields
+/// (unrestricted fields are omitted, which launchd treats as a wildcard)
 fn parse_cron_to_calendar_interval(cron_expr: &str) -> Result<Vec<CalendarInterval>, String> {
-    let parts: Vec<&str> = cron_expr.split_whitespace().collect();
-
-    if parts.len() != 5 {
-        return Err(format!(
-            "Invalid cron expression: expected 5 fields, got {}",
-            parts.len()
-        ));
-    }
-
-    let minute = parts[0];
-    let hour = parts[1];
-    let day = parts[2];
-    let month = parts[3];
-    let weekday = parts[4];
-
-    // Simple case: specific time (e.g., "0 2 * * *" = daily at 2:00 AM)
-    let mut intervals = Vec::new();
+    let schedule = CronSchedule::parse(cron_expr)?;
 
-    // Parse minute and hour (input is in LOCAL timezone)
-    let minutes = parse_cron_field(minute, 0, 59)?;
-    let hours = parse_cron_field(hour, 0, 23)?;
-    let days = if day == "*" {
-        vec![]
-    } else {
-        parse_cron_field(day, 1, 31)?
-    };
-    let months = if month == "*" {
-        vec![]
-    } else {
-        parse_cron_field(month, 1, 12)?
+    // A full field becomes a missing key (wildcard) instead of one dict per value
+    let expand = |field: &CronField| -> Vec<Option<i32>> {
+        if field.is_full() {
+            vec![None]
+        } else {
+            field.values().iter().map(|&v| Some(v as i32)).collect()
+        }
     };
-    let weekdays = if weekday == "*" {
-        vec![]
+
+    // Keys in one launchd dict must ALL match, while cron ORs a restricted day
+    // and a restricted weekday: emit separate day-only and weekday-only dicts then
+    let day_weekday: Vec<(Option<i32>, Option<i32>)> = if schedule.days_or_weekdays() {
+        expand(&schedule.days)
+            .into_iter()
+            .map(|d| (d, None))
+            .chain(expand(&schedule.weekdays).into_iter().map(|w| (None, w)))
+            .collect()
     } else {
-        parse_cron_field(weekday, 0, 6)?
+        expand(&schedule.days)
+            .into_iter()
+            .flat_map(|d| expand(&schedule.weekdays).into_iter().map(move |w| (d, w)))
+            .collect()
     };
 
-    // Generate all combinations
-    if minutes.is_empty() || hours.is_empty() {
-        return Err("Minute and Hour must be specified".to_string());
-    }
-
-    for &m in &minutes {
-        for &h in &hours {
-            // launchd uses LOCAL timezone, NOT UTC
-            // No conversion needed - just use the hour/minute as specified
-            log::info!(
-                "Scheduling backup at LOCAL time: {}:{:02}",
-                h, m
-            );
-
-            let interval = CalendarInterval {
-                minute: Some(m),
-                hour: Some(h),
-                day: if days.is_empty() { None } else { Some(days[0]) },
-                month: if months.is_empty() {
-                    None
-                } else {
-                    Some(months[0])
-                },
-                weekday: if weekdays.is_empty() {
-                    None
-                } else {
-                    Some(weekdays[0])
-                },
-            };
-            intervals.push(interval);
-        }
-    }
-
-    if intervals.is_empty() {
-        return Err("No valid intervals generated from cron expression".to_string());
-    }
+    let minutes = expand(&schedule.minutes);
+    let hours = expand(&schedule.hours);
+    let months = expand(&schedule.months);
 
-    Ok(intervals)
-}
-
-/// Parse a single cron field (supports *, specific values, and ranges)
-fn parse_cron_field(field: &str, min: i32, max: i32) -> Result<Vec<i32>, String> {
-    if field == "*" {
-        // For *, we don't expand - handled separately
-        return Ok(vec![]);
+    let total = minutes.len() * hours.len() * months.len() * day_weekday.len();
+    if total > MAX_CALENDAR_INTERVALS {
+        return Err(format!(
+            "Cron expression '{}' needs {} launchd calendar entries (maximum {}); simplify the schedule",
+            cron_expr, total, MAX_CALENDAR_INTERVALS
+        ));
     }
 
-    if field.contains(',') {
-        // List: "1,5,10"
-        return field
-            .split(',')
-            .map(|s| {
-                s.parse::<i32>()
-                    .map_err(|e| format!("Invalid number: {}", e))
-                    .and_then(|n| {
-                        if n >= min && n <= max {
-                            Ok(n)
-                        } else {
-                            Err(format!("Value {} out of range {}-{}", n, min, max))
-                        }
-                    })
-            })
-            .collect();
-    }
+    // launchd uses LOCAL timezone, NOT UTC - just use the fields as specified
+    log::info!("Scheduling backup at LOCAL time for '{}' ({} calendar entries)", cron_expr, total);
 
-    if field.contains('-') {
-        // Range: "1-5"
-        let parts: Vec<&str> = field.split('-').collect();
-        if parts.len() != 2 {
-            return Err(format!("Invalid range: {}", field));
-        }
-        let start: i32 = parts[0]
-            .parse()
-            .map_err(|e| format!("Invalid range start: {}", e))?;
-        let end: i32 = parts[1]
-            .parse()
-            .map_err(|e| format!("Invalid range end: {}", e))?;
-
-        if start < min || end > max || start > end {
-            return Err(format!("Invalid range {}-{}", start, end));
+    let mut intervals = Vec::with_capacity(total);
+    for &minute in &minutes {
+        for &hour in &hours {
+            for &month in &months {
+                for &(day, weekday) in &day_weekday {
+                    intervals.push(CalendarInterval { minute, hour, day, month, weekday });
+                }
+            }
         }
-
-        return Ok((start..=end).collect());
-    }
-
-    // Single value
-    let val: i32 = field
-        .parse()
-        .map_err(|e| format!("Invalid number: {}", e))?;
-
-    if val < min || val > max {
-        return Err(format!("Value {} out of range {}-{}", val, min, max));
     }
 
-    Ok(vec![val])
+    Ok(intervals)
 }
 
 #[derive(Debug)]
@@ -617,3 +543,111 @@ pub fn is_agent_installed(config_id: &str) -> bool {
         .map(|p| p.exists())
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    impl CalendarInterval {
+        /// launchd semantics: every key present in the dict must match
+        fn matches(&self, minute: i32, hour: i32, day: i32, month: i32, weekday: i32) -> bool {
+            self.minute.is_none_or(|m| m == minute)
+                && self.hour.is_none_or(|h| h == hour)
+                && self.day.is_none_or(|d| d == day)
+                && self.month.is_none_or(|m| m == month)
+                && self.weekday.is_none_or(|w| w == weekday)
+        }
+    }
+
+    /// Random cron field: wildcard, value, range, step or a list of them
+    fn random_field(rng: &mut StdRng, min: u32, max: u32, names: &[&str]) -> String {
+        let item = |rng: &mut StdRng| -> String {
+            let a = rng.gen_range(min..=max);
+            let b = rng.gen_range(a..=max);
+            let step = rng.gen_range(1..=(max - min + 1).min(15));
+            match rng.gen_range(0..6) {
+                0 => "*".to_string(),
+                1 => format!("*/{}", step),
+                2 => format!("{}-{}/{}", a, b, step),
+                3 => format!("{}-{}", a, b),
+                4 if !names.is_empty() => names[(a - min) as usize % names.len()].to_string(),
+                _ => a.to_string(),
+            }
+        };
+        match rng.gen_range(0..4) {
+            0 => (0..rng.gen_range(2..4)).map(|_| item(rng)).collect::<Vec<_>>().join(","),
+            _ => item(rng),
+        }
+    }
+
+    #[test]
+    fn test_weekday_ranges_are_not_truncated() {
+        // Used to run only on Mondays
+        let intervals = parse_cron_to_calendar_interval("0 9 * * 1-5").unwrap();
+        let weekdays: Vec<i32> = intervals.iter().filter_map(|i| i.weekday).collect();
+        assert_eq!(weekdays, vec![1, 2, 3, 4, 5]);
+        assert!(intervals.iter().all(|i| i.minute == Some(0) && i.hour == Some(9) && i.day.is_none()));
+
+        let every_quarter = parse_cron_to_calendar_interval("*/15 * * * *").unwrap();
+        assert_eq!(every_quarter.len(), 4);
+        assert!(every_quarter.iter().all(|i| i.hour.is_none()));
+
+        assert_eq!(parse_cron_to_calendar_interval("@daily").unwrap().len(), 1);
+        let every_minute = parse_cron_to_calendar_interval("* * * * *").unwrap();
+        assert_eq!(every_minute[0].to_xml().matches("<key>").count(), 0);
+    }
+
+    #[test]
+    fn test_calendar_intervals_match_cron_exactly() {
+        // Property: for random expressions, a time is covered by the launchd dicts
+        // if and only if the cron schedule fires at it (no combination dropped or added)
+        let mut rng = StdRng::seed_from_u64(0x1A7C4);
+        let mut checked = 0;
+
+        while checked < 300 {
+            let expr = format!(
+                "{} {} {} {} {}",
+                random_field(&mut rng, 0, 59, &[]),
+                random_field(&mut rng, 0, 23, &[]),
+                random_field(&mut rng, 1, 31, &[]),
+                random_field(&mut rng, 1, 12, &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"]),
+                random_field(&mut rng, 0, 7, &["sun", "mon", "tue", "wed", "thu", "fri", "sat", "sun"]),
+            );
+            let schedule = CronSchedule::parse(&expr).unwrap_or_else(|e| panic!("'{}' should parse: {}", expr, e));
+            let intervals = match parse_cron_to_calendar_interval(&expr) {
+                Ok(intervals) => intervals,
+                Err(e) if e.contains("calendar entries") => continue, // Too large for launchd
+                Err(e) => panic!("'{}' failed: {}", expr, e),
+            };
+            checked += 1;
+
+            // Half of the samples are drawn from the schedule's own values, to hit firing times often
+            let pick = |rng: &mut StdRng, field: &CronField, min: u32, max: u32| -> u32 {
+                if rng.gen_bool(0.5) {
+                    field.values()[rng.gen_range(0..field.values().len())]
+                } else {
+                    rng.gen_range(min..=max)
+                }
+            };
+            for _ in 0..2000 {
+                let minute = pick(&mut rng, &schedule.minutes, 0, 59);
+                let hour = pick(&mut rng, &schedule.hours, 0, 23);
+                let day = pick(&mut rng, &schedule.days, 1, 31);
+                let month = pick(&mut rng, &schedule.months, 1, 12);
+                let weekday = pick(&mut rng, &schedule.weekdays, 0, 6);
+
+                let expected = schedule.matches(minute, hour, day, month, weekday);
+                let covered = intervals.iter().any(|i| {
+                    i.matches(minute as i32, hour as i32, day as i32, month as i32, weekday as i32)
+                });
+                assert_eq!(
+                    covered, expected,
+                    "'{}' at {:02}:{:02} day {} month {} weekday {}",
+                    expr, hour, minute, day, month, weekday
+                );
+            }
+        }
+    }
+}
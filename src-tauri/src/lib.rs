@@ -1,8 +1,22 @@
 pub mod backup;
+pub mod browse;
+pub mod catalog;
+pub mod chain;
+mod cli;
+pub mod container;
+pub mod cron;
 pub mod crypto;
+pub mod history;
+pub mod retention;
+pub mod secrets;
+pub mod selection;
 mod commands;
 mod launchd;
+mod schedule_backend;
+pub mod schedule_policy;
 mod scheduler;
+mod systemd;
+mod watcher;
 pub mod types;
 
 use commands::AppState;
@@ -29,6 +43,11 @@ pub fn run() {
     let args: Vec<String> = std::env::args().collect();
     log::info!("App started with args: {:?}", args);
 
+    // Headless commands (backup, restore, list, verify, configs) never start Tauri
+    if let Some(command) = cli::parse(&args) {
+        std::process::exit(cli::run(command));
+    }
+
     // Detect CLI mode for scheduled backup
     let cli_backup_config_id = if args.len() >= 3 && args[1] == "--backup" {
         Some(args[2].clone())
@@ -137,10 +156,14 @@ pub fn run() {
             commands::delete_config,
             commands::run_backup_now,
             commands::cancel_backup,
+            commands::query_job_history,
+            commands::prune_backups,
             commands::cancel_restore,
             commands::register_schedule,
             commands::unregister_schedule,
+            commands::preview_schedule,
             commands::check_schedule_status,
+            commands::list_scheduled_jobs,
             commands::diagnose_schedule,
             commands::test_schedule_now,
             commands::is_scheduled_mode,
@@ -148,6 +171,14 @@ pub fn run() {
             commands::verify_backup_exists,
             commands::list_available_backups,
             commands::restore_backup,
+            commands::list_archive_entries,
+            commands::restore_point_in_time,
+            commands::import_destination,
+            commands::rekey_backup,
+            commands::create_recovery_key,
+            commands::generate_recipient_keypair,
+            commands::validate_recipient_key,
+            commands::test_secret_source,
             commands::load_preferences,
             commands::save_preferences,
         ])
@@ -264,7 +295,7 @@ pub fn run() {
         .expect("error while running tauri application");
 }
 
-/// Run a scheduled backup (triggered by launchd via CLI args)
+/// Run a scheduled backup (triggered by launchd or a systemd timer via CLI args)
 async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result<(), String> {
     use tauri::Manager;
 
@@ -289,6 +320,20 @@ async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result
         return Err("No configs file found".to_string());
     };
 
+    // OS agents replay missed runs (launchd on wake, systemd after boot): a late trigger is a catch-up, subject to the policy
+    if let Some(schedule_config) = config.schedule.as_ref() {
+        let schedule = cron::CronSchedule::parse(&schedule_config.cron_expression)?;
+        let now = chrono::Local::now();
+        if !schedule_policy::is_on_time(&schedule, &now) {
+            let last_run = schedule_policy::last_run_reference(&config);
+            if !schedule_policy::catch_up_due(&schedule, &schedule_config.catch_up, last_run, &now) {
+                log::info!("⏭️  Late trigger for {} and no catch-up due ({:?}); skipping", config.name, schedule_config.catch_up);
+                return Ok(());
+            }
+            log::info!("⏰ Late trigger for {}: running catch-up backup", config.name);
+        }
+    }
+
     log::info!("Running backup for: {}", config.name);
 
     // Emit progress event: Initializing
@@ -305,6 +350,81 @@ async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result
         &format!("Starting backup: {}", config.name),
     );
 
+    // Retry transient failures (e.g. destination volume not mounted yet) per the schedule's policy
+    let retry = config.schedule.as_ref().map(|s| s.retry.clone()).unwrap_or_default();
+    let (result, attempts) = schedule_policy::run_with_retry(&retry, &config, |attempt| {
+        if attempt > 1 {
+            let _ = app.emit("backup:progress", serde_json::json!({
+                "stage": "initializing",
+                "message": format!("Retrying backup (attempt {} of {})", attempt, retry.max_attempts),
+                "percentage": 0
+            }));
+        }
+        let config = &config;
+        async move {
+            let started_at = chrono::Utc::now().timestamp();
+            let result = run_scheduled_attempt(app, config).await;
+            let record = match &result {
+                Ok(job) => types::JobRecord::completed(config_id, types::JobTrigger::Scheduled, job),
+                Err(e) => types::JobRecord::failed(config_id, types::JobTrigger::Scheduled, &config.backup_type, started_at, e, false),
+            };
+            commands::record_history(app, &record);
+            result
+        }
+    })
+    .await;
+
+    if let Err(e) = commands::record_scheduled_run(app, config_id, result.as_ref().ok(), attempts) {
+        log::warn!("Failed to record scheduled run: {}", e);
+    }
+
+    match result {
+        Ok(job) => {
+            // Send success notification
+            let files_count = job.files_count.unwrap_or(0);
+            let size_mb = job.compressed_size.unwrap_or(0) as f64 / 1_048_576.0;
+
+            // Emit progress event: Completed
+            let _ = app.emit("backup:progress", serde_json::json!({
+                "stage": "completed",
+                "message": format!("Backup completed! {} files ({:.1} MB)", files_count, size_mb),
+                "percentage": 100,
+                "files_processed": files_count,
+                "total_files": files_count
+            }));
+
+            send_notification(
+                app,
+                "InLocker - Backup Completed ✓",
+                &format!(
+                    "{}: {} files backed up ({:.1} MB)",
+                    config.name, files_count, size_mb
+                ),
+            );
+
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Backup failed: {}", e);
+
+            // Send error notification (once, after the last attempt)
+            send_notification(
+                app,
+                "InLocker - Backup Failed ✗",
+                &format!("{}: {}", config.name, e),
+            );
+
+            Err(format!("Backup failed: {}", e))
+        }
+    }
+}
+
+/// One attempt of a scheduled backup: back up, then update the manifest
+async fn run_scheduled_attempt(app: &tauri::AppHandle, config: &BackupConfig) -> Result<types::BackupJob, String> {
+    use tauri::Manager;
+
+    let config_id = config.id.as_str();
+
     // Emit progress event: Scanning
     let _ = app.emit("backup:progress", serde_json::json!({
         "stage": "scanning",
@@ -314,7 +434,7 @@ async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result
 
     // Execute backup using the backup module directly
     use crate::backup;
-    use crate::types::{BackupManifest, BackupType};
+    use crate::types::BackupType;
     use std::path::Path;
 
     let source_path = Path::new(&config.source_path);
@@ -327,9 +447,21 @@ async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result
         .map_err(|e| format!("Failed to get app data dir: {}", e))?
         .join(format!("manifest_{}.json", config_id));
 
+    // Unattended: the password comes from the config's secret provider, if any;
+    // otherwise encrypted configs rely on their recipient public keys
+    let password = match (&config.mode, &config.password_source) {
+        (types::BackupMode::Encrypted, Some(source)) => {
+            log::info!("🔐 Fetching password from {}", source.describe());
+            Some(secrets::resolve_secret(source)?)
+        }
+        _ => None,
+    };
+    backup::check_incremental_supported(&config.mode, &config.backup_type, password.as_ref().map(|p| p.as_str()))?;
+
     let previous_manifest = if manifest_path.exists() && config.backup_type == BackupType::Incremental {
-        let json = std::fs::read_to_string(&manifest_path).ok();
-        json.and_then(|j| serde_json::from_str::<BackupManifest>(&j).ok())
+        backup::load_manifest(&manifest_path, password.as_ref().map(|p| p.as_str()))
+            .map_err(|e| log::warn!("Previous manifest unavailable, running a full backup: {}", e))
+            .ok()
     } else {
         None
     };
@@ -341,10 +473,15 @@ async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result
         "percentage": 30
     }));
 
+    let options = backup::BackupOptions {
+        kdf_strength: commands::resolve_kdf_strength(app, config),
+        recovery: config.recovery.clone(),
+        recipients: config.recipients.clone(),
+    };
+
     // Perform backup
-    // TODO: Add password parameter when CLI supports it
-    match backup::compress_folder(
-        &config_id,
+    let job = backup::compress_folder_with_options(
+        config_id,
         &config.name,
         source_path,
         dest_path,
@@ -352,68 +489,37 @@ async fn run_scheduled_backup(app: &tauri::AppHandle, config_id: &str) -> Result
         &config.mode,
         previous_manifest.as_ref(),
         Some(app),
-        None, // No encryption for CLI mode yet
+        password.as_ref().map(|p| p.as_str()),
         None, // No cancellation support for scheduled backups
-    ) {
-        Ok(job) => {
-            // Emit progress event: Finalizing
-            let _ = app.emit("backup:progress", serde_json::json!({
-                "stage": "finalizing",
-                "message": "Finalizing backup...",
-                "percentage": 90
-            }));
-            log::info!("Backup completed: {} files, {} bytes",
-                job.files_count.unwrap_or(0),
-                job.compressed_size.unwrap_or(0)
-            );
+        &options,
+    )?;
 
-            // Update manifest
-            if let Ok((all_files, _)) = backup::scan_all_files(source_path) {
-                if let Ok(new_manifest) = backup::build_manifest(&config_id, &all_files, source_path) {
-                    if let Ok(manifest_json) = serde_json::to_string_pretty(&new_manifest) {
-                        let _ = std::fs::write(&manifest_path, manifest_json);
-                    }
-                }
-            }
-
-            // Send success notification
-            let files_count = job.files_count.unwrap_or(0);
-            let size_mb = job.compressed_size.unwrap_or(0) as f64 / 1_048_576.0;
-
-            // Emit progress event: Completed
-            let _ = app.emit("backup:progress", serde_json::json!({
-                "stage": "completed",
-                "message": format!("Backup completed! {} files ({:.1} MB)", files_count, size_mb),
-                "percentage": 100,
-                "files_processed": files_count,
-                "total_files": files_count
-            }));
-
-            send_notification(
-                app,
-                "InLocker - Backup Completed ✓",
-                &format!(
-                    "{}: {} files backed up ({:.1} MB)",
-                    config.name, files_count, size_mb
-                ),
-            );
+    // Emit progress event: Finalizing
+    let _ = app.emit("backup:progress", serde_json::json!({
+        "stage": "finalizing",
+        "message": "Finalizing backup...",
+        "percentage": 90
+    }));
+    log::info!("Backup completed: {} files, {} bytes",
+        job.files_count.unwrap_or(0),
+        job.compressed_size.unwrap_or(0)
+    );
 
-            // Note: last_backup_at will be updated next time the app opens normally
-            Ok(())
-        }
-        Err(e) => {
-            log::error!("Backup failed: {}", e);
+    // Update manifest (encrypted for encrypted configs)
+    if let Err(e) = backup::update_manifest(
+        &manifest_path,
+        config_id,
+        source_path,
+        &config.mode,
+        password.as_ref().map(|p| p.as_str()),
+        &options,
+    ) {
+        log::error!("Failed to save manifest: {}", e);
+    }
 
-            // Send error notification
-            send_notification(
-                app,
-                "InLocker - Backup Failed ✗",
-                &format!("{}: {}", config.name, e),
-            );
+    retention::prune_after_backup(config);
 
-            Err(format!("Backup failed: {}", e))
-        }
-    }
+    Ok(job)
 }
 
 /// Send a native macOS notification
@@ -437,27 +543,38 @@ async fn run_scheduled_backup_static(app: &tauri::AppHandle, config_id: &str) ->
 }
 
 /// Restore all schedules on app startup
+///
+/// OS agents (launchd/systemd) persist on their own; configs without a loaded
+/// agent, or that opted for in-app scheduling, get an in-app job. Missed fire
+/// times are caught up either way (see `scheduler::run_agent_catch_up_if_due`).
 async fn restore_schedules(app: tauri::AppHandle) -> Result<(), String> {
     use tauri::Manager;
 
-    let state = app.state::<AppState>();
     let scheduler_state = app.state::<SchedulerState>();
 
-    // Load configs and clone them
-    let configs_to_restore: Vec<BackupConfig> = {
-        let configs = state.configs.lock().map_err(|e| e.to_string())?;
-        configs
-            .iter()
-            .filter(|c| {
-                c.schedule.as_ref().map(|s| s.enabled).unwrap_or(false)
-            })
-            .cloned()
-            .collect()
-    }; // Lock is released here
+    // Configs aren't in AppState until loaded (the UI may not have asked yet)
+    let configs = commands::load_configs(app.clone(), app.state::<AppState>()).await?;
+    let configs_to_restore: Vec<BackupConfig> = configs
+        .into_iter()
+        .filter(|c| c.schedule.as_ref().map(|s| s.enabled).unwrap_or(false))
+        .collect();
 
     // Restore schedules
     for config in configs_to_restore {
-        log::info!("Restoring schedule for config: {}", config.id);
+        // The OS agent may replay missed fire times itself (launchd after sleep,
+        // systemd after boot); catch up later only if it didn't, so the same
+        // missed window isn't backed up twice
+        let in_app = config.schedule.as_ref().is_some_and(|s| s.in_app);
+        if !in_app && scheduler::os_agent_active(&config.id) {
+            log::info!("Schedule for {} is run by the OS agent", config.id);
+            tauri::async_runtime::spawn(scheduler::run_agent_catch_up_if_due(app.clone(), config.id.clone()));
+            continue;
+        }
+
+        // Fire times missed while the app/computer was off (per the catch-up policy)
+        tauri::async_runtime::spawn(scheduler::run_catch_up_if_due(app.clone(), config.clone()));
+
+        log::info!("Restoring in-app schedule for config: {}", config.id);
         match scheduler_state.register_schedule(app.clone(), config.clone()).await {
             Ok(_) => log::info!("Schedule restored for: {}", config.id),
             Err(e) => log::error!("Failed to restore schedule for {}: {}", config.id, e),
@@ -0,0 +1,554 @@
+/// Retention and pruning of old archives
+///
+/// Archives of a config are found in its destination by file name
+/// (`Bkp_InLocker_<name>_<full|incr>_<YYYYmmdd_HHMMSS>[.tar.zst[.enc]]`), then
+/// told apart from other configs writing under the same (sanitized) name by the
+/// config id they record. Files of other configs sharing the destination, or
+/// written under an older config name, are never touched; if an archive records
+/// no id while another config writes under the same name, pruning refuses.
+///
+/// An incremental archive only holds changes since the previous archive, so it
+/// depends on every archive back to the last full one. Keeping an incremental
+/// keeps that whole chain, and the size limit only removes complete chains.
+
+use crate::backup::{self, sanitize_filename};
+use crate::container::ContainerHeader;
+use crate::types::{BackupConfig, RetentionPolicy};
+use chrono::{Datelike, NaiveDateTime, TimeZone};
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+const ARCHIVE_PREFIX: &str = "Bkp_InLocker_";
+
+/// Calendar period of an archive time, for grandfather-father-son rules
+type PeriodOf = fn(&NaiveDateTime) -> (i32, u32);
+
+/// Full or incremental, as recorded in the archive name
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveKind {
+    Full,
+    Incremental,
+}
+
+/// An archive (file, or folder for copy mode) of a config
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub filename: String,
+    pub path: PathBuf,
+    pub kind: ArchiveKind,
+    /// Local time from the archive name
+    pub created: NaiveDateTime,
+    pub size: u64,
+}
+
+/// What prune did (or would do) with one archive
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PruneDecision {
+    pub filename: String,
+    pub path: String,
+    pub kind: ArchiveKind,
+    pub created_at: i64,
+    pub size: u64,
+    /// Rules that keep the archive (empty for removed archives)
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PruneReport {
+    pub config_id: String,
+    pub dry_run: bool,
+    pub kept: Vec<PruneDecision>,
+    pub removed: Vec<PruneDecision>,
+    /// Bytes removed (or that would be removed in a dry run)
+    pub freed_bytes: u64,
+    /// Archives that could not be deleted
+    pub errors: Vec<String>,
+}
+
+impl ArchiveEntry {
+    /// Archive id (name without extension), as recorded in `ArchiveInfo`
+    pub fn archive_id(&self) -> &str {
+        archive_id(&self.filename)
+    }
+
+    /// Unix timestamp of the archive name's local time
+    pub fn created_at(&self) -> i64 {
+        chrono::Local
+            .from_local_datetime(&self.created)
+            .earliest()
+            .map(|t| t.timestamp())
+            .unwrap_or_else(|| self.created.and_utc().timestamp())
+    }
+}
+
+/// Archive id of an archive file or folder name (the name without extension)
+pub fn archive_id(filename: &str) -> &str {
+    filename
+        .strip_suffix(".tar.zst.enc")
+        .or_else(|| filename.strip_suffix(".tar.zst"))
+        .unwrap_or(filename)
+}
+
+/// Parse `Bkp_InLocker_<name>_<kind>_<date>_<time>[ext]` into (name, kind, time)
+fn parse_archive_name(filename: &str) -> Option<(&str, ArchiveKind, NaiveDateTime)> {
+    let stem = archive_id(filename).strip_prefix(ARCHIVE_PREFIX)?;
+
+    let mut parts = stem.rsplitn(4, '_');
+    let (time, date, kind, name) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let kind = match kind {
+        "full" => ArchiveKind::Full,
+        "incr" => ArchiveKind::Incremental,
+        _ => return None,
+    };
+    let created = NaiveDateTime::parse_from_str(&format!("{}_{}", date, time), "%Y%m%d_%H%M%S").ok()?;
+    Some((name, kind, created))
+}
+
+fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| match entry.file_type() {
+                    Ok(t) if t.is_dir() => dir_size(&entry.path()),
+                    _ => entry.metadata().map(|m| m.len()).unwrap_or(0),
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Config name of an archive as written in its file name (sanitized, see `sanitize_filename`)
+pub fn archive_config_name(filename: &str) -> Option<&str> {
+    parse_archive_name(filename).map(|(name, _, _)| name)
+}
+
+/// Archives of a config in its destination, oldest first
+pub fn scan_archives(destination: &Path, config_name: &str) -> Result<Vec<ArchiveEntry>, String> {
+    let wanted = sanitize_filename(config_name);
+    scan_destination_matching(destination, |name| name == wanted)
+}
+
+/// Config id recorded in an archive, read without a password
+///
+/// Encrypted archives carry it in their cleartext header, others in their
+/// `ArchiveInfo`. None for archives written before ids were recorded.
+pub fn recorded_config_id(path: &Path) -> Option<String> {
+    if path.is_file() {
+        let header = fs::File::open(path)
+            .ok()
+            .and_then(|file| ContainerHeader::read_from(&mut BufReader::new(file)).ok());
+        if let Some(header) = header {
+            return header.body.config_id;
+        }
+    }
+    backup::read_archive_info(path, None).ok().flatten().map(|info| info.config_id)
+}
+
+/// Archives of `config` in its destination, oldest first
+///
+/// Archives with its name that record another config's id are left out. Errors
+/// when archives recording no id can't be attributed, because another config
+/// also writes under this name.
+pub fn config_archives(config: &BackupConfig) -> Result<Vec<ArchiveEntry>, String> {
+    archives_of(Path::new(&config.destination_path), &config.id, &config.name)
+}
+
+/// Archives of the config `config_id` named `config_name` in `destination` (see `config_archives`)
+pub fn archives_of(destination: &Path, config_id: &str, config_name: &str) -> Result<Vec<ArchiveEntry>, String> {
+    let mut archives = Vec::new();
+    let mut unrecorded = Vec::new();
+    let mut other_config = false;
+    for archive in scan_archives(destination, config_name)? {
+        match recorded_config_id(&archive.path) {
+            Some(id) if id == config_id => archives.push(archive),
+            Some(_) => other_config = true,
+            None => {
+                unrecorded.push(archive.filename.clone());
+                archives.push(archive);
+            }
+        }
+    }
+
+    if other_config && !unrecorded.is_empty() {
+        return Err(format!(
+            "Another config also writes archives named {:?} to this destination; can't tell whose these are: {}",
+            sanitize_filename(config_name),
+            unrecorded.join(", ")
+        ));
+    }
+    Ok(archives)
+}
+
+/// Archives of every config in a destination, oldest first
+pub fn scan_destination(destination: &Path) -> Result<Vec<ArchiveEntry>, String> {
+    scan_destination_matching(destination, |_| true)
+}
+
+fn scan_destination_matching<F: Fn(&str) -> bool>(destination: &Path, wanted: F) -> Result<Vec<ArchiveEntry>, String> {
+    let mut archives = Vec::new();
+    if !destination.exists() {
+        return Ok(archives);
+    }
+
+    for entry in fs::read_dir(destination).map_err(|e| format!("Failed to read destination directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let filename = entry.file_name().to_string_lossy().to_string();
+        let Some((name, kind, created)) = parse_archive_name(&filename) else {
+            continue;
+        };
+        if !wanted(name) {
+            continue;
+        }
+
+        let path = entry.path();
+        let size = if path.is_dir() {
+            dir_size(&path)
+        } else {
+            entry.metadata().map(|m| m.len()).unwrap_or(0)
+        };
+        archives.push(ArchiveEntry { filename, path, kind, created, size });
+    }
+
+    archives.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.filename.cmp(&b.filename)));
+    Ok(archives)
+}
+
+/// Index of the full archive starting the chain of each archive; `archives` must be oldest first
+///
+/// Each full archive starts a chain; incrementals belong to the chain before them.
+fn chain_starts(archives: &[ArchiveEntry]) -> Vec<usize> {
+    let mut chain_start = Vec::with_capacity(archives.len());
+    for (i, archive) in archives.iter().enumerate() {
+        let start = match chain_start.last() {
+            Some(&previous) if archive.kind == ArchiveKind::Incremental => previous,
+            _ => i,
+        };
+        chain_start.push(start);
+    }
+    chain_start
+}
+
+/// Decide which archives to keep; `archives` must be oldest first
+///
+/// Returns the keep reasons of each archive (empty = remove).
+pub fn plan(archives: &[ArchiveEntry], policy: &RetentionPolicy) -> Vec<Vec<String>> {
+    let mut reasons: Vec<Vec<String>> = vec![Vec::new(); archives.len()];
+    let Some(newest) = archives.last() else {
+        return reasons;
+    };
+    let newest_first: Vec<usize> = (0..archives.len()).rev().collect();
+    let mut keep = |i: usize, reason: &str| {
+        if !reasons[i].iter().any(|r| r == reason) {
+            reasons[i].push(reason.to_string());
+        }
+    };
+
+    keep(archives.len() - 1, "latest");
+
+    let has_keep_rule = policy.keep_last.is_some()
+        || policy.keep_daily.is_some()
+        || policy.keep_weekly.is_some()
+        || policy.keep_monthly.is_some()
+        || policy.keep_yearly.is_some()
+        || policy.keep_within_secs.is_some();
+    if !has_keep_rule {
+        for i in 0..archives.len() {
+            keep(i, "no keep rule");
+        }
+    }
+
+    if let Some(n) = policy.keep_last {
+        for &i in newest_first.iter().take(n as usize) {
+            keep(i, "last");
+        }
+    }
+
+    // Grandfather-father-son: the newest archive of each of the last N periods
+    let periods: [(Option<u32>, &str, PeriodOf); 4] = [
+        (policy.keep_daily, "daily", |t| (t.year(), t.ordinal())),
+        (policy.keep_weekly, "weekly", |t| (t.iso_week().year(), t.iso_week().week())),
+        (policy.keep_monthly, "monthly", |t| (t.year(), t.month())),
+        (policy.keep_yearly, "yearly", |t| (t.year(), 0)),
+    ];
+    for (count, label, period_of) in periods {
+        let Some(count) = count else { continue };
+        let mut last_period = None;
+        let mut kept = 0;
+        for &i in &newest_first {
+            let period = period_of(&archives[i].created);
+            if last_period == Some(period) {
+                continue;
+            }
+            if kept == count {
+                break;
+            }
+            keep(i, label);
+            last_period = Some(period);
+            kept += 1;
+        }
+    }
+
+    if let Some(within) = policy.keep_within_secs {
+        // A window reaching past the representable range keeps everything
+        let cutoff = i64::try_from(within)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|window| newest.created.checked_sub_signed(window));
+        for (i, archive) in archives.iter().enumerate() {
+            if cutoff.is_none_or(|cutoff| archive.created >= cutoff) {
+                keep(i, "within");
+            }
+        }
+    }
+
+    let chain_start = chain_starts(archives);
+
+    // A kept incremental needs its chain up to itself
+    for i in (0..archives.len()).rev() {
+        if archives[i].kind == ArchiveKind::Incremental && !reasons[i].is_empty() {
+            for dependency in &mut reasons[chain_start[i]..i] {
+                if dependency.is_empty() {
+                    dependency.push("dependency".to_string());
+                }
+            }
+        }
+    }
+
+    // Size limit: drop whole chains, oldest first, never the newest one
+    if let Some(max_total_size) = policy.max_total_size {
+        let mut total: u64 = (0..archives.len()).filter(|&i| !reasons[i].is_empty()).map(|i| archives[i].size).sum();
+        let newest_chain = chain_start[archives.len() - 1];
+        let mut start = 0;
+        while total > max_total_size && start < newest_chain {
+            let end = (start + 1..archives.len()).find(|&j| chain_start[j] == j).unwrap_or(archives.len());
+            for j in start..end {
+                if !reasons[j].is_empty() {
+                    total -= archives[j].size;
+                    reasons[j].clear();
+                }
+            }
+            start = end;
+        }
+    }
+
+    reasons
+}
+
+fn decision(archive: &ArchiveEntry, reasons: Vec<String>) -> PruneDecision {
+    PruneDecision {
+        filename: archive.filename.clone(),
+        path: archive.path.to_string_lossy().to_string(),
+        kind: archive.kind,
+        created_at: archive.created_at(),
+        size: archive.size,
+        reasons,
+    }
+}
+
+/// Remove the archives without keep reasons, newest first; returns which were removed
+///
+/// When one can't be removed, the older archives of its chain get a keep reason
+/// instead: the archives after them still depend on them.
+fn remove_unkept<F>(archives: &[ArchiveEntry], reasons: &mut [Vec<String>], errors: &mut Vec<String>, mut remove: F) -> Vec<bool>
+where
+    F: FnMut(&ArchiveEntry) -> std::io::Result<()>,
+{
+    let chain_start = chain_starts(archives);
+    let mut removed = vec![false; archives.len()];
+    let mut blocked_chain = None;
+    for (i, archive) in archives.iter().enumerate().rev() {
+        if !reasons[i].is_empty() {
+            continue;
+        }
+        if blocked_chain == Some(chain_start[i]) {
+            reasons[i].push("dependent not removed".to_string());
+            continue;
+        }
+        match remove(archive) {
+            Ok(()) => removed[i] = true,
+            Err(e) => {
+                log::error!("❌ Failed to remove {}: {}", archive.filename, e);
+                errors.push(format!("{}: {}", archive.filename, e));
+                reasons[i].push("delete failed".to_string());
+                blocked_chain = Some(chain_start[i]);
+            }
+        }
+    }
+    removed
+}
+
+/// Apply a config's retention policy to its destination
+///
+/// With `dry_run` nothing is deleted; the report shows what would be. Archives
+/// are deleted newest first, and once one can't be deleted the older archives
+/// of its chain stay too, so no incremental is left without its parents.
+pub fn prune(config: &BackupConfig, policy: &RetentionPolicy, dry_run: bool) -> Result<PruneReport, String> {
+    let archives = config_archives(config)?;
+    let mut reasons = plan(&archives, policy);
+
+    let mut report = PruneReport {
+        config_id: config.id.clone(),
+        dry_run,
+        kept: Vec::new(),
+        removed: Vec::new(),
+        freed_bytes: 0,
+        errors: Vec::new(),
+    };
+
+    let removed = remove_unkept(&archives, &mut reasons, &mut report.errors, |archive| {
+        if dry_run {
+            return Ok(());
+        }
+        if archive.path.is_dir() {
+            fs::remove_dir_all(&archive.path)?;
+        } else {
+            fs::remove_file(&archive.path)?;
+        }
+        log::info!("🗑️  Pruned {}", archive.filename);
+        Ok(())
+    });
+
+    for ((archive, reasons), removed) in archives.iter().zip(reasons).zip(removed) {
+        if removed {
+            report.freed_bytes += archive.size;
+            report.removed.push(decision(archive, Vec::new()));
+        } else {
+            report.kept.push(decision(archive, reasons));
+        }
+    }
+
+    log::info!(
+        "🧹 Prune{} of {}: {} kept, {} removed ({:.1} MB)",
+        if dry_run { " (dry run)" } else { "" },
+        config.name,
+        report.kept.len(),
+        report.removed.len(),
+        report.freed_bytes as f64 / 1_048_576.0
+    );
+    Ok(report)
+}
+
+/// Prune after a successful backup if the config asks for it (errors are only logged)
+pub fn prune_after_backup(config: &BackupConfig) {
+    let Some(policy) = config.retention.as_ref().filter(|p| p.prune_after_backup) else {
+        return;
+    };
+    if let Err(e) = prune(config, policy, false) {
+        log::error!("❌ Automatic prune failed for {}: {}", config.name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(kind: ArchiveKind, created: &str, size: u64) -> ArchiveEntry {
+        let created = NaiveDateTime::parse_from_str(created, "%Y-%m-%d %H:%M").unwrap();
+        let filename = format!(
+            "Bkp_InLocker_Docs_{}_{}.tar.zst",
+            if kind == ArchiveKind::Full { "full" } else { "incr" },
+            created.format("%Y%m%d_%H%M%S")
+        );
+        ArchiveEntry { path: PathBuf::from(&filename), filename, kind, created, size }
+    }
+
+    fn kept(archives: &[ArchiveEntry], policy: &RetentionPolicy) -> Vec<bool> {
+        plan(archives, policy).iter().map(|r| !r.is_empty()).collect()
+    }
+
+    #[test]
+    fn test_parse_archive_name() {
+        let (name, kind, created) = parse_archive_name("Bkp_InLocker_My_Docs_incr_20240601_023000.tar.zst.enc").unwrap();
+        assert_eq!(name, "My_Docs");
+        assert_eq!(kind, ArchiveKind::Incremental);
+        assert_eq!(created.format("%Y-%m-%d %H:%M:%S").to_string(), "2024-06-01 02:30:00");
+
+        assert!(parse_archive_name("Bkp_InLocker_Docs_full_20240601_023000").is_some(), "Copy-mode folder");
+        assert!(parse_archive_name("backup_20240601.tar.zst").is_none());
+        assert!(parse_archive_name("Bkp_InLocker_Docs_other_20240601_023000.tar.zst").is_none());
+    }
+
+    #[test]
+    fn test_keep_last_and_gfs() {
+        use ArchiveKind::Full;
+        let archives = vec![
+            archive(Full, "2024-04-30 02:00", 1),
+            archive(Full, "2024-05-31 02:00", 1),
+            archive(Full, "2024-06-01 02:00", 1),
+            archive(Full, "2024-06-01 14:00", 1),
+            archive(Full, "2024-06-02 02:00", 1),
+            archive(Full, "2024-06-03 02:00", 1),
+        ];
+
+        let last_two = RetentionPolicy { keep_last: Some(2), ..Default::default() };
+        assert_eq!(kept(&archives, &last_two), vec![false, false, false, false, true, true]);
+
+        // Daily keeps the newest archive of a day (14:00, not 02:00)
+        let daily = RetentionPolicy { keep_daily: Some(3), ..Default::default() };
+        assert_eq!(kept(&archives, &daily), vec![false, false, false, true, true, true]);
+
+        let monthly = RetentionPolicy { keep_monthly: Some(3), ..Default::default() };
+        assert_eq!(kept(&archives, &monthly), vec![true, true, false, false, false, true]);
+
+        let within = RetentionPolicy { keep_within_secs: Some(86_400), ..Default::default() };
+        assert_eq!(kept(&archives, &within), vec![false, false, false, false, true, true]);
+
+        // No keep rule: everything stays
+        assert_eq!(kept(&archives, &RetentionPolicy::default()), vec![true; 6]);
+    }
+
+    #[test]
+    fn test_incremental_chains_are_kept_whole() {
+        use ArchiveKind::{Full, Incremental};
+        let archives = vec![
+            archive(Full, "2024-06-01 02:00", 100),
+            archive(Incremental, "2024-06-02 02:00", 10),
+            archive(Incremental, "2024-06-03 02:00", 10),
+            archive(Full, "2024-06-04 02:00", 100),
+            archive(Incremental, "2024-06-05 02:00", 10),
+        ];
+
+        // The newest incremental needs its full (06-04), not the older chain
+        let policy = RetentionPolicy { keep_daily: Some(1), keep_yearly: Some(1), ..Default::default() };
+        assert_eq!(kept(&archives, &policy), vec![false, false, false, true, true]);
+
+        let policy = RetentionPolicy { keep_within_secs: Some(2 * 86_400), ..Default::default() };
+        assert_eq!(kept(&archives, &policy), vec![true, true, true, true, true], "06-03 needs its full");
+
+        // Size limit removes the oldest complete chain, never the newest one
+        let policy = RetentionPolicy { max_total_size: Some(150), ..Default::default() };
+        assert_eq!(kept(&archives, &policy), vec![false, false, false, true, true]);
+        let policy = RetentionPolicy { max_total_size: Some(1), ..Default::default() };
+        assert_eq!(kept(&archives, &policy), vec![false, false, false, true, true]);
+    }
+
+    #[test]
+    fn test_failed_delete_keeps_the_chain_it_depends_on() {
+        use ArchiveKind::{Full, Incremental};
+        let archives = vec![
+            archive(Full, "2024-06-01 02:00", 100),
+            archive(Incremental, "2024-06-02 02:00", 10),
+            archive(Incremental, "2024-06-03 02:00", 10),
+            archive(Full, "2024-06-04 02:00", 100),
+        ];
+        let mut reasons = plan(&archives, &RetentionPolicy { keep_last: Some(1), ..Default::default() });
+        let mut errors = Vec::new();
+        let stuck = archives[1].filename.clone();
+        let removed = remove_unkept(&archives, &mut reasons, &mut errors, |archive| {
+            if archive.filename == stuck {
+                Err(std::io::Error::other("busy"))
+            } else {
+                Ok(())
+            }
+        });
+
+        // 06-03 goes; 06-02 can't, so its full archive must stay
+        assert_eq!(removed, vec![false, false, true, false]);
+        assert_eq!(reasons[0], vec!["dependent not removed".to_string()]);
+        assert_eq!(reasons[1], vec!["delete failed".to_string()]);
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+/// OS scheduler backends
+///
+/// Scheduled backups are run by the OS service manager so they work while the
+/// app is closed: launchd agents on macOS, systemd user timers on Linux.
+/// Commands go through `platform_backend()` instead of calling either directly.
+
+use crate::{launchd, systemd};
+use std::path::PathBuf;
+
+/// A service manager that can run `inlocker --backup <id>` on a cron schedule
+pub trait ScheduleBackend: Send + Sync {
+    /// Short name for logs and diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Install (or replace) the schedule of a config and activate it
+    fn install(&self, config_id: &str, config_name: &str, cron_expr: &str, app_path: &str) -> Result<(), String>;
+
+    /// Deactivate and remove the schedule of a config
+    fn uninstall(&self, config_id: &str, config_name: Option<&str>) -> Result<(), String>;
+
+    /// Whether the service manager currently has the schedule loaded
+    fn is_loaded(&self, config_id: &str) -> Result<bool, String>;
+
+    /// Identifier of the job in the service manager
+    fn job_label(&self, config_id: &str) -> String;
+
+    /// File defining the schedule (launchd plist or systemd timer)
+    fn schedule_file_path(&self, config_id: &str) -> Result<PathBuf, String>;
+
+    /// Log file that receives the output of scheduled runs
+    fn log_path(&self, config_id: &str) -> Result<PathBuf, String>;
+
+    /// Executable the service manager should start
+    fn executable_path(&self) -> Result<String, String>;
+}
+
+/// macOS launchd agents (~/Library/LaunchAgents)
+pub struct LaunchdBackend;
+
+impl ScheduleBackend for LaunchdBackend {
+    fn name(&self) -> &'static str {
+        "launchd"
+    }
+
+    fn install(&self, config_id: &str, config_name: &str, cron_expr: &str, app_path: &str) -> Result<(), String> {
+        launchd::install_launch_agent(config_id, config_name, cron_expr, app_path)
+    }
+
+    fn uninstall(&self, config_id: &str, config_name: Option<&str>) -> Result<(), String> {
+        launchd::uninstall_launch_agent(config_id, config_name)
+    }
+
+    fn is_loaded(&self, config_id: &str) -> Result<bool, String> {
+        launchd::is_agent_loaded(config_id)
+    }
+
+    fn job_label(&self, config_id: &str) -> String {
+        launchd::get_agent_label(config_id)
+    }
+
+    fn schedule_file_path(&self, config_id: &str) -> Result<PathBuf, String> {
+        launchd::get_plist_path(config_id)
+    }
+
+    fn log_path(&self, config_id: &str) -> Result<PathBuf, String> {
+        launchd::get_log_path(config_id)
+    }
+
+    fn executable_path(&self) -> Result<String, String> {
+        launchd::get_executable_path()
+    }
+}
+
+/// systemd user timers (~/.config/systemd/user)
+pub struct SystemdBackend;
+
+impl ScheduleBackend for SystemdBackend {
+    fn name(&self) -> &'static str {
+        "systemd"
+    }
+
+    fn install(&self, config_id: &str, config_name: &str, cron_expr: &str, app_path: &str) -> Result<(), String> {
+        systemd::install_timer(config_id, config_name, cron_expr, app_path)
+    }
+
+    fn uninstall(&self, config_id: &str, _config_name: Option<&str>) -> Result<(), String> {
+        systemd::uninstall_timer(config_id)
+    }
+
+    fn is_loaded(&self, config_id: &str) -> Result<bool, String> {
+        systemd::is_timer_active(config_id)
+    }
+
+    fn job_label(&self, config_id: &str) -> String {
+        format!("{}.timer", systemd::get_unit_name(config_id))
+    }
+
+    fn schedule_file_path(&self, config_id: &str) -> Result<PathBuf, String> {
+        systemd::get_timer_path(config_id)
+    }
+
+    fn log_path(&self, config_id: &str) -> Result<PathBuf, String> {
+        systemd::get_log_path(config_id)
+    }
+
+    fn executable_path(&self) -> Result<String, String> {
+        systemd::get_executable_path()
+    }
+}
+
+/// Scheduler backend of the current platform
+pub fn platform_backend() -> Result<&'static dyn ScheduleBackend, String> {
+    if cfg!(target_os = "macos") {
+        Ok(&LaunchdBackend)
+    } else if cfg!(target_os = "linux") {
+        Ok(&SystemdBackend)
+    } else {
+        Err("Scheduled backups are not supported on this platform".to_string())
+    }
+}
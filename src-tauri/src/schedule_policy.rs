@@ -0,0 +1,263 @@
+/// Missed-run and retry decisions for scheduled backups
+///
+/// A trigger shortly after a fire time is a regular run. Anything later (launchd
+/// firing on wake, the in-app scheduler resuming after sleep, app start) is a
+/// catch-up, and only runs if the schedule's `CatchUpPolicy` allows it.
+///
+/// Failed runs are retried with exponential backoff per the schedule's
+/// `RetryPolicy`, but only for error classes that may go away by themselves.
+
+use crate::cron::CronSchedule;
+use crate::types::{BackupConfig, CatchUpPolicy, ErrorClass, RetryPolicy, RunAttempt};
+use chrono::{DateTime, Duration, TimeZone};
+use std::future::Future;
+use std::path::Path;
+
+/// A trigger this long after a fire time still counts as on time
+pub const ON_TIME_GRACE_SECS: i64 = 15 * 60;
+
+/// Last scheduled run of a config (falls back to its last backup of any kind)
+pub fn last_run_reference(config: &BackupConfig) -> Option<i64> {
+    config.last_scheduled_run_at.or(config.last_backup_at)
+}
+
+/// Whether a trigger at `now` belongs to a fire time that just passed
+pub fn is_on_time<Tz: TimeZone>(schedule: &CronSchedule, now: &DateTime<Tz>) -> bool {
+    let window_start = now.clone() - Duration::seconds(ON_TIME_GRACE_SECS);
+    schedule.fired_between(&window_start, now)
+}
+
+/// Whether one catch-up backup should run now
+///
+/// A fire time must have been missed since `last_run` (Unix seconds). A schedule
+/// that never ran is overdue under every policy but `Skip`.
+pub fn catch_up_due<Tz: TimeZone>(
+    schedule: &CronSchedule,
+    policy: &CatchUpPolicy,
+    last_run: Option<i64>,
+    now: &DateTime<Tz>,
+) -> bool {
+    let Some(last_run) = last_run.and_then(|ts| now.timezone().timestamp_opt(ts, 0).single()) else {
+        return *policy != CatchUpPolicy::Skip;
+    };
+    if !schedule.fired_between(&last_run, now) {
+        return false;
+    }
+
+    match policy {
+        CatchUpPolicy::Skip => false,
+        CatchUpPolicy::RunOnce => true,
+        CatchUpPolicy::IfOlderThan { max_age_secs } => {
+            now.timestamp() - last_run.timestamp() > *max_age_secs as i64
+        }
+    }
+}
+
+/// I/O failures that can go away on their own (messages as printed by `std::io::Error`):
+/// timeouts, EAGAIN/EBUSY, and network mounts that dropped
+const TRANSIENT_IO_ERRORS: [&str; 10] = [
+    "timed out",
+    "resource temporarily unavailable",
+    "resource busy",
+    "host is down",
+    "no route to host",
+    "network is down",
+    "network is unreachable",
+    "connection reset",
+    "stale file handle",
+    "stale nfs file handle",
+];
+
+/// Classify a failed run of `config`
+///
+/// Missing folders are checked on disk (the error text varies by platform and step);
+/// other classes are recognized from the error message.
+pub fn classify_error(error: &str, config: &BackupConfig) -> ErrorClass {
+    if !Path::new(&config.destination_path).is_dir() {
+        return ErrorClass::DestinationUnavailable;
+    }
+    if !Path::new(&config.source_path).exists() {
+        return ErrorClass::SourceUnavailable;
+    }
+
+    let lower = error.to_lowercase();
+    if ["secret", "keyring", "environment variable"].iter().any(|k| lower.contains(k)) {
+        ErrorClass::SecretUnavailable
+    } else if TRANSIENT_IO_ERRORS.iter().any(|k| lower.contains(k)) {
+        ErrorClass::Io
+    } else {
+        // Permission denied, disk full, corrupt data, ...: the next attempt would fail the same way
+        ErrorClass::Other
+    }
+}
+
+/// Wait before retrying after failed attempt number `attempt` (1-based)
+pub fn retry_delay(policy: &RetryPolicy, attempt: u32) -> std::time::Duration {
+    let factor = (policy.backoff_factor.max(1) as u64).saturating_pow(attempt.saturating_sub(1));
+    let secs = policy.initial_delay_secs.saturating_mul(factor).min(policy.max_delay_secs);
+    std::time::Duration::from_secs(secs)
+}
+
+/// Run `attempt` (called with the 1-based attempt number) until it succeeds,
+/// fails with an error class the policy doesn't retry, or runs out of attempts
+///
+/// Returns the final result and a record of every attempt.
+pub async fn run_with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    config: &BackupConfig,
+    mut attempt: F,
+) -> (Result<T, String>, Vec<RunAttempt>)
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempts = Vec::new();
+
+    for number in 1..=max_attempts {
+        let started_at = chrono::Utc::now().timestamp();
+        let result = attempt(number).await;
+        let finished_at = chrono::Utc::now().timestamp();
+
+        let error = match result {
+            Ok(value) => {
+                attempts.push(RunAttempt { attempt: number, started_at, finished_at, error: None, error_class: None });
+                return (Ok(value), attempts);
+            }
+            Err(e) => e,
+        };
+
+        let class = classify_error(&error, config);
+        attempts.push(RunAttempt {
+            attempt: number,
+            started_at,
+            finished_at,
+            error: Some(error.clone()),
+            error_class: Some(class),
+        });
+
+        if number == max_attempts || !policy.retry_on.contains(&class) {
+            log::error!("❌ Attempt {}/{} failed ({:?}), giving up: {}", number, max_attempts, class, error);
+            return (Err(error), attempts);
+        }
+
+        let delay = retry_delay(policy, number);
+        log::warn!(
+            "⚠️  Attempt {}/{} failed ({:?}): {} - retrying in {}s",
+            number,
+            max_attempts,
+            class,
+            error,
+            delay.as_secs()
+        );
+        tokio::time::sleep(delay).await;
+    }
+
+    unreachable!("the last attempt always returns")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_on_time_window() {
+        let daily = CronSchedule::parse("0 2 * * *").unwrap();
+        assert!(is_on_time(&daily, &at(10, 2, 0)));
+        assert!(is_on_time(&daily, &at(10, 2, 14)));
+        assert!(!is_on_time(&daily, &at(10, 8, 30)), "Woke up hours later");
+    }
+
+    #[test]
+    fn test_catch_up_policies() {
+        let daily = CronSchedule::parse("0 2 * * *").unwrap();
+        let last_run = Some(at(9, 2, 5).timestamp());
+        let now = at(10, 9, 0); // The 02:00 run of the 10th was missed
+
+        assert!(!catch_up_due(&daily, &CatchUpPolicy::Skip, last_run, &now));
+        assert!(catch_up_due(&daily, &CatchUpPolicy::RunOnce, last_run, &now));
+        assert!(catch_up_due(&daily, &CatchUpPolicy::IfOlderThan { max_age_secs: 86_400 }, last_run, &now));
+        assert!(!catch_up_due(&daily, &CatchUpPolicy::IfOlderThan { max_age_secs: 2 * 86_400 }, last_run, &now));
+
+        // Nothing missed since the last run
+        let caught_up = Some(at(10, 8, 0).timestamp());
+        assert!(!catch_up_due(&daily, &CatchUpPolicy::RunOnce, caught_up, &now));
+
+        // Never ran: overdue unless missed runs are skipped
+        assert!(catch_up_due(&daily, &CatchUpPolicy::RunOnce, None, &now));
+        assert!(catch_up_due(&daily, &CatchUpPolicy::IfOlderThan { max_age_secs: 2 * 86_400 }, None, &now));
+        assert!(!catch_up_due(&daily, &CatchUpPolicy::Skip, None, &now));
+    }
+
+    fn config_at(dir: &Path) -> BackupConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": "test",
+            "name": "Test",
+            "source_path": dir.to_string_lossy(),
+            "destination_path": dir.to_string_lossy(),
+            "schedule": null,
+            "enabled": true,
+            "created_at": 0,
+            "updated_at": 0,
+            "last_backup_at": null,
+            "last_backup_checksum": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        let policy = RetryPolicy { initial_delay_secs: 60, backoff_factor: 2, max_delay_secs: 300, ..Default::default() };
+        let delays: Vec<u64> = (1..=5).map(|n| retry_delay(&policy, n).as_secs()).collect();
+        assert_eq!(delays, vec![60, 120, 240, 300, 300]);
+        assert_eq!(retry_delay(&policy, u32::MAX).as_secs(), 300);
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let dir = std::env::temp_dir();
+        let config = config_at(&dir);
+        assert_eq!(classify_error("Keyring entry not found", &config), ErrorClass::SecretUnavailable);
+        assert_eq!(classify_error("Connection timed out (os error 110)", &config), ErrorClass::Io);
+        assert_eq!(classify_error("Device or resource busy (os error 16)", &config), ErrorClass::Io);
+        assert_eq!(classify_error("Stale file handle (os error 116)", &config), ErrorClass::Io);
+        assert_eq!(classify_error("No space left on device (os error 28)", &config), ErrorClass::Other);
+        assert_eq!(classify_error("Permission denied (os error 13)", &config), ErrorClass::Other);
+        assert_eq!(classify_error("Invalid password", &config), ErrorClass::Other);
+
+        let missing = config_at(&dir.join("inlocker_retry_unmounted_volume"));
+        assert_eq!(classify_error("anything", &missing), ErrorClass::DestinationUnavailable);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_retry_stops_on_success_or_permanent_error() {
+        let config = config_at(&std::env::temp_dir());
+        let policy = RetryPolicy { max_attempts: 4, initial_delay_secs: 0, ..Default::default() };
+
+        // Transient errors are retried until the run succeeds
+        let (result, attempts) = run_with_retry(&policy, &config, |n| async move {
+            if n < 3 { Err("Host is down (os error 112)".to_string()) } else { Ok(n) }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(attempts[0].error_class, Some(ErrorClass::Io));
+        assert!(attempts[2].error.is_none());
+
+        // Errors outside retry_on end the run right away
+        let (result, attempts) =
+            run_with_retry(&policy, &config, |_| async { Err::<(), _>("Invalid password".to_string()) }).await;
+        assert!(result.is_err());
+        assert_eq!(attempts.len(), 1);
+
+        // Transient errors give up after max_attempts
+        let (_, attempts) =
+            run_with_retry(&policy, &config, |_| async { Err::<(), _>("Resource busy".to_string()) }).await;
+        assert_eq!(attempts.len(), 4);
+    }
+}
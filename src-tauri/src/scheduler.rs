@@ -1,63 +1,274 @@
-/// Simplified scheduler module
+/// In-app scheduler
 ///
-/// This module has been simplified to remove the in-app tokio-cron-scheduler.
-/// All scheduling is now handled by macOS launchd for independent, system-level scheduling.
+/// Scheduled backups normally run through the OS service manager (launchd on
+/// macOS, systemd user timers on Linux) so they work while the app is closed.
+/// This scheduler is the fallback: it runs jobs on their cron schedule while
+/// the app is open, for configs that opt out of the OS agent (`in_app`) or
+/// when no agent could be installed.
 ///
-/// The SchedulerState struct is kept as a placeholder to maintain compatibility
-/// with existing code, but it no longer manages active jobs.
+/// A job never fires while an OS agent is loaded for the same config, so a
+/// schedule registered in both places still runs once.
 
-/// Placeholder scheduler state
-///
-/// DEPRECATED: This is kept only for compatibility. All scheduling is now done via launchd.
-/// The in-app scheduler has been removed in favor of system-level scheduling.
+use crate::commands::{self, AppState};
+use crate::cron::CronSchedule;
+use crate::schedule_backend::platform_backend;
+use crate::schedule_policy;
+use crate::types::{BackupConfig, BackupResult, JobTrigger, ScheduledJobInfo};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::Manager;
+
+/// Longest single sleep: wake-ups are re-checked against the wall clock, so
+/// suspend/resume and clock changes delay a run by at most this much
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+/// Head start given to an OS agent replaying missed runs before the app catches up itself
+const AGENT_CATCH_UP_DELAY: Duration = Duration::from_secs(120);
+
+/// A registered job: its public state and the task that runs it
+struct ScheduledJob {
+    info: ScheduledJobInfo,
+    task: JoinHandle<()>,
+}
+
+type JobMap = Arc<Mutex<HashMap<String, ScheduledJob>>>;
+
+/// In-process scheduler state (managed by Tauri)
 pub struct SchedulerState {
-    // Empty - launchd handles all scheduling
+    jobs: JobMap,
 }
 
 impl SchedulerState {
-    /// Create a new (empty) scheduler state
+    /// Create an empty scheduler
     pub async fn new() -> Result<Self, String> {
-        log::info!("SchedulerState initialized (using launchd for actual scheduling)");
-        Ok(Self {})
+        log::info!("SchedulerState initialized (in-app fallback for OS scheduling)");
+        Ok(Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        })
     }
 
-    /// Start the scheduler (no-op - launchd handles this)
+    /// Start the scheduler (jobs start running as they are registered)
     pub async fn start(&self) -> Result<(), String> {
-        log::info!("Scheduler started (launchd-based)");
+        log::info!("Scheduler started (in-app fallback)");
         Ok(())
     }
 
-    /// Register a schedule (no-op - launchd handles this)
-    ///
-    /// This is a placeholder. Actual registration is done via launchd::install_launch_agent
-    pub async fn register_schedule(
-        &self,
-        _app: tauri::AppHandle,
-        config: crate::types::BackupConfig,
-    ) -> Result<(), String> {
+    /// Run `config`'s schedule in-app, replacing any job already registered for it
+    pub async fn register_schedule(&self, app: tauri::AppHandle, config: BackupConfig) -> Result<(), String> {
+        let schedule_config = config
+            .schedule
+            .as_ref()
+            .filter(|s| s.enabled)
+            .ok_or("No enabled schedule configured for this backup")?;
+        let schedule = CronSchedule::parse(&schedule_config.cron_expression)?;
+
+        let mut jobs = self.jobs.lock().map_err(|e| e.to_string())?;
+        if let Some(old) = jobs.remove(&config.id) {
+            old.task.abort();
+        }
+
+        let info = ScheduledJobInfo {
+            config_id: config.id.clone(),
+            cron_expression: schedule_config.cron_expression.clone(),
+            next_run: schedule.next_run(&chrono::Local::now()).map(|run| run.timestamp()),
+            last_run: None,
+            last_error: None,
+            running: false,
+        };
+        let task = tauri::async_runtime::spawn(run_job(app, config.id.clone(), schedule, self.jobs.clone()));
+        jobs.insert(config.id.clone(), ScheduledJob { info, task });
+
         log::info!(
-            "Schedule registration requested for config: {} (handled by launchd)",
-            config.id
+            "⏰ In-app schedule registered for config {} ({})",
+            config.id,
+            schedule_config.cron_expression
         );
         Ok(())
     }
 
-    /// Unregister a schedule (no-op - launchd handles this)
-    ///
-    /// This is a placeholder. Actual unregistration is done via launchd::uninstall_launch_agent
+    /// Stop the in-app job of a config (no-op if it has none)
     pub async fn unregister_schedule(&self, config_id: &str) -> Result<(), String> {
-        log::info!(
-            "Schedule unregistration requested for config: {} (handled by launchd)",
-            config_id
-        );
+        let removed = self.jobs.lock().map_err(|e| e.to_string())?.remove(config_id);
+        if let Some(job) = removed {
+            job.task.abort();
+            log::info!("⏰ In-app schedule unregistered for config {}", config_id);
+        }
         Ok(())
     }
 
-    /// Check if a config has a registered schedule
-    ///
-    /// DEPRECATED: Use launchd::is_agent_loaded instead
-    pub async fn is_scheduled(&self, _config_id: &str) -> bool {
-        // Always return false - use launchd::is_agent_loaded for real status
-        false
+    /// Check if a config has an in-app job
+    pub async fn is_scheduled(&self, config_id: &str) -> bool {
+        self.jobs
+            .lock()
+            .map(|jobs| jobs.contains_key(config_id))
+            .unwrap_or(false)
+    }
+
+    /// State of all in-app jobs
+    pub async fn jobs(&self) -> Vec<ScheduledJobInfo> {
+        self.jobs
+            .lock()
+            .map(|jobs| jobs.values().map(|job| job.info.clone()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether the OS service manager has a schedule loaded for this config
+pub fn os_agent_active(config_id: &str) -> bool {
+    platform_backend()
+        .and_then(|backend| backend.is_loaded(config_id))
+        .unwrap_or(false)
+}
+
+/// Update the public state of a job (if it is still registered)
+fn update_job(jobs: &JobMap, config_id: &str, update: impl FnOnce(&mut ScheduledJobInfo)) {
+    if let Ok(mut jobs) = jobs.lock() {
+        if let Some(job) = jobs.get_mut(config_id) {
+            update(&mut job.info);
+        }
+    }
+}
+
+/// Config as currently loaded in AppState
+fn current_config(app: &tauri::AppHandle, config_id: &str) -> Option<BackupConfig> {
+    let state = app.state::<AppState>();
+    let configs = state.configs.lock().ok()?;
+    configs.iter().find(|c| c.id == config_id).cloned()
+}
+
+/// Run a scheduled backup (retrying per the schedule's policy) and record it;
+/// returns the final error, if any
+async fn run_and_record(app: &tauri::AppHandle, config_id: &str) -> Option<String> {
+    let Some(config) = current_config(app, config_id) else {
+        return Some("Config not found".to_string());
+    };
+    let retry = config.schedule.as_ref().map(|s| s.retry.clone()).unwrap_or_default();
+
+    let (result, attempts) = schedule_policy::run_with_retry(&retry, &config, |_| async {
+        match commands::run_backup_with_type(
+            app.clone(),
+            app.state::<AppState>(),
+            config_id.to_string(),
+            None,
+            None,
+            JobTrigger::Scheduled,
+        )
+        .await?
+        {
+            BackupResult { success: true, job, .. } => Ok(job),
+            result => Err(result.message),
+        }
+    })
+    .await;
+
+    let job = result.as_ref().ok().and_then(|job| job.as_ref());
+    if let Err(e) = commands::record_scheduled_run(app, config_id, job, attempts) {
+        log::warn!("⚠️  Failed to record scheduled run of config {}: {}", config_id, e);
+    }
+
+    match result {
+        Ok(_) => None,
+        Err(e) => {
+            crate::send_notification(app, "InLocker - Backup Failed ✗", &format!("{}: {}", config.name, e));
+            Some(e)
+        }
+    }
+}
+
+/// Run one catch-up backup if the config's policy says a missed fire time needs it
+pub async fn run_catch_up_if_due(app: tauri::AppHandle, config: BackupConfig) {
+    let Some(schedule_config) = config.schedule.as_ref().filter(|s| s.enabled) else {
+        return;
+    };
+    let Ok(schedule) = CronSchedule::parse(&schedule_config.cron_expression) else {
+        return;
+    };
+    let last_run = schedule_policy::last_run_reference(&config);
+    if !schedule_policy::catch_up_due(&schedule, &schedule_config.catch_up, last_run, &chrono::Local::now()) {
+        return;
+    }
+
+    log::info!("⏰ Missed scheduled backup for config {} - running catch-up", config.id);
+    match run_and_record(&app, &config.id).await {
+        None => log::info!("✅ Catch-up backup completed for config {}", config.id),
+        Some(e) => log::error!("❌ Catch-up backup failed for config {}: {}", config.id, e),
+    }
+}
+
+/// Catch up at app start on a schedule an OS agent runs, if the agent didn't
+///
+/// launchd replays runs missed during sleep but not while the machine was off;
+/// systemd replays them (`Persistent=true`) shortly after boot. Waits
+/// `AGENT_CATCH_UP_DELAY` so a replay lands first, then checks the config as
+/// saved (the agent may have run in another process): a run recorded since the
+/// missed fire time means nothing is left to catch up on.
+pub async fn run_agent_catch_up_if_due(app: tauri::AppHandle, config_id: String) {
+    tokio::time::sleep(AGENT_CATCH_UP_DELAY).await;
+    let configs = match commands::load_configs(app.clone(), app.state::<AppState>()).await {
+        Ok(configs) => configs,
+        Err(e) => {
+            log::warn!("⚠️  Could not check missed runs of config {}: {}", config_id, e);
+            return;
+        }
+    };
+    if let Some(config) = configs.into_iter().find(|c| c.id == config_id) {
+        run_catch_up_if_due(app, config).await;
+    }
+}
+
+/// Job loop: sleep until the next fire time, run the backup, repeat
+async fn run_job(app: tauri::AppHandle, config_id: String, schedule: CronSchedule, jobs: JobMap) {
+    loop {
+        let Some(next) = schedule.next_run(&chrono::Local::now()) else {
+            log::warn!("⚠️  Schedule of config {} never fires; in-app job stopped", config_id);
+            update_job(&jobs, &config_id, |info| info.next_run = None);
+            return;
+        };
+        update_job(&jobs, &config_id, |info| info.next_run = Some(next.timestamp()));
+
+        loop {
+            let remaining = next - chrono::Local::now();
+            match remaining.to_std() {
+                Ok(wait) if !wait.is_zero() => tokio::time::sleep(wait.min(MAX_SLEEP)).await,
+                _ => break,
+            }
+        }
+
+        if os_agent_active(&config_id) {
+            log::info!("⏭️  Skipping in-app run of config {}: the OS agent runs this schedule", config_id);
+            continue;
+        }
+
+        // Woke up long after the fire time (sleep/suspend): that's a catch-up
+        let now = chrono::Local::now();
+        if !schedule_policy::is_on_time(&schedule, &now) {
+            let due = current_config(&app, &config_id).is_some_and(|config| {
+                let policy = config.schedule.as_ref().map(|s| s.catch_up.clone()).unwrap_or_default();
+                schedule_policy::catch_up_due(&schedule, &policy, schedule_policy::last_run_reference(&config), &now)
+            });
+            if !due {
+                log::info!("⏭️  Missed fire time of config {} (catch-up policy says skip)", config_id);
+                continue;
+            }
+            log::info!("⏰ Running catch-up for missed fire time of config {}", config_id);
+        }
+
+        log::info!("⏰ In-app scheduler starting backup for config {}", config_id);
+        update_job(&jobs, &config_id, |info| {
+            info.running = true;
+            info.last_run = Some(chrono::Utc::now().timestamp());
+        });
+
+        let error = run_and_record(&app, &config_id).await;
+        match &error {
+            None => log::info!("✅ In-app scheduled backup completed for config {}", config_id),
+            Some(e) => log::error!("❌ In-app scheduled backup failed for config {}: {}", config_id, e),
+        }
+        update_job(&jobs, &config_id, |info| {
+            info.running = false;
+            info.last_error = error;
+        });
     }
 }
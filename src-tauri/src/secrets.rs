@@ -0,0 +1,161 @@
+/// Secret providers for unattended encrypted backups
+///
+/// Scheduled backups run headless, so nobody can type the password. A config
+/// stores a `SecretSource` (where to fetch the password), never the password
+/// itself; it is resolved right before the backup runs and zeroized after use.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use zeroize::Zeroizing;
+
+/// Where to fetch the password of an encrypted config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum SecretSource {
+    /// File containing the password (must not be readable by group/others)
+    File { path: String },
+    /// Environment variable of the scheduled process
+    Env { name: String },
+    /// Program whose stdout is the password (run directly, no shell)
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// OS keyring entry (macOS Keychain via `security`, Linux Secret Service via `secret-tool`)
+    Keyring { service: String, account: String },
+}
+
+impl SecretSource {
+    /// Short description for logs and diagnostics (never includes the secret)
+    pub fn describe(&self) -> String {
+        match self {
+            SecretSource::File { path } => format!("file {}", path),
+            SecretSource::Env { name } => format!("environment variable {}", name),
+            SecretSource::Command { program, .. } => format!("command {}", program),
+            SecretSource::Keyring { service, account } => format!("keyring {}/{}", service, account),
+        }
+    }
+}
+
+/// Fetch the password from `source`
+///
+/// A single trailing newline is removed (as written by `echo` or most editors);
+/// an empty result is an error so a misconfigured provider never yields a blank password.
+pub fn resolve_secret(source: &SecretSource) -> Result<Zeroizing<String>, String> {
+    let secret = match source {
+        SecretSource::File { path } => read_secret_file(Path::new(path))?,
+        SecretSource::Env { name } => Zeroizing::new(
+            std::env::var(name).map_err(|_| format!("Environment variable {} is not set", name))?,
+        ),
+        SecretSource::Command { program, args } => run_secret_command(program, args)?,
+        SecretSource::Keyring { service, account } => read_keyring(service, account)?,
+    };
+
+    let trimmed = secret
+        .strip_suffix("\r\n")
+        .or_else(|| secret.strip_suffix('\n'))
+        .unwrap_or(&secret);
+    if trimmed.is_empty() {
+        return Err(format!("Secret from {} is empty", source.describe()));
+    }
+    Ok(Zeroizing::new(trimmed.to_string()))
+}
+
+/// Read a password file, refusing files other users could read
+fn read_secret_file(path: &Path) -> Result<Zeroizing<String>, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path)
+            .map_err(|e| format!("Failed to read secret file {}: {}", path.display(), e))?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "Secret file {} has permissions {:o}; restrict it with chmod 600",
+                path.display(),
+                mode & 0o777
+            ));
+        }
+    }
+
+    fs::read_to_string(path)
+        .map(Zeroizing::new)
+        .map_err(|e| format!("Failed to read secret file {}: {}", path.display(), e))
+}
+
+/// Run a password command and capture its stdout
+fn run_secret_command(program: &str, args: &[String]) -> Result<Zeroizing<String>, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run secret command {}: {}", program, e))?;
+    let stdout = Zeroizing::new(output.stdout);
+
+    if !output.status.success() {
+        // stderr only: stdout may hold part of the secret
+        return Err(format!(
+            "Secret command {} failed ({}): {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    String::from_utf8(stdout.to_vec())
+        .map(Zeroizing::new)
+        .map_err(|_| format!("Secret command {} printed non-UTF-8 output", program))
+}
+
+/// Look up a generic password in the OS keyring
+fn read_keyring(service: &str, account: &str) -> Result<Zeroizing<String>, String> {
+    let (program, args): (&str, Vec<&str>) = if cfg!(target_os = "macos") {
+        ("security", vec!["find-generic-password", "-s", service, "-a", account, "-w"])
+    } else if cfg!(target_os = "linux") {
+        ("secret-tool", vec!["lookup", "service", service, "account", account])
+    } else {
+        return Err("No OS keyring is supported on this platform".to_string());
+    };
+
+    let args: Vec<String> = args.into_iter().map(String::from).collect();
+    run_secret_command(program, &args)
+        .map_err(|e| format!("Keyring lookup for {}/{} failed: {}", service, account, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_serialization() {
+        let source = SecretSource::Keyring {
+            service: "InLocker".to_string(),
+            account: "docs".to_string(),
+        };
+        let json = serde_json::to_string(&source).unwrap();
+        assert_eq!(json, r#"{"provider":"keyring","service":"InLocker","account":"docs"}"#);
+        assert_eq!(serde_json::from_str::<SecretSource>(&json).unwrap(), source);
+    }
+
+    #[test]
+    fn test_command_output_trimmed() {
+        let source = SecretSource::Command {
+            program: "printf".to_string(),
+            args: vec!["CommandPassword123!\\n".to_string()],
+        };
+        assert_eq!(resolve_secret(&source).unwrap().as_str(), "CommandPassword123!");
+    }
+
+    #[test]
+    fn test_missing_env_and_empty_secret_rejected() {
+        let missing = SecretSource::Env { name: "INLOCKER_TEST_UNSET_SECRET".to_string() };
+        assert!(resolve_secret(&missing).is_err());
+
+        let empty = SecretSource::Command { program: "true".to_string(), args: vec![] };
+        assert!(resolve_secret(&empty).unwrap_err().contains("empty"));
+    }
+}
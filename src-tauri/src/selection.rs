@@ -0,0 +1,208 @@
+/// Selective restore: which archive entries to restore, and where to
+///
+/// Paths are relative to the archive root and `/`-separated, as listed by
+/// `browse::list_archive_entries`. An explicit path selects a file, or a
+/// directory with everything under it. Patterns use `*` (any characters within
+/// one path component), `?` (one character) and `**` (any number of
+/// components); a pattern matches an entry when it matches the entry's path or
+/// one of its parent directories, so `photos/**/*.jpg` selects images at any
+/// depth and `**/node_modules` whole directories.
+///
+/// An entry is restored under its archive path, renamed by the longest
+/// matching `rename` rule, with `strip_components` leading components removed;
+/// entries left without a path are skipped.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Entries to restore from an archive (the default restores everything as archived)
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RestoreSelection {
+    /// Archive paths of files or directories to restore (empty = everything)
+    pub paths: Vec<String>,
+    /// Only entries matching one of these patterns (empty = everything)
+    pub include: Vec<String>,
+    /// Entries matching one of these patterns are skipped
+    pub exclude: Vec<String>,
+    /// Leading path components removed from restored paths
+    pub strip_components: usize,
+    /// Archive path (file or directory) -> path to restore it under
+    pub rename: BTreeMap<String, String>,
+}
+
+/// Normal components of a relative path; None if it could leave the destination
+fn components(path: &Path) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(parts)
+}
+
+/// Components of a user-supplied path (a leading `/` means the archive root)
+fn user_components(path: &str) -> Result<Vec<String>, String> {
+    components(Path::new(path.trim_start_matches('/')))
+        .filter(|parts| !parts.is_empty())
+        .ok_or_else(|| format!("Invalid restore path: {:?}", path))
+}
+
+/// Whether `text` matches a single-component pattern with `*` and `?`
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it currently covers up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p + 1, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn path_match(pattern: &[&str], parts: &[String]) -> bool {
+    match pattern.split_first() {
+        None => parts.is_empty(),
+        Some((&"**", rest)) => (0..=parts.len()).any(|skip| path_match(rest, &parts[skip..])),
+        Some((first, rest)) => {
+            !parts.is_empty() && wildcard_match(first, &parts[0]) && path_match(rest, &parts[1..])
+        }
+    }
+}
+
+/// Whether a pattern matches the entry or one of its parent directories
+fn pattern_matches(pattern: &str, parts: &[String]) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+    !pattern.is_empty() && (1..=parts.len()).any(|len| path_match(&pattern, &parts[..len]))
+}
+
+impl RestoreSelection {
+    /// Whether every entry is restored under its archive path
+    pub fn is_everything(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Reject paths that would leave the restore destination
+    pub fn validate(&self) -> Result<(), String> {
+        for path in self.paths.iter().chain(self.rename.keys()).chain(self.rename.values()) {
+            user_components(path)?;
+        }
+        for pattern in self.include.iter().chain(&self.exclude) {
+            if pattern.split('/').any(|c| c == "..") {
+                return Err(format!("Invalid restore pattern: {:?}", pattern));
+            }
+        }
+        Ok(())
+    }
+
+    /// Where an archive entry is restored, relative to the destination (None = not selected)
+    pub fn target(&self, archive_path: &Path) -> Option<PathBuf> {
+        let parts = components(archive_path).filter(|parts| !parts.is_empty())?;
+
+        let selected = self.paths.is_empty()
+            || self.paths.iter().any(|path| user_components(path).is_ok_and(|p| parts.starts_with(&p)));
+        let included = self.include.is_empty() || self.include.iter().any(|p| pattern_matches(p, &parts));
+        let excluded = self.exclude.iter().any(|p| pattern_matches(p, &parts));
+        if !selected || !included || excluded {
+            return None;
+        }
+
+        let renamed = self
+            .rename
+            .iter()
+            .filter_map(|(from, to)| Some((user_components(from).ok()?, user_components(to).ok()?)))
+            .filter(|(from, _)| parts.starts_with(from))
+            .max_by_key(|(from, _)| from.len())
+            .map(|(from, to)| to.into_iter().chain(parts[from.len()..].iter().cloned()).collect())
+            .unwrap_or(parts);
+
+        if renamed.len() <= self.strip_components {
+            return None;
+        }
+        Some(renamed[self.strip_components..].iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(selection: &RestoreSelection, path: &str) -> Option<String> {
+        selection.target(Path::new(path)).map(|p| p.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(wildcard_match("*.jpg", "img.jpg"));
+        assert!(wildcard_match("img_??.*", "img_01.png"));
+        assert!(wildcard_match("*a*b*", "xxaYYbzz"));
+        assert!(!wildcard_match("*.jpg", "img.jpeg"));
+        assert!(!wildcard_match("?", ""));
+
+        let parts = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        assert!(pattern_matches("photos/**/*.jpg", &parts("photos/2024/05/a.jpg")));
+        assert!(pattern_matches("photos/**/*.jpg", &parts("photos/a.jpg")));
+        assert!(pattern_matches("**/node_modules", &parts("app/node_modules/x/index.js")));
+        assert!(pattern_matches("docs", &parts("docs/a.txt")));
+        assert!(!pattern_matches("*.log", &parts("logs/a.log")), "Without ** only the top level matches");
+    }
+
+    #[test]
+    fn test_selection_targets() {
+        assert!(RestoreSelection::default().is_everything());
+        assert_eq!(target(&RestoreSelection::default(), "docs/a.txt").as_deref(), Some("docs/a.txt"));
+
+        let selection = RestoreSelection {
+            paths: vec!["/docs".to_string()],
+            exclude: vec!["**/*.tmp".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(target(&selection, "docs/sub/a.txt").as_deref(), Some("docs/sub/a.txt"));
+        assert_eq!(target(&selection, "docs/sub/a.tmp"), None);
+        assert_eq!(target(&selection, "docsx/a.txt"), None, "Paths match whole components");
+
+        let selection = RestoreSelection {
+            include: vec!["photos/**/*.jpg".to_string()],
+            strip_components: 1,
+            ..Default::default()
+        };
+        assert_eq!(target(&selection, "photos/2024/a.jpg").as_deref(), Some("2024/a.jpg"));
+        assert_eq!(target(&selection, "photos/2024/a.png"), None);
+
+        // The longest rename rule wins, then components are stripped from the new path
+        let selection = RestoreSelection {
+            rename: BTreeMap::from([
+                ("docs".to_string(), "old-docs".to_string()),
+                ("docs/report.pdf".to_string(), "report (restored).pdf".to_string()),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(target(&selection, "docs/sub/a.txt").as_deref(), Some("old-docs/sub/a.txt"));
+        assert_eq!(target(&selection, "docs/report.pdf").as_deref(), Some("report (restored).pdf"));
+        let stripped = RestoreSelection { strip_components: 1, ..selection };
+        assert_eq!(target(&stripped, "docs/report.pdf"), None, "Nothing left after stripping");
+
+        let unsafe_rename = RestoreSelection {
+            rename: BTreeMap::from([("docs".to_string(), "../outside".to_string())]),
+            ..Default::default()
+        };
+        assert!(unsafe_rename.validate().is_err());
+        assert!(RestoreSelection { paths: vec!["a/../../b".to_string()], ..Default::default() }.validate().is_err());
+    }
+}
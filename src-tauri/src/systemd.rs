@@ -0,0 +1,331 @@
+/// systemd user-timer integration for scheduled backups on Linux
+///
+/// Each scheduled config gets a `.service` (runs `inlocker --backup <id>`) and a
+/// `.timer` (one `OnCalendar=` line per cron branch) in ~/.config/systemd/user,
+/// managed with `systemctl --user`. Unit generation is pure so it can be tested
+/// without systemd running.
+
+use crate::cron::{CronField, CronSchedule};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const WEEKDAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Get the systemd user unit directory ($XDG_CONFIG_HOME/systemd/user)
+fn get_units_dir() -> Result<PathBuf, String> {
+    let config_home = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Ok(config_home.join("systemd/user"))
+}
+
+/// Unit name (without suffix) for a config; characters systemd rejects become '_'
+pub fn get_unit_name(config_id: &str) -> String {
+    let id: String = config_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("inlocker-backup-{}", id)
+}
+
+/// Get the .timer unit path for a config
+pub fn get_timer_path(config_id: &str) -> Result<PathBuf, String> {
+    Ok(get_units_dir()?.join(format!("{}.timer", get_unit_name(config_id))))
+}
+
+/// Get the .service unit path for a config
+pub fn get_service_path(config_id: &str) -> Result<PathBuf, String> {
+    Ok(get_units_dir()?.join(format!("{}.service", get_unit_name(config_id))))
+}
+
+/// Get logs directory path ($XDG_STATE_HOME/InLocker/logs)
+pub fn get_logs_dir() -> Result<PathBuf, String> {
+    let state_home = match std::env::var("XDG_STATE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
+            PathBuf::from(home).join(".local/state")
+        }
+    };
+    let logs_dir = state_home.join("InLocker/logs");
+
+    if !logs_dir.exists() {
+        fs::create_dir_all(&logs_dir)
+            .map_err(|e| format!("Failed to create logs directory: {}", e))?;
+        log::info!("Created logs directory: {:?}", logs_dir);
+    }
+
+    Ok(logs_dir)
+}
+
+/// Get log file path for a config
+pub fn get_log_path(config_id: &str) -> Result<PathBuf, String> {
+    Ok(get_logs_dir()?.join(format!("scheduled-{}.log", config_id)))
+}
+
+/// Get error log file path for a config
+pub fn get_error_log_path(config_id: &str) -> Result<PathBuf, String> {
+    Ok(get_logs_dir()?.join(format!("scheduled-{}.err", config_id)))
+}
+
+/// Get the executable path to run (the AppImage itself when running from one)
+pub fn get_executable_path() -> Result<String, String> {
+    if let Ok(appimage) = std::env::var("APPIMAGE") {
+        if !appimage.is_empty() {
+            return Ok(appimage);
+        }
+    }
+
+    let current = std::env::current_exe()
+        .map_err(|e| format!("Failed to get current exe: {}", e))?;
+    current
+        .to_str()
+        .map(String::from)
+        .ok_or_else(|| "Invalid UTF-8 in exe path".to_string())
+}
+
+/// Escape text for a unit file value (`%` starts a specifier, newlines end the value)
+fn escape_unit_value(value: &str) -> String {
+    value
+        .replace('%', "%%")
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Quote one ExecStart argument
+fn quote_exec_arg(arg: &str) -> String {
+    format!("\"{}\"", escape_unit_value(arg).replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Format a cron field as an OnCalendar component: `*`, or values with runs as `a..b`
+fn calendar_component(field: &CronField, format_value: impl Fn(u32) -> String) -> String {
+    if field.is_full() {
+        return "*".to_string();
+    }
+
+    let mut parts = Vec::new();
+    let values = field.values();
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1] == values[j] + 1 {
+            j += 1;
+        }
+        // Two adjacent values read better as a list than as a range
+        if j >= i + 2 {
+            parts.push(format!("{}..{}", format_value(values[i]), format_value(values[j])));
+        } else {
+            parts.extend(values[i..=j].iter().map(|&v| format_value(v)));
+        }
+        i = j + 1;
+    }
+    parts.join(",")
+}
+
+/// Convert a cron expression to OnCalendar= values (local time)
+///
+/// systemd ANDs the weekday with the date, while cron ORs a restricted day
+/// and a restricted weekday: that case yields two entries.
+pub fn cron_to_on_calendar(cron_expr: &str) -> Result<Vec<String>, String> {
+    let schedule = CronSchedule::parse(cron_expr)?;
+
+    let two_digits = |v: u32| format!("{:02}", v);
+    let time = format!(
+        "{}:{}:00",
+        calendar_component(&schedule.hours, two_digits),
+        calendar_component(&schedule.minutes, two_digits)
+    );
+    let months = calendar_component(&schedule.months, two_digits);
+    let days = calendar_component(&schedule.days, two_digits);
+    let weekdays = calendar_component(&schedule.weekdays, |v| WEEKDAY_NAMES[v as usize].to_string());
+
+    Ok(if schedule.days_or_weekdays() {
+        vec![
+            format!("*-{}-{} {}", months, days, time),
+            format!("{} *-{}-* {}", weekdays, months, time),
+        ]
+    } else if schedule.weekdays.is_full() {
+        vec![format!("*-{}-{} {}", months, days, time)]
+    } else {
+        vec![format!("{} *-{}-{} {}", weekdays, months, days, time)]
+    })
+}
+
+/// Generate the .service unit (one-shot run of the scheduled backup)
+pub fn generate_service_unit(
+    config_id: &str,
+    config_name: &str,
+    app_path: &str,
+    log_path: &str,
+    err_log_path: &str,
+) -> String {
+    format!(
+        r#"[Unit]
+Description=InLocker scheduled backup: {}
+
+[Service]
+Type=oneshot
+ExecStart={} --backup {}
+StandardOutput=append:{}
+StandardError=append:{}
+"#,
+        escape_unit_value(config_name),
+        quote_exec_arg(app_path),
+        quote_exec_arg(config_id),
+        escape_unit_value(log_path),
+        escape_unit_value(err_log_path)
+    )
+}
+
+/// Generate the .timer unit for a cron expression
+pub fn generate_timer_unit(config_id: &str, config_name: &str, cron_expr: &str) -> Result<String, String> {
+    let mut timer = format!(
+        "[Unit]\nDescription=InLocker schedule: {}\n\n[Timer]\n",
+        escape_unit_value(config_name)
+    );
+    for calendar in cron_to_on_calendar(cron_expr)? {
+        timer.push_str(&format!("OnCalendar={}\n", calendar));
+    }
+    // Replay a run missed while the machine was off once the timer is active again
+    timer.push_str("Persistent=true\n");
+    timer.push_str(&format!(
+        "Unit={}.service\n\n[Install]\nWantedBy=timers.target\n",
+        get_unit_name(config_id)
+    ));
+    Ok(timer)
+}
+
+/// Run `systemctl --user` with the given arguments
+fn systemctl(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to execute systemctl: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "systemctl --user {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Check if the timer of a config is active
+pub fn is_timer_active(config_id: &str) -> Result<bool, String> {
+    let output = Command::new("systemctl")
+        .args(["--user", "is-active", &format!("{}.timer", get_unit_name(config_id))])
+        .output()
+        .map_err(|e| format!("Failed to execute systemctl: {}", e))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == "active")
+}
+
+/// Install the service and timer units and start the timer
+pub fn install_timer(config_id: &str, config_name: &str, cron_expr: &str, app_path: &str) -> Result<(), String> {
+    log::info!("=== Installing systemd timer for config: {} ===", config_id);
+    log::info!("Cron expression: {}", cron_expr);
+
+    let timer = generate_timer_unit(config_id, config_name, cron_expr)?;
+    let service = generate_service_unit(
+        config_id,
+        config_name,
+        app_path,
+        &get_log_path(config_id)?.to_string_lossy(),
+        &get_error_log_path(config_id)?.to_string_lossy(),
+    );
+
+    let dir = get_units_dir()?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create systemd user unit directory: {}", e))?;
+    fs::write(get_service_path(config_id)?, service)
+        .map_err(|e| format!("Failed to write service unit: {}", e))?;
+    fs::write(get_timer_path(config_id)?, timer)
+        .map_err(|e| format!("Failed to write timer unit: {}", e))?;
+    log::info!("✓ Unit files written to {:?}", dir);
+
+    let timer_unit = format!("{}.timer", get_unit_name(config_id));
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", &timer_unit])?;
+    // Restart so an already running timer picks up a changed OnCalendar
+    systemctl(&["restart", &timer_unit])?;
+
+    if !is_timer_active(config_id)? {
+        return Err(format!("Timer installed but not active: {}", timer_unit));
+    }
+    log::info!("✓ Timer {} active", timer_unit);
+    Ok(())
+}
+
+/// Stop and remove the units of a config
+pub fn uninstall_timer(config_id: &str) -> Result<(), String> {
+    let timer_unit = format!("{}.timer", get_unit_name(config_id));
+
+    // It's ok if the timer was never loaded
+    if let Err(e) = systemctl(&["disable", "--now", &timer_unit]) {
+        log::warn!("Failed to disable timer (continuing anyway): {}", e);
+    }
+
+    for path in [get_timer_path(config_id)?, get_service_path(config_id)?] {
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete unit file: {}", e))?;
+            log::info!("Deleted unit file: {:?}", path);
+        }
+    }
+
+    if let Err(e) = systemctl(&["daemon-reload"]) {
+        log::warn!("Failed to reload systemd user units: {}", e);
+    }
+    log::info!("Uninstalled systemd timer: {}", timer_unit);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cron_to_on_calendar() {
+        assert_eq!(cron_to_on_calendar("0 9 * * 1-5").unwrap(), vec!["Mon..Fri *-*-* 09:00:00"]);
+        assert_eq!(cron_to_on_calendar("*/15 * * * *").unwrap(), vec!["*-*-* *:00,15,30,45:00"]);
+        assert_eq!(cron_to_on_calendar("@monthly").unwrap(), vec!["*-*-01 00:00:00"]);
+        assert_eq!(cron_to_on_calendar("30 2 * jan,jul sat,sun").unwrap(), vec!["Sun,Sat *-01,07-* 02:30:00"]);
+
+        // Restricted day and weekday: cron ORs them, so two entries
+        assert_eq!(
+            cron_to_on_calendar("0 9 1 * mon").unwrap(),
+            vec!["*-*-01 09:00:00", "Mon *-*-* 09:00:00"]
+        );
+        assert!(cron_to_on_calendar("0 25 * * *").is_err());
+    }
+
+    #[test]
+    fn test_unit_files() {
+        let timer = generate_timer_unit("abc-123", "Docs", "0 2 * * *").unwrap();
+        assert!(timer.contains("OnCalendar=*-*-* 02:00:00\n"));
+        assert!(timer.contains("Persistent=true\n"));
+        assert!(timer.contains("Unit=inlocker-backup-abc-123.service\n"));
+        assert!(timer.contains("WantedBy=timers.target"));
+
+        let service = generate_service_unit(
+            "abc-123",
+            "100% \"Docs\"\nInjected=1",
+            "/opt/In Locker/inlocker",
+            "/home/u/.local/state/InLocker/logs/scheduled-abc-123.log",
+            "/home/u/.local/state/InLocker/logs/scheduled-abc-123.err",
+        );
+        assert!(service.contains("ExecStart=\"/opt/In Locker/inlocker\" --backup \"abc-123\"\n"));
+        assert!(service.contains("Description=InLocker scheduled backup: 100%% \"Docs\" Injected=1\n"));
+        assert!(!service.contains("\nInjected=1"));
+        assert_eq!(get_unit_name("a/b c"), "inlocker-backup-a_b_c");
+    }
+}
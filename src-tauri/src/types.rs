@@ -1,3 +1,5 @@
+use crate::crypto::RecoveryEscrow;
+use crate::secrets::SecretSource;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -32,6 +34,30 @@ pub struct BackupConfig {
     pub last_backup_files_count: Option<usize>,
     #[serde(default)]
     pub last_backup_checksum: Option<String>,
+    /// When the last successful scheduled (or catch-up) backup finished
+    #[serde(default)]
+    pub last_scheduled_run_at: Option<i64>,
+    /// Attempts of the last scheduled run (more than one when it was retried)
+    #[serde(default)]
+    pub last_run_attempts: Vec<RunAttempt>,
+    /// Key derivation strength for encrypted backups (None = use app preference)
+    #[serde(default)]
+    pub kdf_strength: Option<KdfStrength>,
+    /// Recovery key set up for encrypted backups (escrowed under the password, never in clear)
+    #[serde(default)]
+    pub recovery: Option<RecoveryEscrow>,
+    /// X25519 public keys that can decrypt this config's archives (lets scheduled backups run without a password)
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    /// Where unattended backups fetch the password (the reference is persisted, never the secret)
+    #[serde(default)]
+    pub password_source: Option<SecretSource>,
+    /// Real-time trigger: back up when files under source_path change
+    #[serde(default)]
+    pub watch: Option<WatchConfig>,
+    /// Which archives to keep in the destination (None = keep everything)
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>,
 }
 
 fn default_backup_type() -> BackupType {
@@ -47,8 +73,149 @@ fn default_backup_mode() -> BackupMode {
 pub struct ScheduleConfig {
     pub cron_expression: String,
     pub preset: Option<SchedulePreset>,
+    /// Next fire time (Unix seconds), computed from cron_expression in local time
     pub next_run: Option<i64>,
     pub enabled: bool,
+    /// Run with the in-app scheduler instead of launchd/systemd (only while the app is open)
+    #[serde(default)]
+    pub in_app: bool,
+    /// What to do about fire times missed while the computer was asleep or off
+    #[serde(default)]
+    pub catch_up: CatchUpPolicy,
+    /// Retries of failed scheduled runs
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+/// Retry policy of failed scheduled backups (exponential backoff)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one (1 = no retry)
+    pub max_attempts: u32,
+    /// Wait before the first retry
+    pub initial_delay_secs: u64,
+    /// Each further wait is multiplied by this
+    pub backoff_factor: u32,
+    /// Upper bound of a single wait
+    pub max_delay_secs: u64,
+    /// Error classes worth retrying
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay_secs: 60,
+            backoff_factor: 2,
+            max_delay_secs: 900,
+            retry_on: vec![
+                ErrorClass::DestinationUnavailable,
+                ErrorClass::SourceUnavailable,
+                ErrorClass::SecretUnavailable,
+                ErrorClass::Io,
+            ],
+        }
+    }
+}
+
+/// Kind of failure of a backup run, used to decide whether to retry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// Destination folder missing (volume not mounted, network share offline)
+    DestinationUnavailable,
+    /// Source folder missing
+    SourceUnavailable,
+    /// Password provider failed (keychain locked, command failed)
+    SecretUnavailable,
+    /// Transient I/O failure (timeout, device busy, network mount dropped)
+    Io,
+    /// Anything else (invalid config, wrong password, permission denied, disk full, ...); retrying won't help
+    Other,
+}
+
+/// One attempt of a scheduled backup run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunAttempt {
+    pub attempt: u32,
+    pub started_at: i64,
+    pub finished_at: i64,
+    /// None if the attempt succeeded
+    pub error: Option<String>,
+    pub error_class: Option<ErrorClass>,
+}
+
+/// Missed-run policy of a schedule
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Wait for the next scheduled time (default)
+    #[default]
+    Skip,
+    /// Run one backup for any number of missed fire times
+    RunOnce,
+    /// Run one backup only if the last scheduled run is older than this
+    IfOlderThan { max_age_secs: u64 },
+}
+
+/// State of a job in the in-app scheduler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJobInfo {
+    pub config_id: String,
+    pub cron_expression: String,
+    pub next_run: Option<i64>,
+    pub last_run: Option<i64>,
+    /// Error of the last run (None if it succeeded or never ran)
+    pub last_error: Option<String>,
+    pub running: bool,
+}
+
+/// Retention rules for the archives of a config (see retention.rs)
+///
+/// An archive is kept if any `keep_*` rule selects it (all archives if no rule
+/// is set); `max_total_size` then drops the oldest chains. The newest archive
+/// and everything a kept incremental depends on are never deleted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep the N most recent archives
+    pub keep_last: Option<u32>,
+    /// Keep the newest archive of each of the last N days that have one
+    pub keep_daily: Option<u32>,
+    /// Same per ISO week
+    pub keep_weekly: Option<u32>,
+    /// Same per calendar month
+    pub keep_monthly: Option<u32>,
+    /// Same per calendar year
+    pub keep_yearly: Option<u32>,
+    /// Keep archives created within this many seconds of the newest one
+    pub keep_within_secs: Option<u64>,
+    /// Upper bound in bytes for all archives of the config
+    pub max_total_size: Option<u64>,
+    /// Prune automatically after each successful backup
+    pub prune_after_backup: bool,
+}
+
+/// Change-triggered backup settings (file-system watcher on the source folder)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchConfig {
+    pub enabled: bool,
+    /// Seconds without changes before a backup starts (debounces bursts of writes)
+    #[serde(default = "default_quiet_period")]
+    pub quiet_period_secs: u64,
+    /// Minimum seconds between two change-triggered backups
+    #[serde(default = "default_min_interval")]
+    pub min_interval_secs: u64,
+}
+
+fn default_quiet_period() -> u64 {
+    30
+}
+
+fn default_min_interval() -> u64 {
+    300
 }
 
 /// Schedule presets
@@ -82,6 +249,22 @@ pub enum BackupMode {
     Encrypted,
 }
 
+/// Argon2id cost level used when creating encrypted backups
+///
+/// Stored parameters travel with each archive, so changing this never affects
+/// existing backups; stronger levels only make new archives slower to brute-force.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KdfStrength {
+    /// 64 MB, 3 iterations (default)
+    #[default]
+    Standard,
+    /// 256 MB, 4 iterations
+    High,
+    /// 1 GB, 4 iterations
+    Maximum,
+}
+
 /// Represents a backup job execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupJob {
@@ -108,6 +291,69 @@ pub enum BackupStatus {
     Running,
     Completed,
     Failed,
+    Cancelled,
+}
+
+/// What started a backup run
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobTrigger {
+    /// "Backup now" in the app
+    Manual,
+    /// OS agent or in-app scheduler (catch-ups and retries included)
+    Scheduled,
+    /// Change detected by the folder watcher
+    Watch,
+    /// Headless `inlocker backup`
+    Cli,
+    /// Archive found when importing a destination (see catalog.rs), not a run on this machine
+    Import,
+}
+
+/// One run in the job history (see history.rs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub job_id: String,
+    pub config_id: String,
+    pub trigger: JobTrigger,
+    pub status: BackupStatus,
+    pub backup_type: BackupType,
+    pub started_at: i64,
+    pub completed_at: i64,
+    pub duration_secs: i64,
+    pub original_size: Option<u64>,
+    pub compressed_size: Option<u64>,
+    pub files_count: Option<usize>,
+    pub changed_files_count: Option<usize>,
+    pub error_message: Option<String>,
+    pub backup_path: Option<String>,
+    pub checksum: Option<String>,
+}
+
+/// Filters and paging of a job history query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// Only runs of this config (all configs if None)
+    pub config_id: Option<String>,
+    pub status: Option<BackupStatus>,
+    pub trigger: Option<JobTrigger>,
+    /// Only runs started at or after this Unix timestamp
+    pub since: Option<i64>,
+    /// Only runs started before this Unix timestamp
+    pub until: Option<i64>,
+    /// Number of matching runs to skip (newest first)
+    pub offset: usize,
+    /// Page size (0 = default page size)
+    pub limit: usize,
+}
+
+/// One page of job history, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPage {
+    pub records: Vec<JobRecord>,
+    /// Number of runs matching the filters (all pages)
+    pub total: usize,
 }
 
 /// Result of a backup operation
@@ -118,16 +364,36 @@ pub struct BackupResult {
     pub job: Option<BackupJob>,
 }
 
+/// Newly generated X25519 key pair for recipient encryption
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipientKeyPair {
+    pub public_key: String,  // Goes into BackupConfig.recipients
+    pub private_key: String, // Shown once; needed to restore
+}
+
+/// Result of saving a config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedConfig {
+    #[serde(flatten)]
+    pub config: BackupConfig,
+    /// Recovery key generated when the config became encrypted; shown once, only its escrow is stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_key: Option<String>,
+}
+
 /// Backup manifest for incremental backups
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupManifest {
     pub config_id: String,
     pub created_at: i64,
     pub files: HashMap<String, FileMetadata>,
+    /// Content hash key (hex) of encrypted configs; only ever stored inside the encrypted manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_key: Option<String>,
 }
 
 /// Metadata for a single file in the manifest
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FileMetadata {
     pub path: String,
     pub size: u64,
@@ -135,6 +401,51 @@ pub struct FileMetadata {
     pub checksum: String,
 }
 
+/// Lineage of one archive, stored inside it (see `backup::ARCHIVE_INFO_PATH`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveInfo {
+    /// Archive name without extension (`Bkp_InLocker_<name>_<kind>_<time>`)
+    pub archive_id: String,
+    pub config_id: String,
+    /// Name and source folder of the config, to rebuild it from the destination alone
+    #[serde(default)]
+    pub config_name: String,
+    #[serde(default)]
+    pub source_path: String,
+    /// InLocker version that wrote the archive
+    #[serde(default)]
+    pub app_version: String,
+    /// What the archive actually holds (an incremental run without a previous manifest is full)
+    pub backup_type: BackupType,
+    /// Archive this incremental builds on (None for full backups)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub created_at: i64,
+    /// Paths (relative to the source) deleted since the parent archive
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<String>,
+    /// Files moved since the parent archive without content changes (not stored again)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renamed: Vec<RenamedFile>,
+}
+
+/// Files stored in one archive, stored inside it (see `backup::ARCHIVE_MANIFEST_PATH`)
+///
+/// Checksums are plain SHA-256 of the contents as archived (the manifest of an
+/// encrypted archive is encrypted with it).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveManifest {
+    pub archive_id: String,
+    pub files: Vec<FileMetadata>,
+}
+
+/// A file moved between two backups, paths relative to the source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+}
+
 /// Application preferences
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppPreferences {
@@ -144,6 +455,9 @@ pub struct AppPreferences {
     /// Auto-close delay in milliseconds (default: 3000ms = 3 seconds)
     #[serde(default = "default_auto_close_delay")]
     pub auto_close_delay_ms: u32,
+    /// Default key derivation strength for new encrypted backups
+    #[serde(default)]
+    pub kdf_strength: KdfStrength,
 }
 
 fn default_auto_close() -> bool {
@@ -159,6 +473,7 @@ impl Default for AppPreferences {
         Self {
             auto_close_progress_window: true,
             auto_close_delay_ms: 3000,
+            kdf_strength: KdfStrength::Standard,
         }
     }
 }
@@ -169,6 +484,7 @@ impl AppPreferences {
         Self {
             auto_close_progress_window: false,
             auto_close_delay_ms: 3000,
+            kdf_strength: KdfStrength::Standard,
         }
     }
 }
@@ -177,7 +493,10 @@ impl AppPreferences {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScheduleDiagnostics {
     pub config_id: String,
+    /// Scheduler backend in use ("launchd", "systemd", or "unsupported" on other platforms)
+    pub backend: String,
     pub has_schedule: bool,
+    /// Schedule file exists (launchd plist or systemd timer unit)
     pub plist_exists: bool,
     pub plist_path: Option<String>,
     pub agent_loaded: bool,
@@ -0,0 +1,289 @@
+/// Change-triggered backups
+///
+/// Each config with `watch.enabled` gets a file-system watcher on its source
+/// folder. Events are debounced (quiet period) and rate-limited (minimum
+/// interval), then an incremental backup runs through `run_backup_now` (or one
+/// of the config's own type, when it can't run incrementals unattended).
+
+use crate::commands::{self, AppState};
+use crate::types::{BackupConfig, BackupMode, BackupType, JobTrigger, WatchConfig};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// How often the watcher thread wakes up to check whether a backup is due
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Active watcher for one config (dropping it stops the watcher thread)
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    source_path: String,
+    settings: WatchConfig,
+}
+
+/// Debounce state: when the last change happened and when the last backup ran
+#[derive(Debug)]
+pub struct Debouncer {
+    quiet_period: Duration,
+    min_interval: Duration,
+    last_change: Option<Instant>,
+    last_run: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(settings: &WatchConfig) -> Self {
+        Self {
+            quiet_period: Duration::from_secs(settings.quiet_period_secs),
+            min_interval: Duration::from_secs(settings.min_interval_secs),
+            last_change: None,
+            last_run: None,
+        }
+    }
+
+    /// Record a file-system change
+    pub fn record_change(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    /// A backup is due once changes are pending, have settled for the quiet
+    /// period, and the minimum interval since the last backup has passed
+    pub fn is_due(&self, now: Instant) -> bool {
+        let settled = self
+            .last_change
+            .is_some_and(|changed| now.duration_since(changed) >= self.quiet_period);
+        let rested = self
+            .last_run
+            .is_none_or(|ran| now.duration_since(ran) >= self.min_interval);
+        settled && rested
+    }
+
+    /// Record that a backup started (changes seen so far are covered by it)
+    pub fn mark_run(&mut self, now: Instant) {
+        self.last_run = Some(now);
+        self.last_change = None;
+    }
+}
+
+/// Whether an event is a change under the source that the backup would pick up
+///
+/// Reads are ignored, and so is anything under the destination (a destination
+/// inside the source would otherwise trigger itself forever).
+fn is_relevant_change(event: &Event, destination: &Path) -> bool {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+    event.paths.iter().any(|path| !path.starts_with(destination))
+}
+
+/// Start or stop watchers so they match `configs`
+///
+/// Watchers whose source folder and settings didn't change keep running.
+pub fn sync_watchers(app: &AppHandle, configs: &[BackupConfig]) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut watchers = state.watchers.lock().map_err(|e| e.to_string())?;
+
+    let wanted: HashMap<&str, &BackupConfig> = configs
+        .iter()
+        .filter(|c| c.enabled && c.watch.as_ref().is_some_and(|w| w.enabled))
+        .map(|c| (c.id.as_str(), c))
+        .collect();
+
+    watchers.retain(|config_id, watcher| {
+        let keep = wanted.get(config_id.as_str()).is_some_and(|c| {
+            c.source_path == watcher.source_path && c.watch.as_ref() == Some(&watcher.settings)
+        });
+        if !keep {
+            log::info!("👁️  Stopped watching config {}", config_id);
+        }
+        keep
+    });
+
+    for (config_id, config) in wanted {
+        if watchers.contains_key(config_id) {
+            continue;
+        }
+        match start_watcher(app.clone(), config) {
+            Ok(watcher) => {
+                log::info!("👁️  Watching {} for changes (config {})", config.source_path, config_id);
+                watchers.insert(config_id.to_string(), watcher);
+            }
+            Err(e) => log::error!("Failed to watch {} for config {}: {}", config.source_path, config_id, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Watch the source folder of `config` and run backups from a dedicated thread
+fn start_watcher(app: AppHandle, config: &BackupConfig) -> Result<ConfigWatcher, String> {
+    let settings = config.watch.clone().ok_or("Watch is not configured")?;
+    let (tx, rx) = mpsc::channel();
+
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    watcher
+        .watch(Path::new(&config.source_path), RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch source folder: {}", e))?;
+
+    let config_id = config.id.clone();
+    let destination = PathBuf::from(&config.destination_path);
+    let mut debouncer = Debouncer::new(&settings);
+
+    std::thread::spawn(move || loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if is_relevant_change(&event, &destination) {
+                    debouncer.record_change(Instant::now());
+                }
+            }
+            Ok(Err(e)) => log::warn!("⚠️  File watcher error for config {}: {}", config_id, e),
+            Err(RecvTimeoutError::Timeout) => {}
+            // Watcher dropped: config deleted, disabled or changed
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if debouncer.is_due(Instant::now()) && !is_backup_running(&app, &config_id) {
+            debouncer.mark_run(Instant::now());
+            run_watch_backup(&app, &config_id);
+        }
+    });
+
+    Ok(ConfigWatcher {
+        _watcher: watcher,
+        source_path: config.source_path.clone(),
+        settings,
+    })
+}
+
+/// Whether a backup for `config_id` is in progress (it owns a cancel flag)
+fn is_backup_running(app: &AppHandle, config_id: &str) -> bool {
+    app.state::<AppState>()
+        .cancel_flags
+        .lock()
+        .map(|flags| flags.contains_key(config_id))
+        .unwrap_or(true)
+}
+
+/// Backup type of a change-triggered run
+///
+/// Incremental, unless the config can't read its manifest unattended (encrypted
+/// with recipient keys and no password source): then the config's own type
+/// (None), as incrementals are refused for it.
+fn watch_backup_type(config: &BackupConfig) -> Option<BackupType> {
+    if config.mode == BackupMode::Encrypted && config.password_source.is_none() {
+        None
+    } else {
+        Some(BackupType::Incremental)
+    }
+}
+
+/// Run a backup for a detected change (blocks the watcher thread)
+fn run_watch_backup(app: &AppHandle, config_id: &str) {
+    let backup_type = {
+        let state = app.state::<AppState>();
+        let configs = state.configs.lock().map(|configs| configs.iter().find(|c| c.id == config_id).cloned());
+        configs.ok().flatten().and_then(|config| watch_backup_type(&config))
+    };
+    log::info!("👁️  Changes detected - starting backup for config {}", config_id);
+    if backup_type.is_none() {
+        log::info!("ℹ️  No password source to read the encrypted manifest unattended; using the config's backup type");
+    }
+
+    let result = tauri::async_runtime::block_on(commands::run_backup_with_type(
+        app.clone(),
+        app.state::<AppState>(),
+        config_id.to_string(),
+        None,
+        backup_type,
+        JobTrigger::Watch,
+    ));
+
+    match result {
+        Ok(result) if result.success => log::info!("✅ Change-triggered backup completed: {}", result.message),
+        Ok(result) => log::error!("❌ Change-triggered backup failed: {}", result.message),
+        Err(e) => log::error!("❌ Change-triggered backup failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(quiet: u64, interval: u64) -> WatchConfig {
+        WatchConfig {
+            enabled: true,
+            quiet_period_secs: quiet,
+            min_interval_secs: interval,
+        }
+    }
+
+    #[test]
+    fn test_debouncer_waits_for_quiet_period() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(&settings(30, 0));
+        assert!(!debouncer.is_due(start), "Nothing changed yet");
+
+        debouncer.record_change(start);
+        assert!(!debouncer.is_due(start + Duration::from_secs(29)));
+
+        // A new change restarts the quiet period
+        debouncer.record_change(start + Duration::from_secs(20));
+        assert!(!debouncer.is_due(start + Duration::from_secs(31)));
+        assert!(debouncer.is_due(start + Duration::from_secs(50)));
+    }
+
+    #[test]
+    fn test_debouncer_respects_min_interval() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(&settings(5, 300));
+
+        debouncer.record_change(start);
+        assert!(debouncer.is_due(start + Duration::from_secs(5)));
+        debouncer.mark_run(start + Duration::from_secs(5));
+        assert!(!debouncer.is_due(start + Duration::from_secs(500)), "Changes were covered by the run");
+
+        debouncer.record_change(start + Duration::from_secs(10));
+        assert!(!debouncer.is_due(start + Duration::from_secs(100)), "Too soon after the last run");
+        assert!(debouncer.is_due(start + Duration::from_secs(305)));
+    }
+
+    #[test]
+    fn test_watch_backup_type() {
+        let config = |mode: &str, password_source: serde_json::Value| -> BackupConfig {
+            serde_json::from_value(serde_json::json!({
+                "id": "watched",
+                "name": "Watched",
+                "source_path": "/data/source",
+                "destination_path": "/data/backups",
+                "schedule": null,
+                "enabled": true,
+                "created_at": 0,
+                "updated_at": 0,
+                "mode": mode,
+                "password_source": password_source
+            }))
+            .unwrap()
+        };
+        let keyring = serde_json::json!({ "provider": "keyring", "service": "InLocker", "account": "watched" });
+
+        assert_eq!(watch_backup_type(&config("compressed", serde_json::Value::Null)), Some(BackupType::Incremental));
+        assert_eq!(watch_backup_type(&config("encrypted", keyring)), Some(BackupType::Incremental));
+        // Recipient keys only: the manifest can't be read unattended
+        assert_eq!(watch_backup_type(&config("encrypted", serde_json::Value::Null)), None);
+    }
+
+    #[test]
+    fn test_destination_changes_ignored() {
+        let destination = Path::new("/data/source/backups");
+        let event = |path: &str| Event::new(EventKind::Any).add_path(PathBuf::from(path));
+
+        assert!(is_relevant_change(&event("/data/source/notes.txt"), destination));
+        assert!(!is_relevant_change(&event("/data/source/backups/Bkp_InLocker_1.tar.zst"), destination));
+        let read = Event::new(EventKind::Access(notify::event::AccessKind::Any))
+            .add_path(PathBuf::from("/data/source/notes.txt"));
+        assert!(!is_relevant_change(&read, destination));
+    }
+}
@@ -1,6 +1,15 @@
-use inlocker_lib::backup::{compress_folder, restore_backup};
-use inlocker_lib::types::{BackupMode, BackupType};
+use inlocker_lib::backup::{
+    build_manifest, compress_folder, read_archive_info, read_archive_manifest, restore_backup, restore_backup_with_selection,
+    scan_all_files, verify_archive,
+};
+use inlocker_lib::browse::{list_archive_entries, EntryKind, EntryQuery};
+use inlocker_lib::catalog::import_destination;
+use inlocker_lib::chain::restore_point_in_time;
+use inlocker_lib::history;
+use inlocker_lib::selection::RestoreSelection;
+use inlocker_lib::types::{BackupConfig, BackupMode, BackupType, HistoryQuery, JobTrigger};
 use std::fs;
+use std::path::{Path, PathBuf};
 
 /// Integration test: Complete backup and restore cycle using REAL functions
 #[test]
@@ -139,6 +148,490 @@ fn test_backup_restore_cycle() {
     let _ = fs::remove_dir_all(&restore_dir);
 }
 
+/// Integration test: verify reads an archive end to end without extracting it
+#[test]
+fn test_verify_archive_detects_corruption() {
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_verify_source");
+    let dest_dir = temp_dir.join("integration_verify_dest");
+    let _ = fs::remove_dir_all(&source_dir);
+    let _ = fs::remove_dir_all(&dest_dir);
+    fs::create_dir_all(source_dir.join("subdir")).unwrap();
+    fs::create_dir_all(&dest_dir).unwrap();
+
+    fs::write(source_dir.join("file1.txt"), b"Verify me").unwrap();
+    fs::write(source_dir.join("subdir/file2.txt"), vec![7u8; 64 * 1024]).unwrap();
+
+    let job = compress_folder(
+        "verify-config-id",
+        "Verify Config",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some("Verify-Password-123!"),
+        None,
+    )
+    .expect("Backup should succeed");
+    let backup_path = std::path::PathBuf::from(job.backup_path.unwrap());
+
+    // Intact archive: every entry is read and authenticated
+    let result = verify_archive(&backup_path, job.checksum.as_deref(), Some("Verify-Password-123!"))
+        .expect("Intact archive should verify");
+    assert_eq!(result.files_count, 2);
+    assert_eq!(result.total_size, 9 + 64 * 1024);
+    assert_eq!(Some(result.checksum), job.checksum);
+
+    // Wrong password and missing password fail
+    assert!(verify_archive(&backup_path, None, Some("wrong-password")).is_err());
+    assert!(verify_archive(&backup_path, None, None).is_err());
+
+    // Flip a byte near the end of the payload: authentication must fail
+    let mut data = fs::read(&backup_path).unwrap();
+    let index = data.len() - 20;
+    data[index] ^= 0xFF;
+    fs::write(&backup_path, &data).unwrap();
+    assert!(verify_archive(&backup_path, None, Some("Verify-Password-123!")).is_err(), "Tampered archive must not verify");
+
+    let _ = fs::remove_dir_all(&source_dir);
+    let _ = fs::remove_dir_all(&dest_dir);
+}
+
+/// Integration test: point-in-time restore applies the full backup and every incremental up to that time
+#[test]
+fn test_point_in_time_restore_follows_incremental_chain() {
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_chain_source");
+    let dest_dir = temp_dir.join("integration_chain_dest");
+    let restore_dir = temp_dir.join("integration_chain_restore");
+    for dir in [&source_dir, &dest_dir, &restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(source_dir.join("subdir")).unwrap();
+    fs::create_dir_all(&dest_dir).unwrap();
+
+    let config: BackupConfig = serde_json::from_value(serde_json::json!({
+        "id": "chain-config-id",
+        "name": "Chain Config",
+        "source_path": source_dir.to_string_lossy(),
+        "destination_path": dest_dir.to_string_lossy(),
+        "schedule": null,
+        "enabled": true,
+        "created_at": 0,
+        "updated_at": 0,
+        "last_backup_at": null,
+        "last_backup_checksum": null
+    }))
+    .unwrap();
+
+    let full_backup = || {
+        compress_folder(
+            &config.id,
+            &config.name,
+            &source_dir,
+            &dest_dir,
+            &BackupType::Full,
+            &BackupMode::Compressed,
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("Backup should succeed")
+    };
+
+    fs::write(source_dir.join("file1.txt"), b"version 1").unwrap();
+    fs::write(source_dir.join("file2.txt"), b"deleted later").unwrap();
+    fs::write(source_dir.join("subdir/file3.txt"), b"unchanged").unwrap();
+    let full = full_backup();
+
+    // Manifest of the full backup, then changes: modify, delete, add
+    // (archive names have one-second resolution)
+    let (files, _) = scan_all_files(&source_dir).unwrap();
+    let manifest = build_manifest(&config.id, &files, &source_dir).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    fs::write(source_dir.join("file1.txt"), b"version 2").unwrap();
+    fs::remove_file(source_dir.join("file2.txt")).unwrap();
+    fs::write(source_dir.join("file4.txt"), b"new file").unwrap();
+    let incremental = compress_folder(
+        &config.id,
+        &config.name,
+        &source_dir,
+        &dest_dir,
+        &BackupType::Incremental,
+        &BackupMode::Compressed,
+        Some(&manifest),
+        None,
+        None,
+        None,
+    )
+    .expect("Incremental backup should succeed");
+    assert_eq!(incremental.files_count, Some(2));
+
+    // The incremental records its parent and the deletion
+    let full_path = PathBuf::from(full.backup_path.as_ref().unwrap());
+    let incremental_path = PathBuf::from(incremental.backup_path.as_ref().unwrap());
+    let info = read_archive_info(&incremental_path, None).unwrap().expect("Archive info should be embedded");
+    assert_eq!(info.backup_type, BackupType::Incremental);
+    assert_eq!(info.parent.as_deref(), full_path.file_name().unwrap().to_str().unwrap().strip_suffix(".tar.zst"));
+    assert_eq!(info.deleted, vec!["file2.txt".to_string()]);
+    assert!(read_archive_info(&full_path, None).unwrap().unwrap().parent.is_none());
+
+    // A later full backup must not affect a restore of an earlier time
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    fs::write(source_dir.join("file1.txt"), b"version 3").unwrap();
+    full_backup();
+
+    let read = |path: &str| fs::read_to_string(restore_dir.join(path)).ok();
+
+    let result = restore_point_in_time(&config, incremental.completed_at.unwrap(), &restore_dir, None, None, None)
+        .expect("Point-in-time restore should succeed");
+    assert_eq!(result.archives.len(), 2, "Full + incremental");
+    assert_eq!(result.deleted_count, 1);
+    assert_eq!(read("file1.txt").as_deref(), Some("version 2"));
+    assert_eq!(read("file2.txt"), None, "Deleted file must not come back");
+    assert_eq!(read("subdir/file3.txt").as_deref(), Some("unchanged"));
+    assert_eq!(read("file4.txt").as_deref(), Some("new file"));
+    assert!(!restore_dir.join(".inlocker").exists(), "Archive metadata is not restored");
+
+    // Restoring the full backup's time ignores the incremental
+    let _ = fs::remove_dir_all(&restore_dir);
+    let result = restore_point_in_time(&config, full.completed_at.unwrap(), &restore_dir, None, None, None).unwrap();
+    assert_eq!(result.archives.len(), 1);
+    assert_eq!(read("file1.txt").as_deref(), Some("version 1"));
+    assert_eq!(read("file2.txt").as_deref(), Some("deleted later"));
+
+    // Nothing to restore before the first backup
+    assert!(restore_point_in_time(&config, full.started_at - 60, Path::new(&restore_dir), None, None, None).is_err());
+
+    for dir in [&source_dir, &dest_dir, &restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Integration test: archives and Copy folders carry their own file list, checksums and config
+#[test]
+fn test_archives_embed_manifest() {
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_manifest_source");
+    let dest_dir = temp_dir.join("integration_manifest_dest");
+    for dir in [&source_dir, &dest_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(source_dir.join("subdir")).unwrap();
+    fs::create_dir_all(&dest_dir).unwrap();
+
+    let contents: [(&str, &[u8]); 2] = [("file1.txt", b"Manifest me"), ("subdir/file2.bin", &[42u8; 100_000])];
+    for (path, data) in contents {
+        fs::write(source_dir.join(path), data).unwrap();
+    }
+
+    let password = "Manifest-Password-123!";
+    for (index, mode) in [BackupMode::Encrypted, BackupMode::Copy].iter().enumerate() {
+        if index > 0 {
+            // Archive names have one-second resolution
+            std::thread::sleep(std::time::Duration::from_millis(1100));
+        }
+        let job = compress_folder(
+            "manifest-config-id",
+            "Manifest Config",
+            &source_dir,
+            &dest_dir,
+            &BackupType::Full,
+            mode,
+            None,
+            None,
+            Some(password),
+            None,
+        )
+        .expect("Backup should succeed");
+        let backup_path = PathBuf::from(job.backup_path.unwrap());
+
+        let info = read_archive_info(&backup_path, Some(password)).unwrap().expect("Archive info should be embedded");
+        assert_eq!(info.config_name, "Manifest Config");
+        assert_eq!(info.source_path, source_dir.to_string_lossy());
+        assert!(!info.app_version.is_empty());
+
+        let manifest = read_archive_manifest(&backup_path, Some(password))
+            .unwrap()
+            .expect("Manifest should be embedded");
+        assert_eq!(manifest.archive_id, info.archive_id);
+        assert_eq!(manifest.files.len(), 2, "Metadata files are not listed: {:?}", mode);
+        for (path, data) in contents {
+            let file = manifest.files.iter().find(|f| f.path == path).expect("File should be listed");
+            assert_eq!(file.size, data.len() as u64);
+            assert_eq!(file.checksum, calculate_sha256(data), "Checksum of {} ({:?})", path, mode);
+        }
+
+        if *mode == BackupMode::Encrypted {
+            assert!(read_archive_manifest(&backup_path, Some("wrong-password")).is_err());
+            let verified = verify_archive(&backup_path, None, Some(password)).unwrap();
+            assert_eq!(verified.files_count, 2);
+        }
+    }
+
+    for dir in [&source_dir, &dest_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Integration test: a destination written elsewhere is adopted into an empty catalog
+#[test]
+fn test_import_destination_rebuilds_configs_and_history() {
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_import_source");
+    let dest_dir = temp_dir.join("integration_import_dest");
+    let data_dir = temp_dir.join("integration_import_data");
+    let restore_dir = temp_dir.join("integration_import_restore");
+    for dir in [&source_dir, &dest_dir, &data_dir, &restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(source_dir.join("subdir")).unwrap();
+    fs::create_dir_all(&dest_dir).unwrap();
+    fs::write(source_dir.join("file1.txt"), b"imported").unwrap();
+    fs::write(source_dir.join("subdir/file2.txt"), b"nested").unwrap();
+
+    // "Another machine": one encrypted and one compressed config sharing the destination
+    let password = "Import-Password-123!";
+    let backup = |id: &str, name: &str, mode: &BackupMode| {
+        compress_folder(id, name, &source_dir, &dest_dir, &BackupType::Full, mode, None, None, Some(password), None)
+            .expect("Backup should succeed")
+    };
+    backup("secret-config-id", "Secret Docs", &BackupMode::Encrypted);
+    backup("plain-config-id", "Plain Docs", &BackupMode::Compressed);
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    backup("secret-config-id", "Secret Docs", &BackupMode::Encrypted);
+
+    // Without the password the encrypted archives are imported from their header and name
+    let mut configs: Vec<BackupConfig> = Vec::new();
+    let report = import_destination(&dest_dir, &mut configs, &data_dir, None, false).expect("Import should succeed");
+    assert_eq!(configs.len(), 2);
+    assert_eq!(report.locked.len(), 2);
+    assert!(report.errors.is_empty());
+    let secret = configs.iter().find(|c| c.id == "secret-config-id").expect("Id comes from the cleartext header");
+    assert_eq!(secret.mode, BackupMode::Encrypted);
+    assert_eq!(secret.name, "Secret-Docs", "Name as written in the file name");
+    assert_eq!(secret.source_path, "", "Source folder is encrypted");
+    let plain = configs.iter().find(|c| c.id == "plain-config-id").unwrap();
+    assert_eq!(plain.name, "Plain Docs");
+    assert_eq!(plain.source_path, source_dir.to_string_lossy());
+    assert_eq!(plain.destination_path, dest_dir.to_string_lossy());
+
+    // With the password: same configs, now complete; archives aren't recorded twice
+    let report = import_destination(&dest_dir, &mut configs, &data_dir, Some(password), false).unwrap();
+    assert_eq!(configs.len(), 2);
+    assert!(report.locked.is_empty());
+    assert!(report.configs.iter().all(|c| !c.created && c.history_added == 0));
+    assert!(report.configs.iter().flat_map(|c| &c.archives).all(|a| a.indexed));
+    let secret = configs.iter().find(|c| c.id == "secret-config-id").unwrap().clone();
+    assert_eq!(secret.name, "Secret Docs");
+    assert_eq!(secret.source_path, source_dir.to_string_lossy());
+
+    let page = history::query(&data_dir, &HistoryQuery { config_id: Some(secret.id.clone()), ..Default::default() }).unwrap();
+    assert_eq!(page.total, 2);
+    assert!(page.records.iter().all(|r| r.trigger == JobTrigger::Import));
+
+    // Imported configs restore like local ones
+    let result = restore_point_in_time(&secret, chrono::Utc::now().timestamp(), &restore_dir, Some(password), None, None)
+        .expect("Restore of an imported config should succeed");
+    assert_eq!(result.archives.len(), 1);
+    assert_eq!(fs::read_to_string(restore_dir.join("subdir/file2.txt")).unwrap(), "nested");
+
+    // A copy of the destination (cloned drive) is reported, not adopted, until confirmed
+    let clone_dir = temp_dir.join("integration_import_clone");
+    let _ = fs::remove_dir_all(&clone_dir);
+    fs::create_dir_all(&clone_dir).unwrap();
+    for entry in fs::read_dir(&dest_dir).unwrap() {
+        let entry = entry.unwrap();
+        fs::copy(entry.path(), clone_dir.join(entry.file_name())).unwrap();
+    }
+    let report = import_destination(&clone_dir, &mut configs, &data_dir, Some(password), false).unwrap();
+    assert_eq!(report.moved.len(), 2);
+    assert!(report.moved.iter().all(|m| !m.repointed && m.from == dest_dir.to_string_lossy()));
+    assert!(report.configs.is_empty());
+    assert!(configs.iter().all(|c| c.destination_path == dest_dir.to_string_lossy()));
+
+    let report = import_destination(&clone_dir, &mut configs, &data_dir, Some(password), true).unwrap();
+    assert!(report.moved.iter().all(|m| m.repointed));
+    assert!(configs.iter().all(|c| c.destination_path == clone_dir.to_string_lossy()));
+    assert!(report.configs.iter().all(|c| c.history_added == 0), "Same archives, already recorded");
+
+    // An empty folder is not a destination
+    assert!(import_destination(&source_dir.join("subdir"), &mut configs, &data_dir, None, false).is_err());
+
+    for dir in [&source_dir, &dest_dir, &data_dir, &restore_dir, &clone_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Integration test: archive contents are listed as a tree without extracting anything
+#[test]
+fn test_list_archive_entries() {
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_browse_source");
+    let dest_dir = temp_dir.join("integration_browse_dest");
+    for dir in [&source_dir, &dest_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(source_dir.join("photos/2024")).unwrap();
+    fs::create_dir_all(&dest_dir).unwrap();
+    fs::write(source_dir.join("notes.txt"), b"notes").unwrap();
+    for i in 0..25 {
+        fs::write(source_dir.join(format!("photos/2024/img_{:02}.jpg", i)), vec![i as u8; 100]).unwrap();
+    }
+
+    let password = "Browse-Password-123!";
+    for (index, mode) in [BackupMode::Encrypted, BackupMode::Copy].iter().enumerate() {
+        if index > 0 {
+            // Archive names have one-second resolution
+            std::thread::sleep(std::time::Duration::from_millis(1100));
+        }
+        let job = compress_folder(
+            "browse-config-id",
+            "Browse Config",
+            &source_dir,
+            &dest_dir,
+            &BackupType::Full,
+            mode,
+            None,
+            None,
+            Some(password),
+            None,
+        )
+        .expect("Backup should succeed");
+        let backup_path = PathBuf::from(job.backup_path.unwrap());
+        let list = |query: EntryQuery| list_archive_entries(&backup_path, Some(password), &query).unwrap();
+
+        // Root: the photos directory (aggregated) then the file; no InLocker metadata
+        let root = list(EntryQuery::default());
+        assert_eq!(root.total, 2, "{:?}", mode);
+        assert_eq!((root.files_count, root.total_size), (26, 5 + 2500));
+        assert_eq!(root.entries[0].name, "photos");
+        assert_eq!(root.entries[0].kind, EntryKind::Directory);
+        assert_eq!(root.entries[0].files_count, 25);
+        assert_eq!(root.entries[1].path, "notes.txt");
+        assert_eq!(root.entries[1].size, 5);
+        assert!(root.entries[1].modified_at > 0);
+
+        // Paging through a directory
+        let query = EntryQuery { prefix: "photos/2024".to_string(), offset: 20, limit: 10, ..Default::default() };
+        let page = list(query);
+        assert_eq!(page.total, 25);
+        assert_eq!(page.entries.len(), 5);
+        assert_eq!(page.entries[0].path, "photos/2024/img_20.jpg");
+        if cfg!(unix) {
+            assert_ne!(page.entries[0].mode, 0);
+        }
+
+        // Recursive listing of everything under a prefix
+        let all = list(EntryQuery { prefix: "photos".to_string(), recursive: true, limit: 100, ..Default::default() });
+        assert_eq!(all.entries.len(), 25);
+        assert!(all.entries.iter().all(|e| e.kind == EntryKind::File && e.size == 100));
+    }
+
+    // Encrypted archives can't be listed without the password
+    let encrypted = fs::read_dir(&dest_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with(".enc"))
+        .unwrap();
+    assert!(list_archive_entries(&encrypted, None, &EntryQuery::default()).is_err());
+
+    for dir in [&source_dir, &dest_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Integration test: restore one folder (or some files) instead of the whole backup
+#[test]
+fn test_selective_restore() {
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_selective_source");
+    let dest_dir = temp_dir.join("integration_selective_dest");
+    let restore_dir = temp_dir.join("integration_selective_restore");
+    for dir in [&source_dir, &dest_dir, &restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(source_dir.join("projects/app/node_modules/dep")).unwrap();
+    fs::create_dir_all(source_dir.join("photos")).unwrap();
+    fs::create_dir_all(&dest_dir).unwrap();
+    fs::write(source_dir.join("projects/app/main.rs"), b"fn main() {}").unwrap();
+    fs::write(source_dir.join("projects/app/debug.log"), b"log").unwrap();
+    fs::write(source_dir.join("projects/app/node_modules/dep/index.js"), b"dep").unwrap();
+    fs::write(source_dir.join("photos/a.jpg"), b"jpeg").unwrap();
+    fs::write(source_dir.join("notes.txt"), b"notes").unwrap();
+
+    for (index, mode) in [BackupMode::Compressed, BackupMode::Copy].iter().enumerate() {
+        if index > 0 {
+            // Archive names have one-second resolution
+            std::thread::sleep(std::time::Duration::from_millis(1100));
+        }
+        let job = compress_folder(
+            "selective-config-id",
+            "Selective Config",
+            &source_dir,
+            &dest_dir,
+            &BackupType::Full,
+            mode,
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("Backup should succeed");
+        let backup_path = PathBuf::from(job.backup_path.unwrap());
+        let restore = |selection: RestoreSelection| {
+            let _ = fs::remove_dir_all(&restore_dir);
+            restore_backup_with_selection(&backup_path, &restore_dir, None, None, None, None, &selection)
+        };
+        let restored = |path: &str| restore_dir.join(path).exists();
+
+        // One folder, without its dependencies and logs
+        let result = restore(RestoreSelection {
+            paths: vec!["projects/app".to_string()],
+            exclude: vec!["**/node_modules".to_string(), "**/*.log".to_string()],
+            ..Default::default()
+        })
+        .expect("Selective restore should succeed");
+        assert_eq!(result.files_count, 1, "{:?}", mode);
+        assert!(restored("projects/app/main.rs"));
+        assert!(!restored("projects/app/debug.log"));
+        assert!(!restored("projects/app/node_modules"));
+        assert!(!restored("notes.txt"));
+        assert!(!restored(".inlocker"));
+
+        // Stripped and renamed: the folder's contents land in the destination root
+        let result = restore(RestoreSelection {
+            paths: vec!["photos".to_string(), "notes.txt".to_string()],
+            rename: [("notes.txt".to_string(), "photos/notes (restored).txt".to_string())].into_iter().collect(),
+            strip_components: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(result.files_count, 2);
+        assert_eq!(fs::read_to_string(restore_dir.join("a.jpg")).unwrap(), "jpeg");
+        assert_eq!(fs::read_to_string(restore_dir.join("notes (restored).txt")).unwrap(), "notes");
+
+        // Nothing selected, or a path leaving the destination
+        assert!(restore(RestoreSelection { include: vec!["*.pdf".to_string()], ..Default::default() }).is_err());
+        let escape = RestoreSelection {
+            rename: [("notes.txt".to_string(), "../notes.txt".to_string())].into_iter().collect(),
+            ..Default::default()
+        };
+        assert!(restore(escape).is_err());
+
+        // No selection restores everything
+        let result = restore(RestoreSelection::default()).unwrap();
+        assert_eq!(result.files_count, 5);
+    }
+
+    for dir in [&source_dir, &dest_dir, &restore_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
 fn calculate_sha256(data: &[u8]) -> String {
     use ring::digest::{Context, SHA256};
     let mut context = Context::new(&SHA256);
@@ -146,3 +639,137 @@ fn calculate_sha256(data: &[u8]) -> String {
     let digest = context.finish();
     hex::encode(digest.as_ref())
 }
+
+/// Integration test: pruning only touches archives recording the config's own id
+#[test]
+fn test_prune_leaves_same_named_configs_alone() {
+    use inlocker_lib::retention::{archive_config_name, prune};
+    use inlocker_lib::types::RetentionPolicy;
+
+    let temp_dir = std::env::temp_dir();
+    let source_dir = temp_dir.join("integration_prune_source");
+    let dest_dir = temp_dir.join("integration_prune_dest");
+    for dir in [&source_dir, &dest_dir] {
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(source_dir.join("file.txt"), b"Shared destination").unwrap();
+
+    let config = |id: &str| -> BackupConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Shared Docs",
+            "source_path": source_dir.to_string_lossy(),
+            "destination_path": dest_dir.to_string_lossy(),
+            "schedule": null,
+            "enabled": true,
+            "created_at": 0,
+            "updated_at": 0
+        }))
+        .unwrap()
+    };
+    let backup = |id: &str| {
+        // Archive names have one-second resolution
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let job = compress_folder(
+            id, "Shared Docs", &source_dir, &dest_dir,
+            &BackupType::Full, &BackupMode::Compressed, None, None, None, None,
+        )
+        .expect("Backup should succeed");
+        PathBuf::from(job.backup_path.unwrap())
+    };
+    let first_a = backup("config-a");
+    let only_b = backup("config-b");
+    let latest_a = backup("config-a");
+
+    // Same file name prefix, but B's archive records B's id
+    let policy = RetentionPolicy { keep_last: Some(1), ..Default::default() };
+    let report = prune(&config("config-a"), &policy, false).unwrap();
+    assert_eq!(report.removed.len(), 1);
+    assert!(!first_a.exists());
+    assert!(latest_a.exists());
+    assert!(only_b.exists(), "Another config's archive must never be pruned");
+
+    // An archive recording no id can't be attributed: refuse rather than guess
+    let filename = only_b.file_name().unwrap().to_string_lossy().to_string();
+    let legacy = dest_dir.join(format!("Bkp_InLocker_{}_full_20200101_000000.tar.zst", archive_config_name(&filename).unwrap()));
+    fs::write(&legacy, b"written before archives recorded their config").unwrap();
+    assert!(prune(&config("config-a"), &policy, false).is_err());
+    assert!(legacy.exists() && only_b.exists() && latest_a.exists());
+
+    for dir in [&source_dir, &dest_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Integration test: same-named configs sharing a destination keep separate chains
+#[test]
+fn test_chains_of_same_named_configs_stay_apart() {
+    use inlocker_lib::retention::archive_id;
+    use inlocker_lib::types::BackupManifest;
+
+    let temp_dir = std::env::temp_dir();
+    let dest_dir = temp_dir.join("integration_shared_chain_dest");
+    let restore_dir = temp_dir.join("integration_shared_chain_restore");
+    let source = |id: &str| temp_dir.join(format!("integration_shared_chain_{}", id));
+    for dir in [&dest_dir, &restore_dir, &source("a"), &source("b")] {
+        let _ = fs::remove_dir_all(dir);
+    }
+    fs::create_dir_all(&dest_dir).unwrap();
+    for id in ["a", "b"] {
+        fs::create_dir_all(source(id)).unwrap();
+        fs::write(source(id).join("owner.txt"), format!("config {}", id)).unwrap();
+    }
+
+    let config = |id: &str| -> BackupConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Shared Docs",
+            "source_path": source(id).to_string_lossy(),
+            "destination_path": dest_dir.to_string_lossy(),
+            "schedule": null,
+            "enabled": true,
+            "created_at": 0,
+            "updated_at": 0
+        }))
+        .unwrap()
+    };
+    let backup = |id: &str, backup_type: &BackupType, manifest: Option<&BackupManifest>| {
+        // Archive names have one-second resolution
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let job = compress_folder(
+            id, "Shared Docs", &source(id), &dest_dir,
+            backup_type, &BackupMode::Compressed, manifest, None, None, None,
+        )
+        .expect("Backup should succeed");
+        PathBuf::from(job.backup_path.unwrap())
+    };
+    let full_a = backup("a", &BackupType::Full, None);
+    let (files, _) = scan_all_files(&source("a")).unwrap();
+    let manifest_a = build_manifest("a", &files, &source("a")).unwrap();
+    backup("b", &BackupType::Full, None);
+    fs::write(source("a").join("added.txt"), "only in a").unwrap();
+    let incremental_a = backup("a", &BackupType::Incremental, Some(&manifest_a));
+    let full_b = backup("b", &BackupType::Full, None);
+
+    // The incremental builds on A's full, not on B's newer archive
+    let info = read_archive_info(&incremental_a, None).unwrap().unwrap();
+    assert_eq!(info.parent.as_deref(), Some(archive_id(&full_a.file_name().unwrap().to_string_lossy())));
+
+    // Restoring A now applies A's chain only, although B's archive is newer
+    let now = chrono::Utc::now().timestamp();
+    let result = restore_point_in_time(&config("a"), now, &restore_dir.join("a"), None, None, None).unwrap();
+    assert_eq!(result.archives.len(), 2);
+    assert_eq!(result.target, incremental_a.file_name().unwrap().to_string_lossy());
+    assert_eq!(fs::read_to_string(restore_dir.join("a/owner.txt")).unwrap(), "config a");
+    assert!(restore_dir.join("a/added.txt").exists());
+
+    let result = restore_point_in_time(&config("b"), now, &restore_dir.join("b"), None, None, None).unwrap();
+    assert_eq!(result.target, full_b.file_name().unwrap().to_string_lossy());
+    assert_eq!(fs::read_to_string(restore_dir.join("b/owner.txt")).unwrap(), "config b");
+    assert!(!restore_dir.join("b/added.txt").exists());
+
+    for dir in [&dest_dir, &restore_dir, &source("a"), &source("b")] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
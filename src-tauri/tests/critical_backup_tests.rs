@@ -4,8 +4,9 @@
 /// reliably in production. These tests cover edge cases, incremental backups,
 /// data integrity, and error handling scenarios.
 
-use inlocker_lib::backup::{build_manifest, compress_folder, restore_backup, scan_all_files};
-use inlocker_lib::types::{BackupManifest, BackupMode, BackupType};
+use inlocker_lib::backup::{build_manifest, compress_folder, read_archive_info, restore_backup, scan_all_files};
+use inlocker_lib::chain::restore_point_in_time;
+use inlocker_lib::types::{BackupConfig, BackupManifest, BackupMode, BackupType, RenamedFile};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -51,8 +52,9 @@ fn cleanup_test_dirs(dirs: &[&Path]) {
 fn test_incremental_backup_only_changed_files() {
     let (source_dir, dest_dir, restore_dir) = setup_test_dirs("incremental_test");
 
-    // STEP 1: Create initial files
-    fs::write(source_dir.join("unchanged.txt"), b"This file stays the same").unwrap();
+    // STEP 1: Create initial files (large enough that archive metadata doesn't dominate the sizes)
+    let unchanged: String = (0..200).map(|i| format!("Line {} of a file that stays the same\n", i)).collect();
+    fs::write(source_dir.join("unchanged.txt"), unchanged).unwrap();
     fs::write(source_dir.join("will_change.txt"), b"Original content v1").unwrap();
     fs::write(source_dir.join("will_delete.txt"), b"This will be deleted").unwrap();
 
@@ -455,9 +457,10 @@ fn test_long_filenames() {
 fn test_backup_idempotency() {
     let (source_dir, dest_dir, _) = setup_test_dirs("idempotency_test");
 
-    // Create test files
-    fs::write(source_dir.join("file1.txt"), b"unchanging content 1").unwrap();
-    fs::write(source_dir.join("file2.txt"), b"unchanging content 2").unwrap();
+    // Create test files (large enough that archive metadata doesn't dominate the sizes)
+    let content = |n: u32| -> String { (0..200).map(|i| format!("unchanging content {} line {}\n", n, i)).collect() };
+    fs::write(source_dir.join("file1.txt"), content(1)).unwrap();
+    fs::write(source_dir.join("file2.txt"), content(2)).unwrap();
 
     // Backup #1
     let backup1 = compress_folder(
@@ -668,6 +671,78 @@ fn test_incremental_handles_deleted_files() {
     cleanup_test_dirs(&[&source_dir, &dest_dir]);
 }
 
+#[test]
+fn test_incremental_records_tombstones_and_renames() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("incremental_renames");
+    fs::create_dir_all(source_dir.join("docs")).unwrap();
+
+    let report = b"Quarterly report ".repeat(1000);
+    fs::write(source_dir.join("docs/report.txt"), &report).unwrap();
+    fs::write(source_dir.join("notes.txt"), b"Notes v1").unwrap();
+    fs::write(source_dir.join("old.txt"), b"Will be deleted").unwrap();
+    fs::create_dir_all(source_dir.join("drafts/2024")).unwrap();
+    fs::write(source_dir.join("drafts/2024/a.txt"), b"Draft A").unwrap();
+    fs::write(source_dir.join("drafts/b.txt"), b"Draft B").unwrap();
+
+    compress_folder(
+        "rename-test", "Rename Test", &source_dir, &dest_dir,
+        &BackupType::Full, &BackupMode::Compressed, None, None, None, None,
+    ).unwrap();
+    let (all_files, _) = scan_all_files(&source_dir).unwrap();
+    let manifest = build_manifest("rename-test", &all_files, &source_dir).unwrap();
+
+    // Move a file, move and edit another, delete one and a whole folder, add one
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    fs::remove_dir_all(source_dir.join("drafts")).unwrap();
+    fs::create_dir_all(source_dir.join("archive")).unwrap();
+    fs::rename(source_dir.join("docs/report.txt"), source_dir.join("archive/report-2024.txt")).unwrap();
+    fs::remove_file(source_dir.join("notes.txt")).unwrap();
+    fs::write(source_dir.join("archive/notes.txt"), b"Notes v2").unwrap();
+    fs::remove_file(source_dir.join("old.txt")).unwrap();
+    fs::write(source_dir.join("new.txt"), b"Brand new").unwrap();
+
+    let incremental = compress_folder(
+        "rename-test", "Rename Test", &source_dir, &dest_dir,
+        &BackupType::Incremental, &BackupMode::Compressed, Some(&manifest), None, None, None,
+    ).unwrap();
+
+    // CRITICAL: The moved file is recorded, not archived again; the edited one is archived
+    assert_eq!(incremental.files_count.unwrap(), 2, "Only archive/notes.txt and new.txt hold new content");
+    let info = read_archive_info(Path::new(incremental.backup_path.as_ref().unwrap()), None)
+        .unwrap()
+        .expect("Incremental should embed its archive info");
+    assert_eq!(
+        info.renamed,
+        vec![RenamedFile { from: "docs/report.txt".into(), to: "archive/report-2024.txt".into() }]
+    );
+    assert_eq!(info.deleted, vec!["drafts/2024/a.txt", "drafts/b.txt", "notes.txt", "old.txt"]);
+
+    // Restoring the chain reproduces the source exactly
+    let config: BackupConfig = serde_json::from_value(serde_json::json!({
+        "id": "rename-test",
+        "name": "Rename Test",
+        "source_path": source_dir.to_string_lossy(),
+        "destination_path": dest_dir.to_string_lossy(),
+        "schedule": null,
+        "enabled": true,
+        "created_at": 0,
+        "updated_at": 0
+    })).unwrap();
+    let result = restore_point_in_time(&config, incremental.completed_at.unwrap(), &restore_dir, None, None, None).unwrap();
+    assert_eq!(result.renamed_count, 1);
+    assert_eq!(result.deleted_count, 4);
+
+    assert_eq!(fs::read(restore_dir.join("archive/report-2024.txt")).unwrap(), report);
+    assert_eq!(fs::read_to_string(restore_dir.join("archive/notes.txt")).unwrap(), "Notes v2");
+    assert_eq!(fs::read_to_string(restore_dir.join("new.txt")).unwrap(), "Brand new");
+    for gone in ["docs/report.txt", "notes.txt", "old.txt", "drafts"] {
+        assert!(!restore_dir.join(gone).exists(), "{} must not come back", gone);
+    }
+    assert!(restore_dir.is_dir(), "Only folders inside the destination are pruned");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+}
+
 // ============================================================================
 // CRITICAL TEST 11: HARDLINK DEDUPLICATION
 // ============================================================================
@@ -939,3 +1014,512 @@ fn test_compressed_mode_with_zstd() {
 
     println!("\n✅ COMPRESSED MODE TEST PASSED - zstd compression verified");
 }
+
+#[test]
+fn test_encrypted_mode_streaming() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("encrypted_mode");
+
+    println!("🔐 Testing ENCRYPTED mode (zstd + chunked AES-256-GCM)...");
+
+    // Incompressible data larger than one encryption chunk (1 MB)
+    let data: Vec<u8> = (0..3_000_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    fs::write(source_dir.join("large.bin"), &data).unwrap();
+    fs::write(source_dir.join("small.txt"), "Encrypted hello").unwrap();
+
+    let password = "EncryptedMode123!";
+    let backup_job = compress_folder(
+        "encrypted-mode-test",
+        "Encrypted Mode Test",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some(password),
+        None,
+    ).unwrap();
+
+    let backup_path = PathBuf::from(backup_job.backup_path.unwrap());
+    assert!(backup_path.to_str().unwrap().ends_with(".tar.zst.enc"));
+
+    // No plaintext temp file may be left next to the archive
+    let leftovers: Vec<_> = fs::read_dir(&dest_dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path() != backup_path)
+        .collect();
+    assert!(leftovers.is_empty(), "Unexpected files in destination: {:?}", leftovers);
+
+    // Wrong password must fail
+    assert!(restore_backup(&backup_path, &restore_dir, None, Some("WrongPassword123!"), None, None).is_err());
+
+    // Correct password restores bit-perfect data
+    restore_backup(&backup_path, &restore_dir, backup_job.checksum, Some(password), None, None).unwrap();
+    assert_eq!(calculate_sha256(&fs::read(restore_dir.join("large.bin")).unwrap()), calculate_sha256(&data));
+    assert_eq!(fs::read_to_string(restore_dir.join("small.txt")).unwrap(), "Encrypted hello");
+
+    // Truncated archive (final chunk removed) must be rejected
+    let archive = fs::read(&backup_path).unwrap();
+    let truncated_path = dest_dir.join("truncated.tar.zst.enc");
+    fs::write(&truncated_path, &archive[..archive.len() - 100]).unwrap();
+    let truncated_restore = restore_dir.join("truncated");
+    let result = restore_backup(&truncated_path, &truncated_restore, None, Some(password), None, None);
+    assert!(result.is_err(), "Truncated encrypted archive must fail to restore");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ ENCRYPTED MODE TEST PASSED - streaming encryption verified");
+}
+
+#[test]
+fn test_encrypted_archive_format_detection() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("enc_format_detection");
+
+    println!("🔎 Testing archive format detection from file contents...");
+
+    fs::write(source_dir.join("doc.txt"), "format detection").unwrap();
+    let password = "FormatDetect123!";
+
+    let backup_job = compress_folder(
+        "format-detection-test",
+        "Format Detection Test",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some(password),
+        None,
+    ).unwrap();
+    let backup_path = PathBuf::from(backup_job.backup_path.unwrap());
+
+    // New archives start with the versioned container header
+    let archive = fs::read(&backup_path).unwrap();
+    assert_eq!(&archive[..8], b"INLOCKR\0", "Encrypted archive must start with magic bytes");
+    assert_eq!(u16::from_le_bytes([archive[8], archive[9]]), 3, "Format version must be 3");
+
+    // Renamed without the .enc extension: still detected as encrypted
+    let renamed = dest_dir.join("renamed_backup.bin");
+    fs::copy(&backup_path, &renamed).unwrap();
+    let result = restore_backup(&renamed, &restore_dir.join("no_password"), None, None, None, None);
+    assert!(result.unwrap_err().contains("no password provided"));
+    restore_backup(&renamed, &restore_dir.join("renamed"), None, Some(password), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("renamed/doc.txt")).unwrap(), "format detection");
+
+    // Unknown (future) format version is rejected with a clear error
+    let mut future = archive.clone();
+    future[8..10].copy_from_slice(&4u16.to_le_bytes());
+    let future_path = dest_dir.join("future.tar.zst.enc");
+    fs::write(&future_path, &future).unwrap();
+    let err = restore_backup(&future_path, &restore_dir.join("future"), None, Some(password), None, None).unwrap_err();
+    assert!(err.contains("Unsupported archive format version 4"), "Unexpected error: {}", err);
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ FORMAT DETECTION TEST PASSED");
+}
+
+#[test]
+fn test_legacy_encrypted_archive_still_restores() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("legacy_encrypted");
+
+    println!("📜 Testing restore of archives written in the original layout...");
+
+    // Build an archive exactly like older InLocker versions did:
+    // [u32 len][EncryptionMetadata JSON][single-shot AES-GCM ciphertext of tar.zst]
+    fs::write(source_dir.join("old.txt"), "written by an older InLocker").unwrap();
+    let mut tar_data = Vec::new();
+    {
+        let mut builder = tar::Builder::new(&mut tar_data);
+        builder.append_path_with_name(source_dir.join("old.txt"), "old.txt").unwrap();
+        builder.finish().unwrap();
+    }
+    let compressed = zstd::encode_all(&tar_data[..], 3).unwrap();
+
+    let password = "LegacyFormat123!";
+    let (ciphertext, metadata) = inlocker_lib::crypto::encrypt(&compressed, password).unwrap();
+    let metadata_json = serde_json::to_vec(&metadata).unwrap();
+
+    let mut legacy = (metadata_json.len() as u32).to_le_bytes().to_vec();
+    legacy.extend_from_slice(&metadata_json);
+    legacy.extend_from_slice(&ciphertext);
+
+    let legacy_path = dest_dir.join("Bkp_InLocker_Legacy_full_20240101_120000.tar.zst.enc");
+    fs::write(&legacy_path, &legacy).unwrap();
+
+    restore_backup(&legacy_path, &restore_dir, None, Some(password), None, None).unwrap();
+    assert_eq!(
+        fs::read_to_string(restore_dir.join("old.txt")).unwrap(),
+        "written by an older InLocker"
+    );
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ LEGACY FORMAT TEST PASSED - backward compatibility verified");
+}
+
+#[test]
+fn test_version_2_archive_still_restores() {
+    use inlocker_lib::container::ContainerHeader;
+    use inlocker_lib::crypto::{new_stream_metadata, StreamEncryptor};
+    use std::io::Write;
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("v2_encrypted");
+
+    println!("📜 Testing restore of version 2 archives (password-derived payload key)...");
+
+    fs::write(source_dir.join("v2.txt"), "no key slots").unwrap();
+    let mut tar_data = Vec::new();
+    {
+        let mut builder = tar::Builder::new(&mut tar_data);
+        builder.append_path_with_name(source_dir.join("v2.txt"), "v2.txt").unwrap();
+        builder.finish().unwrap();
+    }
+    let compressed = zstd::encode_all(&tar_data[..], 3).unwrap();
+
+    // Version 2 layout: [header][chunks], key derived from the password with the header params
+    let password = "VersionTwo123!";
+    let metadata = new_stream_metadata();
+    let mut header = ContainerHeader::encrypted(metadata.clone(), false, "v2-test", 1_700_000_000);
+    header.version = 2;
+    let header_bytes = header.to_bytes().unwrap();
+
+    let mut archive = header_bytes.clone();
+    let mut encryptor = StreamEncryptor::new(Vec::new(), password, &metadata, &header_bytes).unwrap();
+    encryptor.write_all(&compressed).unwrap();
+    archive.extend(encryptor.finish().unwrap());
+
+    let v2_path = dest_dir.join("Bkp_InLocker_V2_full_20250101_120000.tar.zst.enc");
+    fs::write(&v2_path, &archive).unwrap();
+
+    restore_backup(&v2_path, &restore_dir, None, Some(password), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("v2.txt")).unwrap(), "no key slots");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ VERSION 2 FORMAT TEST PASSED");
+}
+
+// ============================================================================
+// TEST: CHANGE PASSWORD (RE-KEY) WITHOUT RE-COMPRESSION
+// ============================================================================
+
+#[test]
+fn test_rekey_changes_password_only() {
+    use inlocker_lib::backup::{rekey_archive, rekey_destination};
+    use inlocker_lib::types::KdfStrength;
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("rekey");
+
+    println!("🔑 Testing password change without re-encrypting the payload...");
+
+    fs::write(source_dir.join("doc.txt"), "re-key me").unwrap();
+    let old_password = "OldPassword123!";
+    let new_password = "NewPassword456!";
+
+    let backup = |name: &str| {
+        let job = compress_folder(
+            "rekey-test",
+            name,
+            &source_dir,
+            &dest_dir,
+            &BackupType::Full,
+            &BackupMode::Encrypted,
+            None,
+            None,
+            Some(old_password),
+            None,
+        ).unwrap();
+        PathBuf::from(job.backup_path.unwrap())
+    };
+    let first = backup("Rekey One");
+    let second = backup("Rekey Two");
+
+    let original = fs::read(&first).unwrap();
+    let result = rekey_archive(&first, old_password, new_password, KdfStrength::Standard).unwrap();
+    let rekeyed = fs::read(&first).unwrap();
+
+    // Header and payload untouched; only the key slot area differs
+    let header_len = 18 + u32::from_le_bytes([original[14], original[15], original[16], original[17]]) as usize;
+    let slots_len = |a: &[u8]| {
+        u32::from_le_bytes([a[header_len], a[header_len + 1], a[header_len + 2], a[header_len + 3]]) as usize
+    };
+    assert_eq!(&original[..header_len], &rekeyed[..header_len], "Header must not change");
+    assert_eq!(
+        &original[header_len + 4 + slots_len(&original)..],
+        &rekeyed[header_len + 4 + slots_len(&rekeyed)..],
+        "Payload must be copied unchanged"
+    );
+    assert_eq!(result.checksum, calculate_sha256(&rekeyed));
+    assert_eq!(result.old_checksum, calculate_sha256(&original));
+
+    // Old password no longer opens it, the new one does
+    assert!(restore_backup(&first, &restore_dir.join("old"), None, Some(old_password), None, None).is_err());
+    restore_backup(&first, &restore_dir.join("new"), None, Some(new_password), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("new/doc.txt")).unwrap(), "re-key me");
+
+    // Wrong current password leaves the archive untouched
+    assert!(rekey_archive(&second, "WrongPassword789!", new_password, KdfStrength::Standard).is_err());
+    assert!(restore_backup(&second, &restore_dir.join("second_old"), None, Some(old_password), None, None).is_ok());
+
+    // Destination-wide re-key is all-or-nothing: `first` no longer opens with the old password
+    assert!(rekey_destination(&dest_dir, old_password, new_password, KdfStrength::Standard).is_err());
+    assert!(restore_backup(&second, &restore_dir.join("still_old"), None, Some(old_password), None, None).is_ok());
+
+    // Once every archive shares the same password, the whole destination re-keys
+    rekey_archive(&second, old_password, new_password, KdfStrength::Standard).unwrap();
+    let report = rekey_destination(&dest_dir, new_password, old_password, KdfStrength::Standard).unwrap();
+    assert_eq!(report.rekeyed.len(), 2);
+    assert!(report.skipped.is_empty());
+    restore_backup(&second, &restore_dir.join("back"), None, Some(old_password), None, None).unwrap();
+
+    // No temporary files left behind
+    let leftovers: Vec<_> = fs::read_dir(&dest_dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".rekey"))
+        .collect();
+    assert!(leftovers.is_empty(), "Temporary files must be cleaned up");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ RE-KEY TEST PASSED");
+}
+
+#[test]
+fn test_restore_with_recovery_key() {
+    use inlocker_lib::backup::{compress_folder_with_options, rekey_archive, BackupOptions};
+    use inlocker_lib::crypto::{generate_recovery_key, setup_recovery};
+    use inlocker_lib::types::KdfStrength;
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("recovery_key");
+
+    println!("🛟 Testing restore with the offline recovery key...");
+
+    fs::write(source_dir.join("doc.txt"), "recoverable").unwrap();
+    let password = "RecoveryPassword123!";
+    let recovery_key = generate_recovery_key();
+    let options = BackupOptions {
+        recovery: Some(setup_recovery(&recovery_key, password, KdfStrength::Standard, b"recovery-test", 0).unwrap()),
+        ..Default::default()
+    };
+
+    let job = compress_folder_with_options(
+        "recovery-test",
+        "Recovery Test",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some(password),
+        None,
+        &options,
+    ).unwrap();
+    let backup_path = PathBuf::from(job.backup_path.unwrap());
+
+    // Both secrets restore
+    restore_backup(&backup_path, &restore_dir.join("password"), None, Some(password), None, None).unwrap();
+    restore_backup(&backup_path, &restore_dir.join("recovery"), None, Some(&recovery_key), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("recovery/doc.txt")).unwrap(), "recoverable");
+    assert!(restore_backup(&backup_path, &restore_dir.join("wrong"), None, Some("WrongPassword456!"), None, None).is_err());
+
+    // Forgotten password: reset it with the recovery key, recovery key keeps working
+    rekey_archive(&backup_path, &recovery_key, "NewPassword789!", KdfStrength::Standard).unwrap();
+    restore_backup(&backup_path, &restore_dir.join("new"), None, Some("NewPassword789!"), None, None).unwrap();
+    restore_backup(&backup_path, &restore_dir.join("recovery_again"), None, Some(&recovery_key), None, None).unwrap();
+    assert!(restore_backup(&backup_path, &restore_dir.join("old"), None, Some(password), None, None).is_err());
+
+    // A password that doesn't match the escrow fails the backup instead of silently dropping the recovery slot
+    let result = compress_folder_with_options(
+        "recovery-test",
+        "Recovery Mismatch",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some("OtherPassword456!"),
+        None,
+        &options,
+    );
+    assert!(result.unwrap_err().contains("recovery key"));
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ RECOVERY KEY TEST PASSED");
+}
+
+#[test]
+fn test_single_archive_rekey_keeps_config_secrets() {
+    use inlocker_lib::backup::{compress_folder_with_options, load_manifest, rekey_config, update_manifest, BackupOptions};
+    use inlocker_lib::crypto::{generate_recovery_key, setup_recovery};
+    use inlocker_lib::types::KdfStrength;
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("rekey_single");
+    fs::create_dir_all(&restore_dir).unwrap();
+    fs::write(source_dir.join("doc.txt"), "v1").unwrap();
+    let old_password = "OldPassword123!";
+    let recovery_key = generate_recovery_key();
+    let mut config: BackupConfig = serde_json::from_value(serde_json::json!({
+        "id": "rekey-single",
+        "name": "Rekey Single",
+        "source_path": source_dir.to_string_lossy(),
+        "destination_path": dest_dir.to_string_lossy(),
+        "schedule": null,
+        "enabled": true,
+        "created_at": 0,
+        "updated_at": 0,
+        "mode": "encrypted"
+    })).unwrap();
+    config.recovery = Some(setup_recovery(&recovery_key, old_password, KdfStrength::Standard, b"rekey-single", 0).unwrap());
+    let options = BackupOptions { recovery: config.recovery.clone(), ..Default::default() };
+    let manifest_path = restore_dir.join("manifest.json");
+
+    let backup = |backup_type: &BackupType, manifest: Option<&BackupManifest>| {
+        compress_folder_with_options(
+            &config.id, &config.name, &source_dir, &dest_dir, backup_type, &BackupMode::Encrypted,
+            manifest, None, Some(old_password), None, &options,
+        )
+    };
+    let first = PathBuf::from(backup(&BackupType::Full, None).unwrap().backup_path.unwrap());
+    update_manifest(&manifest_path, &config.id, &source_dir, &BackupMode::Encrypted, Some(old_password), &options).unwrap();
+
+    // Re-keying one archive leaves the escrow and manifest on the old password
+    let (report, recovery) =
+        rekey_config(&config, Some(&first), &manifest_path, old_password, "NewPassword456!", KdfStrength::Standard).unwrap();
+    assert_eq!(report.rekeyed.len(), 1);
+    assert!(recovery.is_none(), "Escrow must not move to the new password");
+
+    // The next backup still runs with the old password: manifest readable, recovery slot added
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    fs::write(source_dir.join("doc.txt"), "v2").unwrap();
+    let manifest = load_manifest(&manifest_path, Some(old_password)).expect("Manifest keeps the old password");
+    let job = backup(&BackupType::Incremental, Some(&manifest)).expect("Backup with the old password should succeed");
+    assert_eq!(job.backup_type, BackupType::Incremental);
+    let second = PathBuf::from(job.backup_path.unwrap());
+    restore_backup(&second, &restore_dir.join("recovered"), None, Some(&recovery_key), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("recovered/doc.txt")).unwrap(), "v2");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+}
+
+#[test]
+fn test_passwordless_backup_with_recipient_key() {
+    use inlocker_lib::backup::{compress_folder_with_options, BackupOptions};
+    use inlocker_lib::crypto::generate_keypair;
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("recipient_key");
+
+    println!("📬 Testing unattended encrypted backup to a recipient public key...");
+
+    fs::write(source_dir.join("doc.txt"), "scheduled secret").unwrap();
+    let (public_key, private_key) = generate_keypair();
+    let options = BackupOptions {
+        recipients: vec![public_key],
+        ..Default::default()
+    };
+
+    // Scheduled backups run without a password
+    let job = compress_folder_with_options(
+        "recipient-test",
+        "Recipient Test",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        None,
+        None,
+        &options,
+    ).unwrap();
+    let backup_path = PathBuf::from(job.backup_path.unwrap());
+
+    let raw = fs::read(&backup_path).unwrap();
+    assert!(!raw.windows(16).any(|w| w == b"scheduled secret"), "Payload must be encrypted");
+
+    restore_backup(&backup_path, &restore_dir.join("key"), None, Some(&private_key), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("key/doc.txt")).unwrap(), "scheduled secret");
+
+    let (_, other_private_key) = generate_keypair();
+    assert!(restore_backup(&backup_path, &restore_dir.join("other"), None, Some(&other_private_key), None, None).is_err());
+    assert!(restore_backup(&backup_path, &restore_dir.join("none"), None, None, None, None).is_err());
+
+    // Without a password or recipients, encryption is refused
+    let result = compress_folder_with_options(
+        "recipient-test",
+        "No Secret",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        None,
+        None,
+        &BackupOptions::default(),
+    );
+    assert!(result.is_err());
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ RECIPIENT KEY TEST PASSED");
+}
+
+#[test]
+fn test_encrypted_manifest_hides_file_names() {
+    use inlocker_lib::backup::{
+        check_incremental_supported, load_manifest, update_manifest, BackupOptions, KEYED_CHECKSUM_PREFIX,
+    };
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("encrypted_manifest");
+
+    println!("📋 Testing encrypted manifest for encrypted configs...");
+
+    fs::write(source_dir.join("tax_return_2025.pdf"), "confidential").unwrap();
+    let password = "ManifestPassword123!";
+    let manifest_path = dest_dir.join("manifest_enc.json");
+    let options = BackupOptions::default();
+
+    update_manifest(&manifest_path, "manifest-enc", &source_dir, &BackupMode::Encrypted, Some(password), &options).unwrap();
+
+    // Neither file names nor plaintext hashes are visible on disk
+    let raw = fs::read(&manifest_path).unwrap();
+    assert!(!raw.windows(10).any(|w| w == b"tax_return"), "File names must not leak");
+    let plain_sha256 = calculate_sha256(b"confidential");
+    assert!(!String::from_utf8_lossy(&raw).contains(&plain_sha256));
+
+    let manifest = load_manifest(&manifest_path, Some(password)).unwrap();
+    let entry = &manifest.files["tax_return_2025.pdf"];
+    assert!(entry.checksum.starts_with(KEYED_CHECKSUM_PREFIX), "Content hash must be keyed");
+    assert_ne!(entry.checksum.trim_start_matches(KEYED_CHECKSUM_PREFIX), plain_sha256);
+    assert!(load_manifest(&manifest_path, None).is_err());
+    assert!(load_manifest(&manifest_path, Some("WrongPassword456!")).is_err());
+
+    // The hash key is kept between runs, so unchanged files keep their checksum
+    update_manifest(&manifest_path, "manifest-enc", &source_dir, &BackupMode::Encrypted, Some(password), &options).unwrap();
+    let updated = load_manifest(&manifest_path, Some(password)).unwrap();
+    assert_eq!(updated.files["tax_return_2025.pdf"].checksum, entry.checksum);
+
+    // Other modes keep the plain JSON manifest
+    let plain_path = dest_dir.join("manifest_plain.json");
+    update_manifest(&plain_path, "manifest-plain", &source_dir, &BackupMode::Compressed, None, &options).unwrap();
+    let plain: serde_json::Value = serde_json::from_slice(&fs::read(&plain_path).unwrap()).unwrap();
+    assert_eq!(plain["files"]["tax_return_2025.pdf"]["checksum"], plain_sha256.as_str());
+    assert!(load_manifest(&plain_path, None).is_ok());
+
+    // Without the password (recipient keys only) the manifest can't be read, so incrementals are refused
+    assert!(check_incremental_supported(&BackupMode::Encrypted, &BackupType::Incremental, None).is_err());
+    assert!(check_incremental_supported(&BackupMode::Encrypted, &BackupType::Incremental, Some(password)).is_ok());
+    assert!(check_incremental_supported(&BackupMode::Encrypted, &BackupType::Full, None).is_ok());
+    assert!(check_incremental_supported(&BackupMode::Compressed, &BackupType::Incremental, None).is_ok());
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ ENCRYPTED MANIFEST TEST PASSED");
+}
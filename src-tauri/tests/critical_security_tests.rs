@@ -6,8 +6,8 @@
 /// Priority: CRITICAL (Week 1)
 /// Reference: docs/08-testing-strategy.md
 
-use inlocker_lib::backup::{compress_folder, restore_backup};
-use inlocker_lib::types::{BackupMode, BackupType};
+use inlocker_lib::backup::{compress_folder, compress_folder_with_options, restore_backup, BackupOptions};
+use inlocker_lib::types::{BackupMode, BackupType, KdfStrength};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -769,6 +769,232 @@ fn test_very_large_file_integrity() {
     cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
 }
 
+// ============================================================================
+// 🚨 CRITICAL TEST #10: ENCRYPTED ARCHIVE HEADER TAMPERING
+// ============================================================================
+
+/// Helper: Create an encrypted backup and return its raw bytes
+fn encrypted_archive(config_id: &str, source_dir: &Path, dest_dir: &Path, password: &str) -> Vec<u8> {
+    let backup_job = compress_folder(
+        config_id,
+        "Header Tamper Test",
+        source_dir,
+        dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some(password),
+        None,
+    ).unwrap();
+    fs::read(backup_job.backup_path.unwrap()).unwrap()
+}
+
+/// Helper: Length of the container header (fixed part + JSON body)
+fn container_header_len(archive: &[u8]) -> usize {
+    18 + u32::from_le_bytes([archive[14], archive[15], archive[16], archive[17]]) as usize
+}
+
+/// Helper: Byte range of the key slot JSON that follows the header
+fn key_slot_area(archive: &[u8]) -> (usize, usize) {
+    let header_len = container_header_len(archive);
+    let len = u32::from_le_bytes([
+        archive[header_len],
+        archive[header_len + 1],
+        archive[header_len + 2],
+        archive[header_len + 3],
+    ]) as usize;
+    (header_len + 4, header_len + 4 + len)
+}
+
+/// Helper: Replace the first occurrence of `from` (same length as `to`) inside the header
+fn patch_header(archive: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    assert_eq!(from.len(), to.len(), "Patch must keep the header length");
+    let header_len = container_header_len(archive);
+    let pos = archive[..header_len]
+        .windows(from.len())
+        .position(|w| w == from)
+        .expect("pattern not found in header");
+    let mut patched = archive.to_vec();
+    patched[pos..pos + to.len()].copy_from_slice(to);
+    patched
+}
+
+#[test]
+fn test_encrypted_header_tampering_detected() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("header_tamper");
+
+    println!("🔐 Testing that every header edit fails authentication...");
+
+    fs::write(source_dir.join("secret.txt"), "header must be authenticated").unwrap();
+    let password = "HeaderTamper123!";
+
+    let archive = encrypted_archive("header-tamper-a", &source_dir, &dest_dir, password);
+
+    let restore = |name: &str, bytes: &[u8]| {
+        let path = dest_dir.join(format!("{}.tar.zst.enc", name));
+        fs::write(&path, bytes).unwrap();
+        restore_backup(&path, &restore_dir.join(name), None, Some(password), None, None)
+    };
+
+    // Untouched archive restores
+    restore("original", &archive).unwrap();
+    assert_eq!(
+        fs::read_to_string(restore_dir.join("original/secret.txt")).unwrap(),
+        "header must be authenticated"
+    );
+
+    // 1. Config id swapped
+    let tampered = patch_header(&archive, b"\"header-tamper-a\"", b"\"header-tamper-b\"");
+    assert!(restore("config_id", &tampered).is_err(), "Edited config id must be rejected");
+
+    // 2. Creation time changed
+    let header_json = String::from_utf8_lossy(&archive[18..container_header_len(&archive)]).to_string();
+    let created_pos = header_json.find("\"created_at\":").expect("created_at missing from header");
+    let digit_pos = 18 + created_pos + "\"created_at\":".len();
+    let mut tampered = archive.clone();
+    tampered[digit_pos] = if tampered[digit_pos] == b'1' { b'2' } else { b'1' };
+    assert!(restore("created_at", &tampered).is_err(), "Edited creation time must be rejected");
+
+    // 3. Incremental flag toggled
+    let mut tampered = archive.clone();
+    tampered[13] ^= 0b0000_0010;
+    assert!(restore("flags", &tampered).is_err(), "Edited flags must be rejected");
+
+    // 4. Compression id changed (still a known id)
+    let mut tampered = archive.clone();
+    tampered[12] = 0;
+    assert!(restore("compression", &tampered).is_err(), "Edited compression id must be rejected");
+
+    // Nothing was extracted from any tampered archive
+    for name in ["config_id", "created_at", "flags", "compression"] {
+        assert!(
+            !restore_dir.join(name).join("secret.txt").exists(),
+            "Tampered archive '{}' must not produce output",
+            name
+        );
+    }
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ HEADER TAMPERING TEST PASSED");
+}
+
+#[test]
+fn test_encrypted_header_swap_detected() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("header_swap");
+
+    println!("🔐 Testing that headers cannot be swapped between archives...");
+
+    let password = "HeaderSwap123!";
+    fs::write(source_dir.join("a.txt"), "archive A").unwrap();
+    let archive_a = encrypted_archive("header-swap", &source_dir, &dest_dir, password);
+
+    // Sleep so the second archive gets a distinct file name
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    fs::write(source_dir.join("a.txt"), "archive B").unwrap();
+    let archive_b = encrypted_archive("header-swap", &source_dir, &dest_dir, password);
+
+    // Header of A followed by the payload of B
+    let mut swapped = archive_a[..container_header_len(&archive_a)].to_vec();
+    swapped.extend_from_slice(&archive_b[container_header_len(&archive_b)..]);
+
+    let swapped_path = dest_dir.join("swapped.tar.zst.enc");
+    fs::write(&swapped_path, &swapped).unwrap();
+    let result = restore_backup(&swapped_path, &restore_dir, None, Some(password), None, None);
+
+    assert!(result.is_err(), "Archive with a foreign header must be rejected");
+    assert!(!restore_dir.join("a.txt").exists(), "Nothing may be extracted from a swapped archive");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ HEADER SWAP TEST PASSED");
+}
+
+// ============================================================================
+// 🚨 CRITICAL TEST #11: KDF PARAMETERS FROM ARCHIVE HEADER
+// ============================================================================
+
+#[test]
+fn test_kdf_params_from_header_are_bounded() {
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("kdf_bounds");
+
+    println!("🔐 Testing stored Argon2 parameters (honored, but bounded)...");
+
+    fs::write(source_dir.join("secret.txt"), "stronger kdf").unwrap();
+    let password = "KdfBounds123!";
+
+    let backup_job = compress_folder_with_options(
+        "kdf-bounds-test",
+        "KDF Bounds Test",
+        &source_dir,
+        &dest_dir,
+        &BackupType::Full,
+        &BackupMode::Encrypted,
+        None,
+        None,
+        Some(password),
+        None,
+        &BackupOptions { kdf_strength: KdfStrength::High, ..Default::default() },
+    ).unwrap();
+    let backup_path = PathBuf::from(backup_job.backup_path.unwrap());
+    let archive = fs::read(&backup_path).unwrap();
+
+    // The chosen strength is recorded in the key slot and used on restore
+    let (slots_start, slots_end) = key_slot_area(&archive);
+    let slots_json = String::from_utf8_lossy(&archive[slots_start..slots_end]).to_string();
+    assert!(slots_json.contains("\"memory_cost\":262144"), "Key slot must record 256 MB: {}", slots_json);
+    restore_backup(&backup_path, &restore_dir.join("ok"), None, Some(password), None, None).unwrap();
+    assert_eq!(fs::read_to_string(restore_dir.join("ok/secret.txt")).unwrap(), "stronger kdf");
+
+    // A key slot demanding absurd memory is refused before key derivation starts
+    let mut slots: serde_json::Value = serde_json::from_slice(&archive[slots_start..slots_end]).unwrap();
+    slots[0]["memory_cost"] = serde_json::json!(64 * 1024 * 1024); // 64 GB
+    let slots = serde_json::to_vec(&slots).unwrap();
+    let mut tampered = archive[..slots_start - 4].to_vec();
+    tampered.extend_from_slice(&(slots.len() as u32).to_le_bytes());
+    tampered.extend_from_slice(&slots);
+    tampered.extend_from_slice(&archive[slots_end..]);
+    let tampered_path = dest_dir.join("huge_memory.tar.zst.enc");
+    fs::write(&tampered_path, &tampered).unwrap();
+    let err = restore_backup(&tampered_path, &restore_dir.join("huge"), None, Some(password), None, None).unwrap_err();
+    assert!(err.contains("out of range"), "Unexpected error: {}", err);
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ KDF BOUNDS TEST PASSED");
+}
+
+/// CRITICAL TEST: Password files for unattended backups must be private
+///
+/// A world-readable password file defeats the encryption, so the file
+/// provider refuses it instead of silently using it.
+#[test]
+#[cfg(unix)]
+fn test_secret_file_permissions_enforced() {
+    use inlocker_lib::secrets::{resolve_secret, SecretSource};
+    use std::os::unix::fs::PermissionsExt;
+
+    let (source_dir, dest_dir, restore_dir) = setup_test_dirs("secret_file");
+
+    println!("🔐 Testing secret file permission check...");
+
+    let secret_path = dest_dir.join("backup.pass");
+    fs::write(&secret_path, "FilePassword123!\n").unwrap();
+    let source = SecretSource::File { path: secret_path.to_string_lossy().to_string() };
+
+    fs::set_permissions(&secret_path, fs::Permissions::from_mode(0o644)).unwrap();
+    let err = resolve_secret(&source).unwrap_err();
+    assert!(err.contains("chmod 600"), "Unexpected error: {}", err);
+
+    fs::set_permissions(&secret_path, fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(resolve_secret(&source).unwrap().as_str(), "FilePassword123!");
+
+    cleanup_test_dirs(&[&source_dir, &dest_dir, &restore_dir]);
+
+    println!("\n✅ SECRET FILE PERMISSIONS TEST PASSED");
+}
+
 /// Helper: Calculate SHA-256 checksum
 fn calculate_sha256(data: &[u8]) -> String {
     use ring::digest::{Context, SHA256};
@@ -521,3 +521,414 @@ fn test_iv_uniqueness_across_encryptions() {
 
     assert_eq!(nonces.len(), 10, "All 10 nonces should be unique");
 }
+
+// ============================================================================
+// TEST CATEGORY 8: STREAMING ENCRYPTION (STREAM construction)
+// ============================================================================
+
+/// Helper: encrypt with a small chunk size so tests cross many chunk boundaries
+fn stream_encrypt(plaintext: &[u8], password: &str, chunk_size: u32) -> (Vec<u8>, EncryptionMetadata) {
+    use std::io::Write;
+
+    let mut metadata = new_stream_metadata();
+    metadata.chunk_size = chunk_size;
+
+    let mut encryptor = StreamEncryptor::new(Vec::new(), password, &metadata, &[]).unwrap();
+    encryptor.write_all(plaintext).unwrap();
+    (encryptor.finish().unwrap(), metadata)
+}
+
+/// Helper: decrypt a whole stream, returning the error message on failure
+fn stream_decrypt(ciphertext: &[u8], password: &str, metadata: &EncryptionMetadata) -> Result<Vec<u8>, String> {
+    let mut plaintext = Vec::new();
+    decrypt_streaming(ciphertext, &mut plaintext, password, metadata)?;
+    Ok(plaintext)
+}
+
+#[test]
+fn test_stream_round_trip_across_chunks() {
+    let password = "StreamPassword123!";
+    let plaintext: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+    let (ciphertext, metadata) = stream_encrypt(&plaintext, password, 1024);
+
+    // 9 full chunks + 1 partial final chunk, each with a 16-byte tag
+    assert_eq!(ciphertext.len(), plaintext.len() + 10 * 16);
+
+    let decrypted = stream_decrypt(&ciphertext, password, &metadata).unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn test_stream_exact_chunk_multiple() {
+    let password = "StreamPassword123!";
+    let plaintext = vec![0x42u8; 4096];
+
+    // Exact multiple: 4 full chunks + an empty final chunk (tag only)
+    let (ciphertext, metadata) = stream_encrypt(&plaintext, password, 1024);
+    assert_eq!(ciphertext.len(), plaintext.len() + 5 * 16);
+
+    let decrypted = stream_decrypt(&ciphertext, password, &metadata).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // Empty input still produces one authenticated final chunk
+    let (ciphertext, metadata) = stream_encrypt(b"", password, 1024);
+    assert_eq!(ciphertext.len(), 16);
+    assert!(stream_decrypt(&ciphertext, password, &metadata).unwrap().is_empty());
+}
+
+#[test]
+fn test_stream_wrong_password_fails() {
+    let (ciphertext, metadata) = stream_encrypt(b"Streamed secret", "CorrectPassword123!", 1024);
+
+    let result = stream_decrypt(&ciphertext, "WrongPassword456!", &metadata);
+    assert!(result.is_err());
+    assert!(result.unwrap_err().contains("Decryption failed"));
+}
+
+#[test]
+fn test_stream_truncation_detected() {
+    let password = "StreamPassword123!";
+    let plaintext = vec![7u8; 5000];
+    let (ciphertext, metadata) = stream_encrypt(&plaintext, password, 1024);
+    let sealed_chunk = 1024 + 16;
+
+    // Drop the final chunk: the stream now ends on a full (non-final) chunk
+    let truncated = &ciphertext[..4 * sealed_chunk];
+    assert!(
+        stream_decrypt(truncated, password, &metadata).is_err(),
+        "Dropping the final chunk must be detected"
+    );
+
+    // Cut in the middle of a chunk
+    let truncated = &ciphertext[..2 * sealed_chunk + 100];
+    assert!(
+        stream_decrypt(truncated, password, &metadata).is_err(),
+        "Cutting a chunk short must be detected"
+    );
+
+    // Appending garbage after the final chunk must be detected as well
+    let mut extended = ciphertext.clone();
+    extended.extend_from_slice(&[0u8; 32]);
+    assert!(
+        stream_decrypt(&extended, password, &metadata).is_err(),
+        "Trailing data must be detected"
+    );
+}
+
+#[test]
+fn test_stream_reordered_chunks_detected() {
+    let password = "StreamPassword123!";
+    let plaintext: Vec<u8> = (0..4000).map(|i| (i % 256) as u8).collect();
+    let (ciphertext, metadata) = stream_encrypt(&plaintext, password, 1024);
+    let sealed_chunk = 1024 + 16;
+
+    // Swap chunks 1 and 2
+    let mut reordered = ciphertext.clone();
+    reordered[sealed_chunk..2 * sealed_chunk].copy_from_slice(&ciphertext[2 * sealed_chunk..3 * sealed_chunk]);
+    reordered[2 * sealed_chunk..3 * sealed_chunk].copy_from_slice(&ciphertext[sealed_chunk..2 * sealed_chunk]);
+
+    assert!(
+        stream_decrypt(&reordered, password, &metadata).is_err(),
+        "Reordered chunks must fail authentication"
+    );
+}
+
+#[test]
+fn test_stream_metadata_fresh_per_payload() {
+    let meta1 = new_stream_metadata();
+    let meta2 = new_stream_metadata();
+
+    assert_ne!(meta1.salt, meta2.salt, "Salt must be random per payload");
+    assert_ne!(meta1.nonce, meta2.nonce, "Nonce prefix must be random per payload");
+    assert_eq!(meta1.chunk_size, STREAM_CHUNK_SIZE);
+}
+
+// ============================================================================
+// TEST CATEGORY 9: ASSOCIATED DATA (header binding)
+// ============================================================================
+
+#[test]
+fn test_associated_data_must_match() {
+    let password = "AadPassword123!";
+    let plaintext = b"bound to its header";
+
+    let (ciphertext, metadata) = encrypt_with_aad(plaintext, password, b"header-a").unwrap();
+
+    assert_eq!(decrypt_with_aad(&ciphertext, password, &metadata, b"header-a").unwrap(), plaintext);
+    assert!(decrypt_with_aad(&ciphertext, password, &metadata, b"header-b").is_err());
+    assert!(decrypt(&ciphertext, password, &metadata).is_err(), "Empty AAD must not match");
+}
+
+#[test]
+fn test_stream_associated_data_must_match() {
+    use std::io::{Read, Write};
+
+    let password = "AadPassword123!";
+    let plaintext: Vec<u8> = (0..3000).map(|i| (i % 256) as u8).collect();
+    let mut metadata = new_stream_metadata();
+    metadata.chunk_size = 1024;
+
+    let mut encryptor = StreamEncryptor::new(Vec::new(), password, &metadata, b"header-a").unwrap();
+    encryptor.write_all(&plaintext).unwrap();
+    let ciphertext = encryptor.finish().unwrap();
+
+    let mut decrypted = Vec::new();
+    StreamDecryptor::new(&ciphertext[..], password, &metadata, b"header-a")
+        .unwrap()
+        .read_to_end(&mut decrypted)
+        .unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    assert!(
+        StreamDecryptor::new(&ciphertext[..], password, &metadata, b"header-b").is_err(),
+        "Different associated data must fail on the first chunk"
+    );
+}
+
+// ============================================================================
+// TEST CATEGORY 10: KDF PARAMETERS (stored in metadata)
+// ============================================================================
+
+#[test]
+fn test_derive_key_honors_metadata_params() {
+    let password = "KdfParams123!";
+    let salt = generate_salt();
+
+    let standard = EncryptionMetadata::default();
+    let lighter = EncryptionMetadata {
+        memory_cost: 16 * 1024,
+        iterations: 2,
+        ..Default::default()
+    };
+
+    let key_default = derive_key(password, &salt).unwrap();
+    let key_standard = derive_key_with_params(password, &salt, &standard).unwrap();
+    let key_lighter = derive_key_with_params(password, &salt, &lighter).unwrap();
+
+    assert_eq!(key_default, key_standard, "derive_key must use the default parameters");
+    assert_ne!(key_standard, key_lighter, "Different parameters must give different keys");
+}
+
+#[test]
+fn test_kdf_params_out_of_bounds_rejected() {
+    let salt = generate_salt();
+
+    type Tamper = fn(&mut EncryptionMetadata);
+    let cases: [(&str, Tamper); 5] = [
+        ("memory too large", |m| m.memory_cost = MAX_MEMORY_COST + 1),
+        ("memory too small", |m| m.memory_cost = MIN_MEMORY_COST - 1),
+        ("too many iterations", |m| m.iterations = MAX_ITERATIONS + 1),
+        ("zero parallelism", |m| m.parallelism = 0),
+        ("unknown version", |m| m.version = 0x10),
+    ];
+
+    for (name, tamper) in cases {
+        let mut metadata = EncryptionMetadata::default();
+        tamper(&mut metadata);
+        assert!(validate_kdf_params(&metadata).is_err(), "{} must be rejected", name);
+        assert!(
+            derive_key_with_params("password", &salt, &metadata).is_err(),
+            "{} must be rejected before deriving",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_stream_round_trip_with_custom_kdf_strength() {
+    let password = "KdfStrength123!";
+    let plaintext = b"encrypted with a stronger KDF";
+
+    let mut metadata = new_stream_metadata();
+    metadata.memory_cost = 32 * 1024;
+    metadata.iterations = 4;
+
+    let mut ciphertext = Vec::new();
+    {
+        use std::io::Write;
+        let mut encryptor = StreamEncryptor::new(Vec::new(), password, &metadata, &[]).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        ciphertext.extend(encryptor.finish().unwrap());
+    }
+
+    // Decrypting with the recorded parameters works...
+    let decrypted = stream_decrypt(&ciphertext, password, &metadata).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // ...while the default parameters derive a different key
+    let mut default_params = metadata.clone();
+    default_params.memory_cost = EncryptionMetadata::default().memory_cost;
+    default_params.iterations = EncryptionMetadata::default().iterations;
+    assert!(stream_decrypt(&ciphertext, password, &default_params).is_err());
+}
+
+// ============================================================================
+// TEST CATEGORY 11: KEY WRAPPING (data key in key slots)
+// ============================================================================
+
+#[test]
+fn test_wrapped_data_key_round_trip() {
+    use inlocker_lib::types::KdfStrength;
+
+    let data_key = generate_data_key();
+    assert_eq!(data_key.len(), DATA_KEY_LEN);
+
+    let slot = wrap_data_key(&data_key, "WrapPassword123!", KdfStrength::Standard, b"header").unwrap();
+    let (unwrapped, index) = unwrap_data_key(std::slice::from_ref(&slot), "WrapPassword123!", b"header").unwrap();
+    assert_eq!(&unwrapped[..], &data_key[..]);
+    assert_eq!(index, 0);
+
+    // Wrong password or a different header must not open the slot
+    assert!(unwrap_data_key(std::slice::from_ref(&slot), "WrongPassword456!", b"header").is_err());
+    assert!(unwrap_data_key(&[slot], "WrapPassword123!", b"other header").is_err());
+    assert!(unwrap_data_key(&[], "WrapPassword123!", b"header").is_err());
+}
+
+#[test]
+fn test_rekey_slots_keeps_data_key() {
+    use inlocker_lib::types::KdfStrength;
+    use std::io::{Read, Write};
+
+    let data_key = generate_data_key();
+    let slots = vec![wrap_data_key(&data_key, "OldPassword123!", KdfStrength::Standard, b"header").unwrap()];
+
+    // Payload encrypted once with the data key
+    let mut metadata = new_stream_metadata();
+    metadata.chunk_size = 1024;
+    let mut encryptor = StreamEncryptor::with_data_key(Vec::new(), &data_key, &metadata, b"header").unwrap();
+    encryptor.write_all(b"payload is never re-encrypted").unwrap();
+    let ciphertext = encryptor.finish().unwrap();
+
+    let rekeyed = rekey_slots(&slots, "OldPassword123!", "NewPassword456!", KdfStrength::Standard, b"header").unwrap();
+    assert_eq!(rekeyed.len(), 1);
+    assert!(unwrap_data_key(&rekeyed, "OldPassword123!", b"header").is_err());
+
+    let (unwrapped, _) = unwrap_data_key(&rekeyed, "NewPassword456!", b"header").unwrap();
+    let mut plaintext = Vec::new();
+    StreamDecryptor::with_data_key(&ciphertext[..], &unwrapped, &metadata, b"header")
+        .unwrap()
+        .read_to_end(&mut plaintext)
+        .unwrap();
+    assert_eq!(plaintext, b"payload is never re-encrypted");
+
+    // Wrong current password: nothing to re-key
+    assert!(rekey_slots(&slots, "WrongPassword789!", "NewPassword456!", KdfStrength::Standard, b"header").is_err());
+}
+
+// ============================================================================
+// TEST CATEGORY 12: RECOVERY KEY SLOTS
+// ============================================================================
+
+#[test]
+fn test_recovery_key_format() {
+    let key = generate_recovery_key();
+
+    // 8 groups of 4 base32 characters
+    let groups: Vec<&str> = key.split('-').collect();
+    assert_eq!(groups.len(), 8, "Unexpected recovery key format: {}", key.as_str());
+    assert!(groups.iter().all(|g| g.len() == 4 && g.chars().all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c))));
+
+    assert_ne!(key.as_str(), generate_recovery_key().as_str(), "Recovery keys must be random");
+    assert_eq!(
+        normalize_recovery_key(" abcd-EFGH 2345 ").as_str(),
+        "ABCDEFGH2345",
+        "Separators, whitespace and case must not matter"
+    );
+}
+
+#[test]
+fn test_recovery_slot_opens_with_recovery_key() {
+    use inlocker_lib::types::KdfStrength;
+
+    let password = "BackupPassword123!";
+    let recovery_key = generate_recovery_key();
+    let recovery = setup_recovery(&recovery_key, password, KdfStrength::Standard, b"config-1", 0).unwrap();
+
+    let data_key = generate_data_key();
+    let slots = vec![
+        wrap_data_key(&data_key, password, KdfStrength::Standard, b"header").unwrap(),
+        recovery_key_slot(&data_key, &recovery, password, b"config-1", b"header").unwrap(),
+    ];
+    assert_eq!(slots[1].kind, KeySlotKind::Recovery);
+
+    // Either secret opens the archive; the recovery key may be typed loosely
+    let (by_password, index) = unwrap_data_key(&slots, password, b"header").unwrap();
+    assert_eq!((&by_password[..], index), (&data_key[..], 0));
+    let typed = recovery_key.to_lowercase().replace('-', " ");
+    let (by_recovery, index) = unwrap_data_key(&slots, &typed, b"header").unwrap();
+    assert_eq!((&by_recovery[..], index), (&data_key[..], 1));
+
+    // The escrow only opens with the password it was set up with
+    assert!(recovery_key_slot(&data_key, &recovery, "OtherPassword456!", b"config-1", b"header").is_err());
+}
+
+#[test]
+fn test_forgotten_password_reset_with_recovery_key() {
+    use inlocker_lib::types::KdfStrength;
+
+    let old_password = "ForgottenPassword123!";
+    let new_password = "FreshPassword456!";
+    let recovery_key = generate_recovery_key();
+    let recovery = setup_recovery(&recovery_key, old_password, KdfStrength::Standard, b"config-1", 0).unwrap();
+
+    let data_key = generate_data_key();
+    let slots = vec![
+        wrap_data_key(&data_key, old_password, KdfStrength::Standard, b"header").unwrap(),
+        recovery_key_slot(&data_key, &recovery, old_password, b"config-1", b"header").unwrap(),
+    ];
+
+    // Password slot replaced, recovery slot kept
+    let rekeyed = rekey_slots(&slots, &recovery_key, new_password, KdfStrength::Standard, b"header").unwrap();
+    assert_eq!(rekeyed.len(), 2);
+    assert_eq!(rekeyed.iter().filter(|s| s.kind == KeySlotKind::Password).count(), 1);
+    assert!(unwrap_data_key(&rekeyed, old_password, b"header").is_err());
+    assert!(unwrap_data_key(&rekeyed, new_password, b"header").is_ok());
+    assert!(unwrap_data_key(&rekeyed, &recovery_key, b"header").is_ok());
+
+    // The escrow can be re-wrapped with the recovery key, but not with a wrong key
+    let recovery = rekey_recovery_escrow(&recovery, &recovery_key, new_password, KdfStrength::Standard, b"config-1").unwrap();
+    assert!(recovery_key_slot(&data_key, &recovery, new_password, b"config-1", b"header").is_ok());
+    let wrong_key = generate_recovery_key();
+    assert!(rekey_recovery_escrow(&recovery, &wrong_key, new_password, KdfStrength::Standard, b"config-1").is_err());
+}
+
+// ============================================================================
+// TEST CATEGORY 13: X25519 RECIPIENT KEY SLOTS
+// ============================================================================
+
+#[test]
+fn test_keypair_export_and_import() {
+    let (public_key, private_key) = generate_keypair();
+
+    assert!(public_key.starts_with(PUBLIC_KEY_PREFIX));
+    assert!(private_key.starts_with(PRIVATE_KEY_PREFIX));
+    assert!(parse_public_key(&public_key).is_ok());
+    assert_eq!(public_key_of(&private_key).unwrap(), public_key, "Public key must be derivable from the private key");
+
+    // Keys are not interchangeable and must be well-formed
+    assert!(parse_public_key(&private_key).is_err());
+    assert!(parse_private_key(&public_key).is_err());
+    assert!(parse_public_key("inlocker-pk-1234").is_err());
+    assert!(parse_public_key(&format!("{}{}", PUBLIC_KEY_PREFIX, "zz".repeat(32))).is_err());
+}
+
+#[test]
+fn test_recipient_slot_opens_with_private_key_only() {
+    let (public_key, private_key) = generate_keypair();
+    let (_, other_private_key) = generate_keypair();
+
+    let data_key = generate_data_key();
+    let slot = wrap_data_key_to_recipient(&data_key, &public_key, b"header").unwrap();
+    assert_eq!(slot.kind, KeySlotKind::X25519);
+    assert!(slot.ephemeral_key.is_some());
+    let slots = vec![slot];
+
+    let (unwrapped, index) = unwrap_data_key(&slots, &private_key, b"header").unwrap();
+    assert_eq!(unwrapped.as_slice(), data_key.as_slice());
+    assert_eq!(index, 0);
+
+    assert!(unwrap_data_key(&slots, &other_private_key, b"header").is_err(), "Another private key must not open the slot");
+    assert!(unwrap_data_key(&slots, "SomePassword123!", b"header").is_err(), "A password must not open the slot");
+    assert!(unwrap_data_key(&slots, &private_key, b"tampered").is_err(), "Slot must be bound to the header");
+}
@@ -19,13 +19,216 @@ export interface BackupConfig {
   last_backup_compressed_size: number | null;
   last_backup_files_count: number | null;
   last_backup_checksum: string | null;
+  last_scheduled_run_at?: number | null;
+  last_run_attempts?: RunAttempt[]; // Attempts of the last scheduled run (retries included)
+  kdf_strength?: 'standard' | 'high' | 'maximum' | null; // Overrides the app preference when set
+  recovery?: unknown | null; // Escrowed recovery key (managed by the backend, created by save_config)
+  recipients?: string[]; // X25519 public keys (inlocker-pk-...) for password-less encrypted backups
+  password_source?: SecretSource | null; // Where scheduled backups fetch the password (never the password itself)
+  watch?: WatchConfig | null; // Change-triggered backups
+  retention?: RetentionPolicy | null; // Which archives to keep (null = keep everything)
 }
 
+// Result of invoke('save_config', { config })
+export interface SavedConfig extends BackupConfig {
+  recovery_key?: string; // Generated when the config became encrypted with its password; shown once
+}
+
+// Archives kept if any keep_* rule selects them; incremental chains are never broken
+export interface RetentionPolicy {
+  keep_last?: number | null;
+  keep_daily?: number | null;
+  keep_weekly?: number | null;
+  keep_monthly?: number | null;
+  keep_yearly?: number | null;
+  keep_within_secs?: number | null;
+  max_total_size?: number | null; // Bytes
+  prune_after_backup?: boolean;
+}
+
+// Result of invoke('prune_backups', { configId, dryRun })
+export interface PruneDecision {
+  filename: string;
+  path: string;
+  kind: 'full' | 'incremental';
+  created_at: number;
+  size: number;
+  reasons: string[]; // Why the archive is kept (empty when removed)
+}
+
+export interface PruneReport {
+  config_id: string;
+  dry_run: boolean;
+  kept: PruneDecision[];
+  removed: PruneDecision[];
+  freed_bytes: number;
+  errors: string[];
+}
+
+// Result of invoke('restore_point_in_time', { configId, timestamp, restoreDestination, password })
+export interface ChainRestoreResult {
+  success: boolean;
+  message: string;
+  target: string; // Newest archive at or before the requested time
+  archives: string[]; // Applied oldest first (full backup, then incrementals)
+  files_count: number;
+  renamed_count: number; // Files moved because a later archive recorded the move
+  deleted_count: number; // Files removed because a later archive recorded their deletion
+  started_at: number;
+  completed_at: number;
+}
+
+// Archive browsing: invoke('list_archive_entries', { archivePath, password, query })
+export interface EntryQuery {
+  prefix?: string; // Directory to list ('' = archive root)
+  recursive?: boolean; // Every file under prefix instead of its direct children
+  offset?: number;
+  limit?: number; // 0 or unset = 200
+}
+
+export interface ArchiveNode {
+  path: string; // Relative to the archive root, '/'-separated
+  name: string;
+  kind: 'file' | 'directory' | 'symlink' | 'hardlink' | 'other';
+  size: number; // Directories: total size of the files under them
+  modified_at: number;
+  mode: number; // Unix permission bits (0 when unknown)
+  files_count: number; // Directories: number of files under them
+}
+
+export interface EntryPage {
+  archive: string;
+  prefix: string;
+  entries: ArchiveNode[]; // Directories first, then by name (recursive: archive order)
+  total: number; // Entries across all pages
+  files_count: number;
+  total_size: number;
+}
+
+// Selective restore: invoke('restore_backup', { ..., selection })
+// Paths are archive paths as listed by list_archive_entries; patterns use *, ? and **
+export interface RestoreSelection {
+  paths?: string[]; // Files or folders to restore (empty = everything)
+  include?: string[]; // Only entries matching one of these patterns
+  exclude?: string[]; // Entries matching one of these patterns are skipped
+  strip_components?: number; // Leading path components removed from restored paths
+  rename?: Record<string, string>; // Archive path (file or folder) -> path to restore it under
+}
+
+// Result of invoke('import_destination', { destinationPath, password, repoint })
+export interface ImportedArchive {
+  filename: string;
+  path: string;
+  backup_type: 'full' | 'incremental';
+  created_at: number;
+  size: number;
+  indexed: boolean; // Embedded metadata was read (false: locked, or an older archive)
+}
+
+export interface ImportedConfig {
+  config_id: string;
+  name: string;
+  created: boolean; // false = an existing config was updated
+  archives: ImportedArchive[]; // Oldest first
+  history_added: number;
+}
+
+// A known config whose archives were found in another folder (moved or cloned drive)
+export interface MovedConfig {
+  config_id: string;
+  name: string;
+  from: string; // Destination the config points at
+  to: string; // Folder being imported
+  repointed: boolean; // false = left unchanged; import again with repoint: true to move it
+}
+
+export interface ImportReport {
+  destination: string;
+  configs: ImportedConfig[];
+  moved: MovedConfig[];
+  locked: string[]; // Encrypted archives that need the password (import again with it)
+  errors: string[];
+}
+
+export interface WatchConfig {
+  enabled: boolean;
+  quiet_period_secs: number; // Seconds without changes before a backup starts (default 30)
+  min_interval_secs: number; // Minimum seconds between change-triggered backups (default 300)
+}
+
+export type SecretSource =
+  | { provider: 'file'; path: string }
+  | { provider: 'env'; name: string }
+  | { provider: 'command'; program: string; args?: string[] }
+  | { provider: 'keyring'; service: string; account: string };
+
 export interface ScheduleConfig {
   cron_expression: string;
   preset: 'hourly' | 'daily' | 'weekly' | 'monthly' | 'custom' | null;
   next_run: number | null;
   enabled: boolean;
+  in_app?: boolean; // Run with the in-app scheduler instead of launchd/systemd
+  catch_up?: CatchUpPolicy;
+  retry?: RetryPolicy;
+}
+
+// Automatic retry of failed scheduled backups (exponential backoff)
+export type ErrorClass = 'destination_unavailable' | 'source_unavailable' | 'secret_unavailable' | 'io' | 'other';
+
+export interface RetryPolicy {
+  max_attempts: number; // Including the first attempt (1 = no retry)
+  initial_delay_secs: number;
+  backoff_factor: number;
+  max_delay_secs: number;
+  retry_on: ErrorClass[];
+}
+
+export interface RunAttempt {
+  attempt: number;
+  started_at: number;
+  finished_at: number;
+  error: string | null;
+  error_class: ErrorClass | null;
+}
+
+// What to do about runs missed while the computer was asleep or off
+export type CatchUpPolicy =
+  | { policy: 'skip' }
+  | { policy: 'run_once' }
+  | { policy: 'if_older_than'; max_age_secs: number };
+
+// Job history (query with invoke('query_job_history', { query }))
+export interface JobRecord {
+  job_id: string;
+  config_id: string;
+  trigger: 'manual' | 'scheduled' | 'watch' | 'cli' | 'import'; // import = found by import_destination
+  status: 'completed' | 'failed' | 'cancelled';
+  backup_type: 'full' | 'incremental';
+  started_at: number;
+  completed_at: number;
+  duration_secs: number;
+  original_size: number | null;
+  compressed_size: number | null;
+  files_count: number | null;
+  changed_files_count: number | null;
+  error_message: string | null;
+  backup_path: string | null;
+  checksum: string | null;
+}
+
+export interface HistoryQuery {
+  config_id?: string | null;
+  status?: JobRecord['status'] | null;
+  trigger?: JobRecord['trigger'] | null;
+  since?: number | null; // Unix seconds, inclusive
+  until?: number | null; // Unix seconds, exclusive
+  offset?: number;
+  limit?: number; // 0 or unset = 50
+}
+
+export interface HistoryPage {
+  records: JobRecord[]; // Newest first
+  total: number; // Matching runs across all pages
 }
 
 interface BackupStore {
@@ -36,7 +239,7 @@ interface BackupStore {
 
   // Actions
   loadConfigs: () => Promise<void>;
-  saveConfig: (config: BackupConfig) => Promise<void>;
+  saveConfig: (config: BackupConfig) => Promise<string | null>; // Resolves to a new recovery key, if one was created
   deleteConfig: (configId: string) => Promise<void>;
   selectFolder: () => Promise<string | null>;
 }
@@ -62,7 +265,9 @@ export const useBackupStore = create<BackupStore>((set, get) => ({
   saveConfig: async (config: BackupConfig) => {
     set({ isLoading: true, error: null });
     try {
-      await invoke('save_config', { config });
+      const saved = await invoke<SavedConfig>('save_config', { config });
+      // The backend fills in schedule.next_run and the recovery escrow; the password is not kept
+      config = { ...config, schedule: saved.schedule, recovery: saved.recovery, encryption_password: undefined };
 
       // Update config in-place without full reload to preserve UI state
       // This prevents disrupting running backups when editing other configs
@@ -77,8 +282,10 @@ export const useBackupStore = create<BackupStore>((set, get) => ({
       }
 
       set({ configs: updatedConfigs, isLoading: false });
+      return saved.recovery_key ?? null;
     } catch (error) {
       set({ error: String(error), isLoading: false });
+      return null;
     }
   },
 
@@ -4,8 +4,11 @@ import { invoke } from '@tauri-apps/api/core';
 export interface AppPreferences {
   auto_close_progress_window: boolean;
   auto_close_delay_ms: number;
+  kdf_strength: KdfStrength; // Argon2id cost for new encrypted backups
 }
 
+export type KdfStrength = 'standard' | 'high' | 'maximum';
+
 interface PreferencesStore {
   // State
   preferences: AppPreferences;
@@ -21,6 +24,7 @@ interface PreferencesStore {
 const defaultPreferences: AppPreferences = {
   auto_close_progress_window: true,
   auto_close_delay_ms: 3000,
+  kdf_strength: 'standard',
 };
 
 export const usePreferencesStore = create<PreferencesStore>((set, get) => ({
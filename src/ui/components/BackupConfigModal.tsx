@@ -1,5 +1,6 @@
-import { useState } from 'react';
-import { BackupConfig, ScheduleConfig, useBackupStore } from '../../store/useBackupStore';
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { BackupConfig, CatchUpPolicy, RetryPolicy, ScheduleConfig, useBackupStore } from '../../store/useBackupStore';
 
 interface BackupConfigModalProps {
   config: BackupConfig;
@@ -39,9 +40,23 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
   const [backupMode, setBackupMode] = useState<'copy' | 'compressed' | 'encrypted'>(
     config.mode || 'compressed'
   );
+  // Only asked when the config becomes encrypted: sets up its recovery key
+  const needsRecoveryKey = !config.recovery;
+  const [encryptionPassword, setEncryptionPassword] = useState<string>('');
+  const [confirmPassword, setConfirmPassword] = useState<string>('');
+  const [passwordError, setPasswordError] = useState<string | null>(null);
   const [schedulePreset, setSchedulePreset] = useState<string>(
     config.schedule?.preset || 'none'
   );
+  const [inAppSchedule, setInAppSchedule] = useState<boolean>(config.schedule?.in_app ?? false);
+  const [catchUp, setCatchUp] = useState<CatchUpPolicy>(config.schedule?.catch_up ?? { policy: 'skip' });
+  const [retry, setRetry] = useState<RetryPolicy>(config.schedule?.retry ?? {
+    max_attempts: 3,
+    initial_delay_secs: 60,
+    backoff_factor: 2,
+    max_delay_secs: 900,
+    retry_on: ['destination_unavailable', 'source_unavailable', 'secret_unavailable', 'io'],
+  });
 
   const handleSelectDestination = async () => {
     const folder = await selectFolder();
@@ -56,30 +71,46 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
   const [weekday, setWeekday] = useState<number>(parsedCron.weekday); // 0 = Sunday
   const [monthday, setMonthday] = useState<number>(parsedCron.day); // 1-31
 
-  const handleSave = () => {
-    let cronExpression = '';
-
-    // Generate cron expression internally based on simple selections
+  // Generate cron expression internally based on simple selections
+  const buildCronExpression = (): string => {
     switch (schedulePreset) {
-      case 'none':
-        cronExpression = '';
-        break;
       case 'hourly':
-        cronExpression = '0 * * * *'; // Every hour at minute 0
-        break;
+        return '0 * * * *'; // Every hour at minute 0
       case 'daily':
-        cronExpression = `${minute} ${hour} * * *`; // Daily at specified time
-        break;
+        return `${minute} ${hour} * * *`; // Daily at specified time
       case 'weekly':
-        cronExpression = `${minute} ${hour} * * ${weekday}`; // Weekly on specified day/time
-        break;
+        return `${minute} ${hour} * * ${weekday}`; // Weekly on specified day/time
       case 'monthly':
-        cronExpression = `${minute} ${hour} ${monthday} * *`; // Monthly on specified day/time
-        break;
+        return `${minute} ${hour} ${monthday} * *`; // Monthly on specified day/time
       default:
-        cronExpression = '';
+        return '';
+    }
+  };
+
+  // Upcoming runs as computed by the backend schedule engine (local time, DST-aware)
+  const [upcomingRuns, setUpcomingRuns] = useState<number[]>([]);
+  useEffect(() => {
+    const cronExpression = buildCronExpression();
+    if (!cronExpression) {
+      setUpcomingRuns([]);
+      return;
+    }
+    invoke<number[]>('preview_schedule', { cronExpression, count: 3 })
+      .then(setUpcomingRuns)
+      .catch((error) => {
+        console.error('Failed to preview schedule:', error);
+        setUpcomingRuns([]);
+      });
+  }, [schedulePreset, hour, minute, weekday, monthday]);
+
+  const handleSave = () => {
+    if (backupMode === 'encrypted' && needsRecoveryKey && encryptionPassword !== confirmPassword) {
+      setPasswordError('Passwords do not match');
+      return;
     }
 
+    const cronExpression = buildCronExpression();
+
     console.log('💾 SAVING CONFIGURATION');
     console.log('   Preset:', schedulePreset);
     console.log('   Hour:', hour, '| Minute:', minute);
@@ -94,6 +125,9 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
             preset: schedulePreset as any,
             next_run: null,
             enabled: true,
+            in_app: inAppSchedule,
+            catch_up: catchUp,
+            retry,
           };
 
     const updatedConfig: BackupConfig = {
@@ -102,6 +136,8 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
       destination_path: destinationPath.trim() || config.destination_path,
       backup_type: backupType,
       mode: backupMode,
+      encryption_password:
+        backupMode === 'encrypted' && needsRecoveryKey && encryptionPassword ? encryptionPassword : undefined,
       schedule: scheduleConfig,
       updated_at: Date.now(),
     };
@@ -260,6 +296,29 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
                   </div>
                 </div>
               </div>
+              {needsRecoveryKey && (
+                <div className="mt-3 space-y-2">
+                  <div className="text-xs text-gray-400">
+                    Enter the password you will use for this backup to create its recovery key.
+                    The key opens every archive if the password is forgotten and is shown only once.
+                  </div>
+                  <input
+                    type="password"
+                    value={encryptionPassword}
+                    onChange={(e) => { setEncryptionPassword(e.target.value); setPasswordError(null); }}
+                    placeholder="Password"
+                    className="w-full px-3 py-2 bg-gray-800 border border-gray-700 rounded text-sm text-gray-300 focus:border-emerald-600 focus:outline-none"
+                  />
+                  <input
+                    type="password"
+                    value={confirmPassword}
+                    onChange={(e) => { setConfirmPassword(e.target.value); setPasswordError(null); }}
+                    placeholder="Confirm password"
+                    className="w-full px-3 py-2 bg-gray-800 border border-gray-700 rounded text-sm text-gray-300 focus:border-emerald-600 focus:outline-none"
+                  />
+                  {passwordError && <div className="text-xs text-red-400">{passwordError}</div>}
+                </div>
+              )}
             </div>
           )}
 
@@ -288,6 +347,83 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
             </select>
           </div>
 
+          {schedulePreset !== 'none' && (
+            <label className="flex items-start gap-2 text-sm text-gray-300 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={inAppSchedule}
+                onChange={(e) => setInAppSchedule(e.target.checked)}
+                className="mt-0.5"
+              />
+              <span>
+                Use in-app scheduler
+                <span className="block text-xs text-gray-400">
+                  Runs only while InLocker is open, instead of the system scheduler (launchd/systemd)
+                </span>
+              </span>
+            </label>
+          )}
+
+          {/* Missed-run policy */}
+          {schedulePreset !== 'none' && (
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-2">
+                Missed Runs
+              </label>
+              <select
+                value={catchUp.policy}
+                onChange={(e) => {
+                  const policy = e.target.value;
+                  setCatchUp(
+                    policy === 'if_older_than'
+                      ? { policy: 'if_older_than', max_age_secs: 24 * 3600 }
+                      : { policy: policy as 'skip' | 'run_once' }
+                  );
+                }}
+                className="w-full px-3 py-2 bg-gray-800 border border-gray-700 rounded text-sm text-gray-300 focus:border-emerald-600 focus:outline-none transition-colors"
+              >
+                <option value="skip">Skip (wait for the next scheduled time)</option>
+                <option value="run_once">Run once when possible</option>
+                <option value="if_older_than">Run once if the last backup is too old</option>
+              </select>
+              {catchUp.policy === 'if_older_than' && (
+                <div className="mt-2 flex items-center gap-2 text-xs text-gray-400">
+                  <span>Older than</span>
+                  <input
+                    type="number"
+                    min="1"
+                    value={Math.round(catchUp.max_age_secs / 3600)}
+                    onChange={(e) => setCatchUp({ policy: 'if_older_than', max_age_secs: Math.max(1, parseInt(e.target.value) || 1) * 3600 })}
+                    className="w-20 px-2 py-1 bg-gray-800 border border-gray-700 rounded text-sm text-gray-300 focus:border-emerald-600 focus:outline-none"
+                  />
+                  <span>hours</span>
+                </div>
+              )}
+            </div>
+          )}
+
+          {/* Retry policy */}
+          {schedulePreset !== 'none' && (
+            <div>
+              <label className="block text-sm font-medium text-gray-300 mb-2">
+                Retry Failed Runs
+              </label>
+              <select
+                value={retry.max_attempts}
+                onChange={(e) => setRetry({ ...retry, max_attempts: parseInt(e.target.value) })}
+                className="w-full px-3 py-2 bg-gray-800 border border-gray-700 rounded text-sm text-gray-300 focus:border-emerald-600 focus:outline-none transition-colors"
+              >
+                <option value={1}>Don't retry</option>
+                <option value={2}>Retry once</option>
+                <option value={3}>Retry up to 2 times</option>
+                <option value={5}>Retry up to 4 times</option>
+              </select>
+              <p className="text-xs text-gray-500 mt-1">
+                Only for temporary problems (destination not mounted, missing password, timeouts, busy or disconnected drives); waits {Math.round(retry.initial_delay_secs / 60)} min, doubling each time
+              </p>
+            </div>
+          )}
+
           {/* Time Picker for Daily/Weekly/Monthly */}
           {(schedulePreset === 'daily' || schedulePreset === 'weekly' || schedulePreset === 'monthly') && (
             <div>
@@ -363,29 +499,9 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
 
           {/* Schedule Summary */}
           {schedulePreset !== 'none' && (() => {
-            const now = new Date();
-            const currentHour = now.getHours();
-            const currentMinute = now.getMinutes();
-
-            let nextRunMessage = '';
-            let isTimePassed = false;
-
-            if (schedulePreset === 'hourly') {
-              nextRunMessage = 'Next run: Top of the next hour';
-            } else if (schedulePreset === 'daily') {
-              // Check if time has passed today
-              if (currentHour > hour || (currentHour === hour && currentMinute >= minute)) {
-                isTimePassed = true;
-                nextRunMessage = `Next run: Tomorrow at ${hour.toString().padStart(2, '0')}:${minute.toString().padStart(2, '0')}`;
-              } else {
-                const minutesUntil = (hour - currentHour) * 60 + (minute - currentMinute);
-                const hoursUntil = Math.floor(minutesUntil / 60);
-                const minsUntil = minutesUntil % 60;
-                nextRunMessage = `Next run: Today at ${hour.toString().padStart(2, '0')}:${minute.toString().padStart(2, '0')} (in ${hoursUntil}h ${minsUntil}m)`;
-              }
-            } else if (schedulePreset === 'weekly' || schedulePreset === 'monthly') {
-              nextRunMessage = `Next run: Check schedule logs`;
-            }
+            // Warn when the first run isn't today (e.g. the time already passed)
+            const isTimePassed = schedulePreset === 'daily' && upcomingRuns.length > 0 &&
+              new Date(upcomingRuns[0] * 1000).toDateString() !== new Date().toDateString();
 
             return (
               <div className={`${isTimePassed ? 'bg-yellow-900/20 border-yellow-800' : 'bg-blue-900/20 border-blue-800'} border rounded p-3`}>
@@ -400,7 +516,18 @@ export function BackupConfigModal({ config, onSave, onClose }: BackupConfigModal
                       {schedulePreset === 'weekly' && `Runs every ${['Sunday', 'Monday', 'Tuesday', 'Wednesday', 'Thursday', 'Friday', 'Saturday'][weekday]} at ${hour.toString().padStart(2, '0')}:${minute.toString().padStart(2, '0')}`}
                       {schedulePreset === 'monthly' && `Runs on day ${monthday} of each month at ${hour.toString().padStart(2, '0')}:${minute.toString().padStart(2, '0')}`}
                     </div>
-                    <div className="mt-1 text-xs opacity-90">{nextRunMessage}</div>
+                    {upcomingRuns.length > 0 ? (
+                      <div className="mt-1 text-xs opacity-90">
+                        <div>Upcoming runs:</div>
+                        <ul className="ml-3">
+                          {upcomingRuns.map((run) => (
+                            <li key={run}>{new Date(run * 1000).toLocaleString()}</li>
+                          ))}
+                        </ul>
+                      </div>
+                    ) : (
+                      <div className="mt-1 text-xs opacity-90">Next run: never (this date does not occur)</div>
+                    )}
                     {isTimePassed && (
                       <div className="mt-2 text-xs opacity-75">
                         Note: Time has passed today. Use "Test Now" button for immediate execution.
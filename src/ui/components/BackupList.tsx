@@ -25,6 +25,7 @@ export function BackupList() {
   const [expandedCards, setExpandedCards] = useState<Set<string>>(new Set());
   const [passwordPrompt, setPasswordPrompt] = useState<{ configId: string; show: boolean }>({ configId: '', show: false });
   const [passwordInput, setPasswordInput] = useState('');
+  const [recoveryKey, setRecoveryKey] = useState<string | null>(null);
   const [backupFileExists, setBackupFileExists] = useState<Map<string, boolean>>(new Map());
   const [testingSchedule, setTestingSchedule] = useState<Set<string>>(new Set());
 
@@ -134,7 +135,10 @@ export function BackupList() {
   };
 
   const handleSaveConfig = async (updatedConfig: BackupConfig) => {
-    await saveConfig(updatedConfig);
+    const newRecoveryKey = await saveConfig(updatedConfig);
+    if (newRecoveryKey) {
+      setRecoveryKey(newRecoveryKey);
+    }
 
     // Register or unregister schedule based on config
     try {
@@ -385,6 +389,33 @@ export function BackupList() {
         />
       )}
 
+      {recoveryKey && (
+        <div className="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
+          <div className="bg-gray-800 rounded-lg p-6 w-[28rem] border border-gray-700">
+            <h3 className="text-lg font-semibold text-white mb-2">Your Recovery Key</h3>
+            <p className="text-sm text-gray-400 mb-4">
+              Write it down or store it offline. It opens this backup's archives if the password is
+              forgotten, and it will not be shown again.
+            </p>
+            <input
+              type="text"
+              readOnly
+              value={recoveryKey}
+              onFocus={(e) => e.target.select()}
+              className="w-full px-3 py-2 bg-gray-900 border border-gray-700 rounded text-emerald-300 font-mono text-sm mb-4"
+            />
+            <div className="flex justify-end">
+              <button
+                onClick={() => setRecoveryKey(null)}
+                className="px-4 py-2 bg-emerald-600 hover:bg-emerald-500 rounded text-white transition-colors"
+              >
+                I saved it
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+
       {passwordPrompt.show && (
         <div className="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
           <div className="bg-gray-800 rounded-lg p-6 w-96 border border-gray-700">
@@ -583,6 +614,16 @@ export function BackupList() {
                             </span>
                           </div>
                         )}
+
+                        {/* Row 3, Col 1 */}
+                        {config.schedule?.enabled && config.schedule.next_run && (
+                          <div className="flex items-baseline gap-2">
+                            <span className="text-gray-400 text-xs whitespace-nowrap">Next:</span>
+                            <span className="text-gray-300 text-xs whitespace-nowrap">
+                              {new Date(config.schedule.next_run * 1000).toLocaleString()}
+                            </span>
+                          </div>
+                        )}
                       </div>
                     </div>
                   </>